@@ -0,0 +1,32 @@
+// The target of `--self-test`: it crashes on inputs starting with "FUZZ".
+// Each byte is checked in its own branch, so that coverage alone guides the fuzzer to the bug.
+#include <unistd.h>
+
+__attribute__((noinline)) static void check(const unsigned char *buf, ssize_t len) {
+    if (len < 4) {
+        return;
+    }
+    if (buf[0] == 'F') {
+        if (buf[1] == 'U') {
+            if (buf[2] == 'Z') {
+                if (buf[3] == 'Z') {
+                    *(volatile int *)0 = 0;
+                }
+            }
+        }
+    }
+}
+
+// The harness exit (`--harness-exit selftest_end`): a run ends when it's called, before the process could exit,
+// so every input is run from the snapshot at `main` without the target ever exiting
+__attribute__((noinline)) void selftest_end(void) {
+    __asm__ volatile("");
+}
+
+int main(void) {
+    unsigned char buf[64];
+    ssize_t len = read(0, buf, sizeof(buf));
+    check(buf, len);
+    selftest_end();
+    _exit(0);
+}
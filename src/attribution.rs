@@ -0,0 +1,126 @@
+use std::{fs, ops::Range, path::Path};
+
+use libafl::Error;
+use serde::Serialize;
+
+/// Width of the longest bar of the heatmap
+const BAR_WIDTH: usize = 40;
+
+/// How the coverage of an input changed when one of its regions was mutated, see `--byte-attribution`
+#[derive(Debug, Serialize)]
+pub struct RegionImpact {
+    pub offset: usize,
+    pub len: usize,
+    /// Stable map entries that changed with every bit of the region flipped
+    pub flip: usize,
+    /// Stable map entries that changed with the region zeroed
+    pub zero: usize,
+}
+
+impl RegionImpact {
+    /// The importance of the region: the most entries either mutation changed
+    pub fn importance(&self) -> usize {
+        self.flip.max(self.zero)
+    }
+}
+
+/// The per-region importance map of an input, i.e. which of its bytes drive the target's control flow
+#[derive(Debug, Serialize)]
+pub struct AttributionReport {
+    pub input_len: usize,
+    /// Entries of the map covered by the input
+    pub covered: usize,
+    /// Entries that differed between two runs of the unmodified input, ignored in the comparisons
+    pub flaky: usize,
+    pub regions: Vec<RegionImpact>,
+    /// Not serialized, only used to compare the mutated runs
+    #[serde(skip)]
+    baseline: Vec<u8>,
+    #[serde(skip)]
+    unstable: Vec<bool>,
+}
+
+impl AttributionReport {
+    /// Start a report from two maps of the unmodified input of `input_len` bytes
+    pub fn new(input_len: usize, first: Vec<u8>, second: &[u8]) -> Self {
+        let unstable = first
+            .iter()
+            .zip(second)
+            .map(|(a, b)| a != b)
+            .collect::<Vec<_>>();
+        Self {
+            input_len,
+            covered: first.iter().filter(|v| **v != 0).count(),
+            flaky: unstable.iter().filter(|u| **u).count(),
+            regions: Vec::new(),
+            baseline: first,
+            unstable,
+        }
+    }
+
+    /// The regions mutated for an input of `input_len` bytes, single bytes unless that makes more than
+    /// `max_regions` of them, in which case the input is cut in `max_regions` chunks of equal size
+    pub fn regions(input_len: usize, max_regions: usize) -> Vec<Range<usize>> {
+        let chunk = input_len.div_ceil(max_regions).max(1);
+        (0..input_len)
+            .step_by(chunk)
+            .map(|start| start..(start + chunk).min(input_len))
+            .collect()
+    }
+
+    /// The stable entries of `map` that differ from the unmodified input's
+    pub fn changed(&self, map: &[u8]) -> usize {
+        self.baseline
+            .iter()
+            .zip(map)
+            .zip(&self.unstable)
+            .filter(|((a, b), unstable)| !**unstable && a != b)
+            .count()
+    }
+
+    pub fn add(&mut self, region: Range<usize>, flip: usize, zero: usize) {
+        self.regions.push(RegionImpact {
+            offset: region.start,
+            len: region.len(),
+            flip,
+            zero,
+        });
+    }
+
+    /// Print the heatmap, one line per region with a bar as long as its importance
+    pub fn print(&self) {
+        println!(
+            "Byte attribution of {} bytes in {} regions ({} covered entries, {} flaky ignored)",
+            self.input_len,
+            self.regions.len(),
+            self.covered,
+            self.flaky
+        );
+        let max = self.regions.iter().map(RegionImpact::importance).max().unwrap_or(0);
+        for region in &self.regions {
+            let bar = if max == 0 {
+                0
+            } else {
+                (region.importance() * BAR_WIDTH).div_ceil(max)
+            };
+            println!(
+                "{:#08x} +{:<4} flip {:>5} zero {:>5} {}",
+                region.offset,
+                region.len,
+                region.flip,
+                region.zero,
+                "#".repeat(bar)
+            );
+        }
+        if max == 0 {
+            println!("No region changes the coverage, the target may not read the input");
+        }
+    }
+
+    pub fn write_json(&self, path: &Path) -> Result<(), Error> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| Error::serialize(format!("Failed to serialize the byte attribution: {e}")))?;
+        fs::write(path, json)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,138 @@
+use core::time::Duration;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use libafl::Error;
+use libafl_bolts::current_time;
+
+/// Periodically copies the queue directory to `<dir>/<timestamp>/`, keeping only the `keep` latest copies,
+/// and/or flushes it to a mirror directory (see `--corpus-tmpfs`), each at its own interval.
+/// This guards the corpus against disk or process failures between restarts.
+#[derive(Debug)]
+pub struct Checkpointer {
+    queue_dir: PathBuf,
+    // The directory of the timestamped copies, how many to keep and how often to take one
+    snapshots: Option<(PathBuf, usize, Duration)>,
+    last_snapshot: Duration,
+    // The mirror directory and how often to flush to it
+    mirror: Option<(PathBuf, Duration)>,
+    last_flush: Duration,
+}
+
+impl Checkpointer {
+    /// Checkpoints nothing until given [`Checkpointer::snapshots`] or a [`Checkpointer::mirror`]
+    pub fn new(queue_dir: PathBuf) -> Self {
+        let now = current_time();
+        Self {
+            queue_dir,
+            snapshots: None,
+            last_snapshot: now,
+            mirror: None,
+            last_flush: now,
+        }
+    }
+
+    /// Copy the queue to `<dir>/<timestamp>/` every `interval`, keeping the `keep` latest copies
+    pub fn snapshots(mut self, dir: PathBuf, keep: usize, interval: Duration) -> Self {
+        self.snapshots = Some((dir, keep, interval));
+        self
+    }
+
+    /// Make `dir` the same as the queue every `interval`, e.g. the persistent copy of a queue on a tmpfs
+    pub fn mirror(mut self, dir: PathBuf, interval: Duration) -> Self {
+        self.mirror = Some((dir, interval));
+        self
+    }
+
+    /// Flush the mirror and take a snapshot, each if its interval elapsed since the last one
+    pub fn maybe_checkpoint(&mut self) -> Result<(), Error> {
+        let now = current_time();
+        if let Some((_, interval)) = &self.mirror {
+            if now.saturating_sub(self.last_flush) >= *interval {
+                self.last_flush = now;
+                self.flush()?;
+            }
+        }
+        if let Some((dir, keep, interval)) = &self.snapshots {
+            if now.saturating_sub(self.last_snapshot) >= *interval {
+                self.last_snapshot = now;
+                let dest = dir.join(format!("{:010}", now.as_secs()));
+                let copied = copy_files(&self.queue_dir, &dest)?;
+                log::info!("Checkpointed {copied} corpus files to {dest:?}");
+                prune(dir, *keep)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Bring the mirror, if any, up to date with the queue, e.g. before exiting
+    pub fn flush(&self) -> Result<(), Error> {
+        if let Some((mirror, _)) = &self.mirror {
+            let (copied, removed) = sync_files(&self.queue_dir, mirror)?;
+            log::info!("Flushed the corpus to {mirror:?}: {copied} new files, {removed} removed");
+        }
+        Ok(())
+    }
+}
+
+fn prune(dir: &Path, keep: usize) -> Result<(), Error> {
+    let mut checkpoints = fs::read_dir(dir)?
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .collect::<Vec<PathBuf>>();
+    // Names are fixed-width timestamps, so the oldest ones come first
+    checkpoints.sort();
+
+    let excess = checkpoints.len().saturating_sub(keep);
+    for old in &checkpoints[..excess] {
+        log::info!("Pruning old checkpoint {old:?}");
+        fs::remove_dir_all(old)?;
+    }
+    Ok(())
+}
+
+/// The regular files of `dir`, without the corpus lock files
+fn corpus_files(dir: &Path) -> Result<Vec<PathBuf>, Error> {
+    Ok(fs::read_dir(dir)?
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file() && !path.extension().is_some_and(|ext| ext == "lafl_lock"))
+        .collect())
+}
+
+/// Copy the regular files of `src` into `dest`, skipping the corpus lock files
+fn copy_files(src: &Path, dest: &Path) -> Result<usize, Error> {
+    fs::create_dir_all(dest)?;
+    let files = corpus_files(src)?;
+    for path in &files {
+        fs::copy(path, dest.join(path.file_name().unwrap()))?;
+    }
+    Ok(files.len())
+}
+
+/// Make the corpus files of `dest` the same as the ones of `src`: copy the new ones (corpus files are never
+/// modified, a file of the same name and size is taken for the same) and remove the ones gone from `src`
+fn sync_files(src: &Path, dest: &Path) -> Result<(usize, usize), Error> {
+    fs::create_dir_all(dest)?;
+    let mut copied = 0;
+    for path in corpus_files(src)? {
+        let target = dest.join(path.file_name().unwrap());
+        let same = fs::metadata(&target)
+            .is_ok_and(|meta| meta.len() == fs::metadata(&path).map_or(u64::MAX, |meta| meta.len()));
+        if !same {
+            fs::copy(&path, &target)?;
+            copied += 1;
+        }
+    }
+    let mut removed = 0;
+    for path in corpus_files(dest)? {
+        if !src.join(path.file_name().unwrap()).exists() {
+            fs::remove_file(&path)?;
+            removed += 1;
+        }
+    }
+    Ok((copied, removed))
+}
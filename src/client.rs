@@ -1,4 +1,4 @@
-use std::env;
+use std::{env, fs, path::Path};
 
 use libafl::{
     corpus::{InMemoryOnDiskCorpus, OnDiskCorpus},
@@ -39,11 +39,41 @@ impl Client<'_> {
             .next()
             .ok_or_else(|| Error::empty_optional("Failed to read program name"))?;
 
-        let mut args = self.options.args.clone();
-        args.insert(0, program);
+        // qemu parameters must come before the target binary, everything after it is the guest argv
+        let mut args = vec![program];
+        if let Some(cpu) = &self.options.cpu {
+            args.push("-cpu".to_string());
+            args.push(cpu.clone());
+        }
+        if let Some(size) = self.options.guest_stack_size {
+            args.push("-s".to_string());
+            args.push(size.to_string());
+        }
+        args.extend(self.options.qemu_args.iter().cloned());
+        args.extend(self.options.args.iter().cloned());
+        if let Some(argv_file) = &self.options.argv_file {
+            args.extend(Self::argv_from_file(argv_file)?);
+        }
         Ok(args)
     }
 
+    /// Read the guest arguments of `--argv-file`, each non-comment line is split like a shell would
+    fn argv_from_file(path: &Path) -> Result<Vec<String>, Error> {
+        let content = fs::read_to_string(path)?;
+        let mut argv = Vec::new();
+        for (i, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let words = shell_words::split(line).map_err(|e| {
+                Error::illegal_argument(format!("{}:{}: {e}", path.display(), i + 1))
+            })?;
+            argv.extend(words);
+        }
+        Ok(argv)
+    }
+
     #[expect(clippy::unused_self)] // Api should look the same as args above
     pub fn env(&self) -> Vec<(String, String)> {
         env::vars()
@@ -59,7 +89,19 @@ impl Client<'_> {
         client_description: ClientDescription,
     ) -> Result<(), Error> {
         let core_id = client_description.core_id();
+        if self.options.no_affinity {
+            log::info!("Client {} started for {:?}, not pinned", client_description.id(), core_id);
+        } else {
+            log::info!("Client {} pinned to {:?}", client_description.id(), core_id);
+        }
+
         let mut args = self.args()?;
+        if self.options.isolate_tmp {
+            // qemu sets it in the guest environment, the variables of this process are shared by the clients
+            let tmp_dir = self.options.tmp_dir(core_id);
+            fs::create_dir_all(&tmp_dir)?;
+            args.splice(1..1, ["-E".to_string(), format!("TMPDIR={}", tmp_dir.display())]);
+        }
         Harness::edit_args(&mut args);
         log::debug!("ARGS: {:#?}", args);
 
@@ -114,7 +156,7 @@ impl Client<'_> {
             .mgr(mgr)
             .client_description(client_description);
 
-        if self.options.rerun_input.is_some() && self.options.drcov.is_some() {
+        if self.options.replays_input() && self.options.drcov.is_some() {
             // Special code path for re-running inputs with DrCov.
             // TODO: Add ASan support, injection support
             let drcov = self.options.drcov.as_ref().unwrap();
@@ -0,0 +1,247 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::File,
+    io::{BufWriter, Write},
+    ops::Range,
+    path::Path,
+};
+
+use addr2line::Loader;
+use capstone::{InsnGroupId, InsnGroupType};
+use libafl::{feedbacks::MapFeedbackMetadata, Error, HasMetadata, HasNamedMetadata};
+use libafl_qemu::{capstone, modules::edges::QemuEdgesMapMetadata, GuestAddr, GuestUsize, Qemu};
+
+/// Write the merged coverage of a campaign, i.e. the non-zero entries of the map feedback
+/// history named `map_name`, one per line:
+///
+/// ```text
+/// <index> <src> <dst>
+/// ```
+///
+/// `src` and `dst` are the guest addresses of the edge, as recorded by the edge coverage module.
+/// They are replaced by `-` if the edge is unknown, only the raw index is meaningful then.
+///
+/// Returns the number of covered entries.
+pub fn dump_coverage<S>(state: &S, map_name: &str, path: &Path) -> Result<usize, Error>
+where
+    S: HasMetadata + HasNamedMetadata,
+{
+    let history = &state
+        .named_metadata::<MapFeedbackMetadata<u8>>(map_name)?
+        .history_map;
+
+    let edges = state
+        .metadata_map()
+        .get::<QemuEdgesMapMetadata>()
+        .map(|meta| {
+            meta.map
+                .iter()
+                .map(|(edge, id)| (*id as usize, *edge))
+                .collect::<HashMap<usize, (GuestAddr, GuestAddr)>>()
+        })
+        .unwrap_or_default();
+
+    let mut writer = BufWriter::new(File::create(path)?);
+    let mut covered = 0;
+    for (idx, _) in history.iter().enumerate().filter(|(_, v)| **v != 0) {
+        match edges.get(&idx) {
+            Some((src, dst)) => writeln!(writer, "{idx} {src:#x} {dst:#x}")?,
+            None => writeln!(writer, "{idx} - -")?,
+        }
+        covered += 1;
+    }
+    writer.flush()?;
+
+    Ok(covered)
+}
+
+/// Write the edges hit in a single execution's `map`, one per line as `<src> <dst>`,
+/// the offsets of the edge's guest addresses from `load_addr`. Edges the edge module didn't record are skipped.
+pub fn dump_edges<S>(state: &S, map: &[u8], load_addr: GuestAddr, path: &Path) -> Result<usize, Error>
+where
+    S: HasMetadata,
+{
+    let Some(meta) = state.metadata_map().get::<QemuEdgesMapMetadata>() else {
+        return Err(Error::key_not_found("No edges metadata in the state"));
+    };
+
+    let mut writer = BufWriter::new(File::create(path)?);
+    let mut written = 0;
+    for ((src, dst), id) in &meta.map {
+        if map.get(*id as usize).is_some_and(|v| *v != 0) {
+            writeln!(
+                writer,
+                "{:#x} {:#x}",
+                src.wrapping_sub(load_addr),
+                dst.wrapping_sub(load_addr)
+            )?;
+            written += 1;
+        }
+    }
+    writer.flush()?;
+
+    Ok(written)
+}
+
+/// Number of non-zero entries of the map feedback history named `map_name`, i.e. the edges found so far
+pub fn covered_edges<S>(state: &S, map_name: &str) -> Result<usize, Error>
+where
+    S: HasNamedMetadata,
+{
+    Ok(state
+        .named_metadata::<MapFeedbackMetadata<u8>>(map_name)?
+        .history_map
+        .iter()
+        .filter(|v| **v != 0)
+        .count())
+}
+
+/// [`covered_edges`] in every `slice_len` entries of the map feedback history named `map_name`
+pub fn covered_edges_per_slice<S>(state: &S, map_name: &str, slice_len: usize) -> Result<Vec<usize>, Error>
+where
+    S: HasNamedMetadata,
+{
+    Ok(state
+        .named_metadata::<MapFeedbackMetadata<u8>>(map_name)?
+        .history_map
+        .chunks(slice_len)
+        .map(|slice| slice.iter().filter(|v| **v != 0).count())
+        .collect())
+}
+
+/// Estimate the number of basic blocks in the guest code `ranges` by a linear disassembly,
+/// counting one block per range plus one after every jump, call, return or interrupt.
+///
+/// It's only a rough denominator for the coverage percentage: data mixed with the code,
+/// blocks only reached through indirect branches and blocks split by jump targets are not accounted for.
+pub fn count_blocks(qemu: Qemu, ranges: &[Range<GuestAddr>]) -> Result<usize, Error> {
+    let cs = capstone()
+        .detail(true)
+        .build()
+        .map_err(|e| Error::unknown(format!("Failed to create capstone: {e}")))?;
+    let terminators = [
+        InsnGroupType::CS_GRP_JUMP,
+        InsnGroupType::CS_GRP_CALL,
+        InsnGroupType::CS_GRP_RET,
+        InsnGroupType::CS_GRP_INT,
+    ]
+    .map(|group| InsnGroupId(group as u8));
+
+    let mut blocks = 0;
+    for range in ranges {
+        let mut code = vec![0; (range.end - range.start) as usize];
+        qemu.read_mem(range.start, &mut code)
+            .map_err(|e| Error::unknown(format!("Failed to read code @{:#x}: {e:?}", range.start)))?;
+        let insns = cs
+            .disasm_all(&code, range.start.into())
+            .map_err(|e| Error::unknown(format!("Failed to disassemble @{:#x}: {e}", range.start)))?;
+
+        blocks += 1;
+        for insn in insns.iter() {
+            let detail = cs
+                .insn_detail(insn)
+                .map_err(|e| Error::unknown(format!("Failed to get instruction detail: {e}")))?;
+            if detail.groups().iter().any(|group| terminators.contains(group)) {
+                blocks += 1;
+            }
+        }
+    }
+
+    Ok(blocks)
+}
+
+/// Write the guest `blocks` (as `(start, length, executions)`) of the target's `text` as an LCOV tracefile,
+/// the lines of every instruction being found in the DWARF line info of `binary`, loaded `bias` bytes
+/// above its link addresses. A line executed by several instructions gets the most executed one's count,
+/// and the lines of the line table in `text` that no executed instruction maps to are written with a count of 0.
+///
+/// Without debug info the lines are unknown, so it writes an address-only report instead, one block per line:
+///
+/// ```text
+/// <start offset> <length> <executions>
+/// ```
+///
+/// Returns the number of lines (or blocks) written and whether the line info was used.
+pub fn write_lcov(
+    qemu: Qemu,
+    binary: &Path,
+    bias: GuestAddr,
+    text: &Range<GuestAddr>,
+    blocks: &[(GuestAddr, GuestUsize, u64)],
+    path: &Path,
+) -> Result<(usize, bool), Error> {
+    let mut blocks = blocks
+        .iter()
+        .filter(|(start, _, _)| text.contains(start))
+        .copied()
+        .collect::<Vec<_>>();
+    blocks.sort_unstable();
+
+    let cs = capstone()
+        .detail(false)
+        .build()
+        .map_err(|e| Error::unknown(format!("Failed to create capstone: {e}")))?;
+    let loader = Loader::new(binary)
+        .inspect_err(|e| log::warn!("Failed to load the debug info of {}: {e}", binary.display()))
+        .ok();
+
+    // Executions of every line, by file
+    let mut files: BTreeMap<String, BTreeMap<u32, u64>> = BTreeMap::new();
+    if let Some(loader) = &loader {
+        // Every line of the text first, the executed ones get their count below
+        let link_text = u64::from(text.start - bias)..u64::from(text.end - bias);
+        let rows = loader
+            .find_location_range(link_text.start, link_text.end)
+            .map_err(|e| Error::unknown(format!("Failed to read the line table of {}: {e}", binary.display())))?;
+        for (_, _, location) in rows {
+            if let (Some(file), Some(line)) = (location.file, location.line) {
+                files.entry(file.to_string()).or_default().entry(line).or_default();
+            }
+        }
+
+        for (start, len, hits) in &blocks {
+            let mut code = vec![0; *len as usize];
+            qemu.read_mem(*start, &mut code)
+                .map_err(|e| Error::unknown(format!("Failed to read code @{start:#x}: {e:?}")))?;
+            let insns = cs
+                .disasm_all(&code, (*start).into())
+                .map_err(|e| Error::unknown(format!("Failed to disassemble @{start:#x}: {e}")))?;
+            for insn in insns.iter() {
+                let Ok(Some(location)) = loader.find_location(insn.address() - u64::from(bias)) else {
+                    continue;
+                };
+                let (Some(file), Some(line)) = (location.file, location.line) else {
+                    continue;
+                };
+                let count = files.entry(file.to_string()).or_default().entry(line).or_default();
+                *count = (*count).max(*hits);
+            }
+        }
+    }
+
+    let mut writer = BufWriter::new(File::create(path)?);
+    if files.is_empty() {
+        log::warn!("No line info for the covered blocks, writing the block addresses instead");
+        for (start, len, hits) in &blocks {
+            writeln!(writer, "{:#x} {len} {hits}", start.wrapping_sub(bias))?;
+        }
+        writer.flush()?;
+        return Ok((blocks.len(), false));
+    }
+
+    let mut written = 0;
+    writeln!(writer, "TN:")?;
+    for (file, lines) in &files {
+        writeln!(writer, "SF:{file}")?;
+        for (line, hits) in lines {
+            writeln!(writer, "DA:{line},{hits}")?;
+        }
+        writeln!(writer, "LH:{}", lines.values().filter(|hits| **hits != 0).count())?;
+        writeln!(writer, "LF:{}", lines.len())?;
+        writeln!(writer, "end_of_record")?;
+        written += lines.len();
+    }
+    writer.flush()?;
+
+    Ok((written, true))
+}
@@ -0,0 +1,130 @@
+use std::{
+    env,
+    fs::{self, Permissions},
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
+};
+
+use libafl::Error;
+use libafl_qemu::GuestAddr;
+
+use crate::{
+    options::FuzzerOptions,
+    triage::{child_args, Triage},
+};
+
+/// Options that only make sense for the debug config driver and must not be forwarded to the replay
+const DEBUG_ONLY_ARGS: [(&str, bool); 2] = [("--emit-debug-config", true), ("--tui", false)];
+
+/// Port of qemu's gdbstub in the replay
+const GDB_PORT: u16 = 1234;
+
+/// Writes what it takes to debug a crash next to it:
+///
+/// - `<crash>.gdbstub.sh` replays the crash with `-r`, qemu waiting for a debugger on [`GDB_PORT`]
+/// - `<crash>.gdb` attaches gdb to it, with a breakpoint at the faulting PC
+/// - `<crash>.launch.json` does the same from VSCode (C/C++ extension)
+///
+/// The faulting PC comes from a first replay, like the [`Triage`] does.
+pub struct DebugConfig<'a> {
+    options: &'a FuzzerOptions,
+}
+
+impl<'a> DebugConfig<'a> {
+    pub fn new(options: &'a FuzzerOptions) -> Self {
+        Self { options }
+    }
+
+    pub fn run(&self) -> Result<(), Error> {
+        let crash = self
+            .options
+            .emit_debug_config
+            .as_ref()
+            .ok_or_else(|| Error::empty_optional("No crash provided"))?;
+        let crash = fs::canonicalize(crash)?;
+
+        let info = Triage::new(self.options).replay(&crash)?;
+        if info.pc.is_none() {
+            log::warn!("No faulting PC for {crash:?} ({:?}), the debugger won't break on it", info.exit_kind);
+        }
+        let target = self.options.target_binary()?;
+        let target = fs::canonicalize(&target).unwrap_or(target);
+
+        let gdbstub = with_suffix(&crash, "gdbstub.sh");
+        fs::write(&gdbstub, Self::gdbstub_script(&crash)?)?;
+        fs::set_permissions(&gdbstub, Permissions::from_mode(0o755))?;
+
+        let gdb = with_suffix(&crash, "gdb");
+        fs::write(&gdb, Self::gdb_script(&target, info.pc))?;
+
+        let launch = with_suffix(&crash, "launch.json");
+        fs::write(&launch, Self::vscode_launch(&crash, &target, info.pc)?)?;
+
+        println!("Start the replay with {gdbstub:?}, then attach with `gdb-multiarch -x {}` or the VSCode config {launch:?}", gdb.display());
+        if let Some(pc) = info.pc {
+            println!("The crash faults @ {pc:#x}");
+        }
+        Ok(())
+    }
+
+    /// This fuzzer's own command line replaying `crash`, with qemu's gdbstub listening
+    fn gdbstub_script(crash: &Path) -> Result<String, Error> {
+        let mut command = vec![env::current_exe()?.to_string_lossy().into_owned()];
+        command.extend(child_args(
+            &DEBUG_ONLY_ARGS,
+            [
+                "--qemu-arg=-g".to_string(),
+                format!("--qemu-arg={GDB_PORT}"),
+                "-r".to_string(),
+                crash.to_string_lossy().into_owned(),
+            ],
+        ));
+
+        let cwd = env::current_dir()?;
+        Ok(format!(
+            "#!/bin/sh\n# Replays {} and waits for a debugger on port {GDB_PORT}\ncd {} || exit 1\nexec {}\n",
+            crash.display(),
+            shell_words::quote(&cwd.to_string_lossy()),
+            shell_words::join(command)
+        ))
+    }
+
+    fn gdb_script(target: &Path, pc: Option<GuestAddr>) -> String {
+        let mut script = format!("file {}\ntarget remote localhost:{GDB_PORT}\n", target.display());
+        if let Some(pc) = pc {
+            script.push_str(&format!("break *{pc:#x}\n"));
+        }
+        script.push_str("continue\n");
+        script
+    }
+
+    fn vscode_launch(crash: &Path, target: &Path, pc: Option<GuestAddr>) -> Result<String, Error> {
+        let setup = pc
+            .map(|pc| vec![serde_json::json!({ "text": format!("break *{pc:#x}") })])
+            .unwrap_or_default();
+        let config = serde_json::json!({
+            "version": "0.2.0",
+            "configurations": [{
+                "name": format!("Debug {}", crash.display()),
+                "type": "cppdbg",
+                "request": "launch",
+                "program": target,
+                "cwd": env::current_dir()?,
+                "MIMode": "gdb",
+                "miDebuggerPath": "gdb-multiarch",
+                "miDebuggerServerAddress": format!("localhost:{GDB_PORT}"),
+                "stopAtEntry": false,
+                "postRemoteConnectCommands": setup,
+            }],
+        });
+        serde_json::to_string_pretty(&config)
+            .map_err(|e| Error::serialize(format!("Failed to serialize the launch config: {e}")))
+    }
+}
+
+/// `<path>.<suffix>`
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(format!(".{suffix}"));
+    PathBuf::from(path)
+}
@@ -0,0 +1,145 @@
+use std::{
+    collections::BTreeSet,
+    env, fs,
+    path::{Path, PathBuf},
+    process::{self, Command, Stdio},
+};
+
+use libafl::Error;
+use libafl_qemu::GuestAddr;
+
+use crate::{
+    options::FuzzerOptions,
+    triage::{child_args, Triage},
+};
+
+/// Options that only make sense for the diff driver and must not be forwarded to the replays
+const DIFF_ONLY_ARGS: [(&str, bool); 3] =
+    [("--diff-binary", true), ("--target", true), ("--tui", false)];
+
+/// Maximum number of divergent edges printed per side
+const MAX_PRINTED_EDGES: usize = 32;
+
+/// An edge, as offsets of its source and destination from the binary's load address
+pub type Edge = (GuestAddr, GuestAddr);
+
+/// Replays the input corpus on the target and on `--diff-binary`, and reports the edges only one of them hit.
+///
+/// Like the [`Triage`], every input is replayed by a child instance of this fuzzer running with `-r`,
+/// which writes the edges it hit through a file. Inputs crashing a binary don't contribute to its coverage.
+/// Edges are compared by offset, so the comparison is only meaningful between builds sharing most of their layout.
+pub struct CoverageDiff<'a> {
+    options: &'a FuzzerOptions,
+}
+
+impl<'a> CoverageDiff<'a> {
+    pub fn new(options: &'a FuzzerOptions) -> Self {
+        Self { options }
+    }
+
+    pub fn run(&self) -> Result<(), Error> {
+        let secondary = self
+            .options
+            .diff_binary
+            .as_ref()
+            .ok_or_else(|| Error::empty_optional("No binary to diff against provided"))?;
+        let primary = self.options.target_binary()?;
+
+        let inputs = Triage::inputs(&self.options.input_dir())?;
+        let primary_edges = self.coverage(&primary, &primary, &inputs)?;
+        let secondary_edges = self.coverage(&primary, secondary, &inputs)?;
+
+        println!(
+            "Replayed {} inputs: {} edges in {primary:?}, {} edges in {secondary:?}, {} in common",
+            inputs.len(),
+            primary_edges.len(),
+            secondary_edges.len(),
+            primary_edges.intersection(&secondary_edges).count()
+        );
+        Self::print_only(&primary, primary_edges.difference(&secondary_edges));
+        Self::print_only(secondary, secondary_edges.difference(&primary_edges));
+
+        Ok(())
+    }
+
+    /// The union of the edges hit by `inputs` on `binary`
+    fn coverage(
+        &self,
+        primary: &Path,
+        binary: &Path,
+        inputs: &[PathBuf],
+    ) -> Result<BTreeSet<Edge>, Error> {
+        let edges_file = env::temp_dir().join(format!("h1k0_edges_{}.txt", process::id()));
+        let mut edges = BTreeSet::new();
+
+        for input in inputs {
+            let _ = fs::remove_file(&edges_file);
+            let status = Command::new(env::current_exe()?)
+                .args(Self::replay_args(primary, binary, input, &edges_file))
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .status()?;
+
+            match fs::read_to_string(&edges_file) {
+                Ok(content) => edges.extend(Self::parse_edges(&content)?),
+                Err(_) => log::warn!("No coverage for {input:?} on {binary:?} (child exited with {status})"),
+            }
+        }
+        let _ = fs::remove_file(&edges_file);
+
+        Ok(edges)
+    }
+
+    /// Our own command line, with the target binary swapped for `binary` and `-r <input>` before the target arguments
+    fn replay_args(primary: &Path, binary: &Path, input: &Path, edges_file: &Path) -> Vec<String> {
+        let binary = binary.to_string_lossy().into_owned();
+        let mut args = child_args(
+            &DIFF_ONLY_ARGS,
+            [
+                "--target".to_string(),
+                binary.clone(),
+                "-r".to_string(),
+                input.to_string_lossy().into_owned(),
+                "--edges-out".to_string(),
+                edges_file.to_string_lossy().into_owned(),
+            ],
+        );
+
+        let target_args = args.iter().position(|a| a == "--").map_or(args.len(), |pos| pos + 1);
+        if let Some(arg) = args[target_args..]
+            .iter_mut()
+            .find(|arg| Path::new(arg.as_str()) == primary)
+        {
+            *arg = binary;
+        }
+        args
+    }
+
+    /// Lines of `<src offset> <dst offset>` in hex, as written by `--edges-out`
+    pub fn parse_edges(content: &str) -> Result<Vec<Edge>, Error> {
+        content
+            .lines()
+            .map(|line| {
+                let parse = |field: Option<&str>| {
+                    field
+                        .and_then(|f| GuestAddr::from_str_radix(f.trim_start_matches("0x"), 16).ok())
+                        .ok_or_else(|| Error::illegal_argument(format!("Invalid edge: {line}")))
+                };
+                let mut fields = line.split_whitespace();
+                Ok((parse(fields.next())?, parse(fields.next())?))
+            })
+            .collect()
+    }
+
+    fn print_only<'e>(binary: &Path, edges: impl Iterator<Item = &'e Edge>) {
+        let edges = edges.collect::<Vec<&Edge>>();
+        println!("{} edges only hit in {binary:?}", edges.len());
+        for (src, dst) in edges.iter().take(MAX_PRINTED_EDGES) {
+            println!("    +{src:#x} -> +{dst:#x}");
+        }
+        if edges.len() > MAX_PRINTED_EDGES {
+            println!("    ... and {} more", edges.len() - MAX_PRINTED_EDGES);
+        }
+    }
+}
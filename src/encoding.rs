@@ -0,0 +1,46 @@
+use crate::options::Encoding;
+
+/// The value of a base64 digit (standard or URL-safe alphabet)
+fn base64_digit(byte: u8) -> Option<u32> {
+    match byte {
+        b'A'..=b'Z' => Some((byte - b'A') as u32),
+        b'a'..=b'z' => Some((byte - b'a' + 26) as u32),
+        b'0'..=b'9' => Some((byte - b'0' + 52) as u32),
+        b'+' | b'-' => Some(62),
+        b'/' | b'_' => Some(63),
+        _ => None,
+    }
+}
+
+impl Encoding {
+    /// Decode `bytes`, leniently: the bytes outside of the alphabet (padding included) are skipped
+    /// and a trailing partial group is dropped, so a mutated input always decodes to something
+    pub fn decode(self, bytes: &[u8]) -> Vec<u8> {
+        match self {
+            Encoding::Raw => bytes.to_vec(),
+            Encoding::Base64 => {
+                let mut decoded = Vec::with_capacity(bytes.len() / 4 * 3);
+                let (mut acc, mut bits) = (0u32, 0);
+                for digit in bytes.iter().filter_map(|byte| base64_digit(*byte)) {
+                    acc = (acc << 6) | digit;
+                    bits += 6;
+                    if bits >= 8 {
+                        bits -= 8;
+                        decoded.push((acc >> bits) as u8);
+                    }
+                }
+                decoded
+            }
+            Encoding::Hex => {
+                let nibbles = bytes
+                    .iter()
+                    .filter_map(|byte| (*byte as char).to_digit(16))
+                    .collect::<Vec<u32>>();
+                nibbles
+                    .chunks_exact(2)
+                    .map(|pair| (pair[0] << 4 | pair[1]) as u8)
+                    .collect()
+            }
+        }
+    }
+}
@@ -0,0 +1,3 @@
+pub mod scaled_timeout;
+
+pub use scaled_timeout::ScaledTimeoutExecutor;
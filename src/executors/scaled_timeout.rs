@@ -0,0 +1,110 @@
+use core::time::Duration;
+use std::{collections::HashMap, mem};
+
+use libafl::{
+    executors::{hooks::timer::TimerStruct, Executor, ExitKind, HasObservers},
+    Error,
+};
+use libafl_bolts::{tuples::RefIndexable, HasLen};
+
+/// Number of timeouts between the base and the maximum, see [`ScaledTimeoutExecutor`]
+const TIMEOUT_STEPS: u64 = 32;
+
+/// Scales the timeout of every execution with the size of its input, see `--timeout-per-kb`:
+/// `base + per_kb * <whole KBs of the input>`, at most `max`.
+///
+/// The in-process executor arms the same timer for every execution, so the timer of the input's timeout
+/// is swapped in before running it. Building a timer creates a kernel timer, so the timeouts are rounded
+/// up to one of [`TIMEOUT_STEPS`] steps between the base and the maximum and their timers are kept for reuse.
+pub struct ScaledTimeoutExecutor<E> {
+    inner: E,
+    timer: fn(&mut E) -> &mut TimerStruct,
+    base_ms: u64,
+    per_kb_ms: u64,
+    max_ms: u64,
+    current_ms: u64,
+    timers: HashMap<u64, TimerStruct>,
+}
+
+impl<E> ScaledTimeoutExecutor<E> {
+    /// Wrap `inner`, built with the `base` timeout, whose timer is given by `timer`
+    pub fn new(
+        inner: E,
+        timer: fn(&mut E) -> &mut TimerStruct,
+        base: Duration,
+        per_kb: Duration,
+        max: Duration,
+    ) -> Self {
+        let base_ms = base.as_millis() as u64;
+        Self {
+            inner,
+            timer,
+            base_ms,
+            per_kb_ms: per_kb.as_millis() as u64,
+            max_ms: (max.as_millis() as u64).max(base_ms),
+            current_ms: base_ms,
+            timers: HashMap::new(),
+        }
+    }
+
+    pub fn inner_mut(&mut self) -> &mut E {
+        &mut self.inner
+    }
+
+    /// The timeout of an input of `len` bytes, in ms
+    fn timeout_ms(&self, len: usize) -> u64 {
+        if self.per_kb_ms == 0 {
+            return self.base_ms;
+        }
+        let extra = (len as u64 / 1024).saturating_mul(self.per_kb_ms);
+        let step = ((self.max_ms - self.base_ms) / TIMEOUT_STEPS).max(1);
+        self.base_ms
+            .saturating_add(extra.div_ceil(step).saturating_mul(step))
+            .min(self.max_ms)
+    }
+
+    fn set_timeout_ms(&mut self, timeout_ms: u64) {
+        if timeout_ms == self.current_ms {
+            return;
+        }
+        let timer = self
+            .timers
+            .remove(&timeout_ms)
+            .unwrap_or_else(|| TimerStruct::new(Duration::from_millis(timeout_ms)));
+        let previous = mem::replace((self.timer)(&mut self.inner), timer);
+        self.timers.insert(self.current_ms, previous);
+        self.current_ms = timeout_ms;
+    }
+}
+
+impl<E, EM, I, S, Z> Executor<EM, I, S, Z> for ScaledTimeoutExecutor<E>
+where
+    E: Executor<EM, I, S, Z>,
+    I: HasLen,
+{
+    fn run_target(
+        &mut self,
+        fuzzer: &mut Z,
+        state: &mut S,
+        mgr: &mut EM,
+        input: &I,
+    ) -> Result<ExitKind, Error> {
+        self.set_timeout_ms(self.timeout_ms(input.len()));
+        self.inner.run_target(fuzzer, state, mgr, input)
+    }
+}
+
+impl<E> HasObservers for ScaledTimeoutExecutor<E>
+where
+    E: HasObservers,
+{
+    type Observers = E::Observers;
+
+    fn observers(&self) -> RefIndexable<&Self::Observers, Self::Observers> {
+        self.inner.observers()
+    }
+
+    fn observers_mut(&mut self) -> RefIndexable<&mut Self::Observers, Self::Observers> {
+        self.inner.observers_mut()
+    }
+}
@@ -0,0 +1,63 @@
+use core::time::Duration;
+use std::net::{Ipv4Addr, SocketAddr, TcpStream};
+
+use libafl_bolts::current_time;
+use serde::{Deserialize, Serialize};
+
+/// How often a client checks that its broker is still there, see `--standby-port`
+const PROBE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// A broker not accepting connections for this long is taken for dead
+const PROBE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Failed probes in a row before failing over, a busy broker may miss one
+const PROBE_MISSES: u32 = 3;
+
+/// Kept in the state of a client that failed over to the standby broker on `port`.
+/// The state outlives the client's restarts, which reattach to the standby by themselves.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BrokerFailover {
+    pub port: u16,
+}
+
+libafl_bolts::impl_serdeany!(BrokerFailover);
+
+/// Tells when the broker on a local port is gone, by connecting to it every [`PROBE_INTERVAL`].
+/// The clients talk to the broker through shared memory, which doesn't notice a dead peer,
+/// while its listening socket closes with it.
+#[derive(Debug)]
+pub struct BrokerProbe {
+    addr: SocketAddr,
+    last: Duration,
+    misses: u32,
+}
+
+impl BrokerProbe {
+    pub fn new(port: u16) -> Self {
+        Self {
+            addr: SocketAddr::from((Ipv4Addr::LOCALHOST, port)),
+            last: current_time(),
+            misses: 0,
+        }
+    }
+
+    /// Whether the broker is lost, probing it when it's time to
+    pub fn lost(&mut self) -> bool {
+        if current_time().saturating_sub(self.last) < PROBE_INTERVAL {
+            return false;
+        }
+        self.last = current_time();
+        match TcpStream::connect_timeout(&self.addr, PROBE_TIMEOUT) {
+            Ok(_) => self.misses = 0,
+            Err(e) => {
+                self.misses += 1;
+                log::warn!(
+                    "The broker at {} is unreachable ({}/{PROBE_MISSES}): {e}",
+                    self.addr,
+                    self.misses
+                );
+            }
+        }
+        self.misses >= PROBE_MISSES
+    }
+}
@@ -0,0 +1,80 @@
+use std::{borrow::Cow, marker::PhantomData};
+
+use libafl::{
+    executors::ExitKind,
+    feedbacks::{Feedback, StateInitializer},
+    observers::MapObserver,
+    Error, HasMetadata,
+};
+use libafl_bolts::{
+    tuples::{Handle, Handled, MatchNameRef},
+    Named,
+};
+
+use crate::modules::collisions::{CollisionSample, CollisionStats};
+
+/// Adds the map slots used by the runs the [`crate::modules::CollisionSampleModule`] sampled to the
+/// [`CollisionStats`] of the state, along with their block transitions. It never reports an input as interesting.
+#[derive(Debug)]
+pub struct CollisionFeedback<C, O> {
+    map_handle: Handle<C>,
+    phantom: PhantomData<O>,
+}
+
+impl<C, O> CollisionFeedback<C, O>
+where
+    C: AsRef<O> + Named,
+{
+    pub fn new(map_observer: &C) -> Self {
+        Self {
+            map_handle: map_observer.handle(),
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<C, EM, I, O, OT, S> Feedback<EM, I, OT, S> for CollisionFeedback<C, O>
+where
+    C: AsRef<O>,
+    O: MapObserver<Entry = u8>,
+    OT: MatchNameRef,
+    S: HasMetadata,
+{
+    fn is_interesting(
+        &mut self,
+        state: &mut S,
+        _manager: &mut EM,
+        _input: &I,
+        observers: &OT,
+        _exit_kind: &ExitKind,
+    ) -> Result<bool, Error> {
+        let Some(transitions) = state
+            .metadata_map_mut()
+            .get_mut::<CollisionSample>()
+            .and_then(|sample| sample.transitions.take())
+        else {
+            return Ok(false);
+        };
+
+        let slots = observers[&self.map_handle]
+            .as_ref()
+            .to_vec()
+            .iter()
+            .filter(|hits| **hits != 0)
+            .count();
+        let stats = state.metadata_or_insert_with(CollisionStats::default);
+        stats.runs += 1;
+        stats.transitions += transitions as u64;
+        stats.slots += slots as u64;
+        Ok(false)
+    }
+}
+
+impl<C, O, S> StateInitializer<S> for CollisionFeedback<C, O> {}
+
+impl<C, O> Named for CollisionFeedback<C, O> {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("CollisionFeedback");
+        &NAME
+    }
+}
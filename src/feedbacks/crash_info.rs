@@ -0,0 +1,123 @@
+use std::{borrow::Cow, fs, path::PathBuf};
+
+use libafl::{
+    corpus::Testcase,
+    executors::ExitKind,
+    feedbacks::{Feedback, StateInitializer},
+    inputs::{HasTargetBytes, Input},
+    Error, HasMetadata,
+};
+use libafl_bolts::Named;
+
+use crate::modules::{CrashInfo, ExecMeta};
+
+/// Classifies every execution by exit kind, signal and faulting PC.
+/// It never reports an input as interesting on its own, it only attaches a [`CrashInfo`]
+/// to the saved solutions and optionally dumps it to `report` (used by crash triage).
+/// A crash on a signal outside of the allowed ones is ignored (see [`ExecMeta::ignore`]), like a clean exit.
+#[derive(Debug, Default)]
+pub struct CrashInfoFeedback {
+    report: Option<PathBuf>,
+    last: Option<CrashInfo>,
+    descriptive_names: bool,
+    crash_signals: Option<Vec<i32>>,
+}
+
+impl CrashInfoFeedback {
+    pub fn new(report: Option<PathBuf>) -> Self {
+        Self {
+            report,
+            ..Default::default()
+        }
+    }
+
+    /// Name the solutions after their [`CrashInfo`] and size, like `sig11_pc0x401234_len128_<hash>`,
+    /// see `--descriptive-crash-names`. The later feedbacks of the objective see the name as the testcase's filename.
+    pub fn descriptive_names(mut self, enabled: bool) -> Self {
+        self.descriptive_names = enabled;
+        self
+    }
+
+    /// Only count the guest crashes on these signals, see `--crash-signals`
+    pub fn crash_signals(mut self, signals: Vec<i32>) -> Self {
+        self.crash_signals = Some(signals);
+        self
+    }
+
+    fn descriptive_name<I: Input + HasTargetBytes>(info: &CrashInfo, input: &I) -> String {
+        let kind = match (info.exit_kind, info.signal) {
+            (ExitKind::Timeout, _) => "timeout".to_string(),
+            (_, Some(signal)) => format!("sig{signal}"),
+            (exit_kind, None) => format!("{exit_kind:?}").to_lowercase(),
+        };
+        let pc = info.pc.map(|pc| format!("_pc{pc:#x}")).unwrap_or_default();
+        // The hash keeps the names of different inputs apart
+        format!(
+            "{kind}{pc}_len{}_{}",
+            input.target_bytes().len(),
+            input.generate_name(None)
+        )
+    }
+}
+
+impl<EM, I, OT, S> Feedback<EM, I, OT, S> for CrashInfoFeedback
+where
+    I: Input + HasTargetBytes,
+    S: HasMetadata,
+{
+    fn is_interesting(
+        &mut self,
+        _state: &mut S,
+        _manager: &mut EM,
+        _input: &I,
+        _observers: &OT,
+        exit_kind: &ExitKind,
+    ) -> Result<bool, Error> {
+        let info = CrashInfo::new(*exit_kind);
+        if let (Some(signals), Some(signal)) = (&self.crash_signals, info.signal) {
+            if !signals.contains(&signal) {
+                log::debug!("Guest signal {signal} isn't a crash, ignoring the run");
+                // Runs before the crash feedback of the objective, which consumes it
+                _state.metadata_or_insert_with(ExecMeta::new).ignore = true;
+            }
+        }
+        if let Some(report) = &self.report {
+            let json = serde_json::to_string(&info)
+                .map_err(|e| Error::serialize(format!("Failed to serialize crash info: {e}")))?;
+            fs::write(report, json)?;
+        }
+        self.last = Some(info);
+        Ok(false)
+    }
+
+    fn append_metadata(
+        &mut self,
+        _state: &mut S,
+        _manager: &mut EM,
+        _observers: &OT,
+        testcase: &mut Testcase<I>,
+    ) -> Result<(), Error> {
+        if let Some(info) = self.last.take() {
+            if self.descriptive_names {
+                let name = testcase
+                    .input()
+                    .as_ref()
+                    .map(|input| Self::descriptive_name(&info, input));
+                if name.is_some() {
+                    *testcase.filename_mut() = name;
+                }
+            }
+            testcase.add_metadata(info);
+        }
+        Ok(())
+    }
+}
+
+impl<S> StateInitializer<S> for CrashInfoFeedback {}
+
+impl Named for CrashInfoFeedback {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("CrashInfoFeedback");
+        &NAME
+    }
+}
@@ -0,0 +1,64 @@
+use std::{borrow::Cow, path::PathBuf};
+
+use libafl::{
+    executors::ExitKind,
+    feedbacks::{Feedback, StateInitializer},
+    inputs::Input,
+    Error, HasMetadata,
+};
+use libafl_bolts::Named;
+
+use crate::{feedbacks::finding::save_finding, modules::ExecMeta};
+
+/// Saves the inputs making the target `exit()` with `code` as findings.
+///
+/// Exits are turned into aborts by the injector (see [`ExecMeta::ignore`]), so this feedback
+/// belongs to the objective, where it sees them. It never reports an input as a crash.
+#[derive(Debug)]
+pub struct ExitCodeFeedback {
+    code: Option<i32>,
+    dir: PathBuf,
+}
+
+impl ExitCodeFeedback {
+    pub fn new(code: Option<i32>, dir: PathBuf) -> Self {
+        Self { code, dir }
+    }
+}
+
+impl<EM, I, OT, S> Feedback<EM, I, OT, S> for ExitCodeFeedback
+where
+    S: HasMetadata,
+    I: Input,
+{
+    fn is_interesting(
+        &mut self,
+        _state: &mut S,
+        _manager: &mut EM,
+        _input: &I,
+        _observers: &OT,
+        _exit_kind: &ExitKind,
+    ) -> Result<bool, Error> {
+        let exit_code = _state
+            .metadata_map_mut()
+            .get_mut::<ExecMeta>()
+            .and_then(|exec_meta| exec_meta.exit_code.take());
+
+        if let (Some(code), Some(exit_code)) = (self.code, exit_code) {
+            if code == exit_code {
+                save_finding(&self.dir, _input, &format!("exit code {exit_code}"))?;
+            }
+        }
+
+        Ok(false)
+    }
+}
+
+impl<S> StateInitializer<S> for ExitCodeFeedback {}
+
+impl Named for ExitCodeFeedback {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("ExitCodeFeedback");
+        &NAME
+    }
+}
@@ -0,0 +1,74 @@
+use std::{
+    borrow::Cow,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use libafl::{
+    executors::ExitKind,
+    feedbacks::{Feedback, StateInitializer},
+    inputs::Input,
+    Error, HasMetadata,
+};
+use libafl_bolts::Named;
+
+use crate::modules::ExecMeta;
+
+/// Saves the inputs flagged as a finding by a detector (see [`ExecMeta::finding`]) into their own directory.
+/// Findings are neither corpus entries nor crash solutions, so this feedback never reports an input as interesting.
+#[derive(Debug)]
+pub struct FindingFeedback {
+    dir: PathBuf,
+}
+
+impl FindingFeedback {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+}
+
+impl<EM, I, OT, S> Feedback<EM, I, OT, S> for FindingFeedback
+where
+    S: HasMetadata,
+    I: Input,
+{
+    fn is_interesting(
+        &mut self,
+        _state: &mut S,
+        _manager: &mut EM,
+        _input: &I,
+        _observers: &OT,
+        _exit_kind: &ExitKind,
+    ) -> Result<bool, Error> {
+        let reason = _state
+            .metadata_map_mut()
+            .get_mut::<ExecMeta>()
+            .and_then(|exec_meta| exec_meta.finding.take());
+
+        if let Some(reason) = reason {
+            save_finding(&self.dir, _input, &reason)?;
+        }
+
+        Ok(false)
+    }
+}
+
+impl<S> StateInitializer<S> for FindingFeedback {}
+
+/// Save `input` into the findings `dir`, unless it's already there
+pub fn save_finding<I: Input>(dir: &Path, input: &I, reason: &str) -> Result<(), Error> {
+    fs::create_dir_all(dir)?;
+    let path = dir.join(input.generate_name(None));
+    if !path.exists() {
+        input.to_file(&path)?;
+        log::info!("New finding ({reason}) saved to {path:?}");
+    }
+    Ok(())
+}
+
+impl Named for FindingFeedback {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("FindingFeedback");
+        &NAME
+    }
+}
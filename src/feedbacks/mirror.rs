@@ -0,0 +1,78 @@
+use std::{borrow::Cow, fs, path::PathBuf};
+
+use libafl::{
+    corpus::Testcase,
+    executors::ExitKind,
+    feedbacks::{Feedback, StateInitializer},
+    inputs::Input,
+    Error,
+};
+use libafl_bolts::{core_affinity::CoreId, Named};
+
+/// Copies every new solution into a directory shared by all the clients, see `--solutions-dir`.
+/// It never reports an input as interesting on its own, it only runs once the rest of the objective did.
+#[derive(Debug)]
+pub struct SolutionMirrorFeedback {
+    dir: Option<PathBuf>,
+    core_id: CoreId,
+}
+
+impl SolutionMirrorFeedback {
+    pub fn new(dir: Option<PathBuf>, core_id: CoreId) -> Self {
+        Self { dir, core_id }
+    }
+}
+
+impl<EM, I, OT, S> Feedback<EM, I, OT, S> for SolutionMirrorFeedback
+where
+    I: Input,
+{
+    fn is_interesting(
+        &mut self,
+        _state: &mut S,
+        _manager: &mut EM,
+        _input: &I,
+        _observers: &OT,
+        _exit_kind: &ExitKind,
+    ) -> Result<bool, Error> {
+        Ok(false)
+    }
+
+    fn append_metadata(
+        &mut self,
+        _state: &mut S,
+        _manager: &mut EM,
+        _observers: &OT,
+        testcase: &mut Testcase<I>,
+    ) -> Result<(), Error> {
+        let (Some(dir), Some(input)) = (&self.dir, testcase.input()) else {
+            return Ok(());
+        };
+
+        fs::create_dir_all(dir)?;
+        // The core prefix keeps the clients apart, the suffix keeps a client's own solutions apart
+        let name = testcase
+            .filename()
+            .clone()
+            .unwrap_or_else(|| input.generate_name(None));
+        let name = format!("core_{:03}_{name}", self.core_id.0);
+        let mut path = dir.join(&name);
+        let mut n = 1;
+        while path.exists() {
+            path = dir.join(format!("{name}_{n}"));
+            n += 1;
+        }
+        input.to_file(&path)?;
+        log::info!("Solution mirrored to {path:?}");
+        Ok(())
+    }
+}
+
+impl<S> StateInitializer<S> for SolutionMirrorFeedback {}
+
+impl Named for SolutionMirrorFeedback {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("SolutionMirrorFeedback");
+        &NAME
+    }
+}
@@ -1 +1,13 @@
-pub mod ignore_exit;
\ No newline at end of file
+pub mod collisions;
+pub mod crash_info;
+pub mod exit_code;
+pub mod finding;
+pub mod ignore_exit;
+pub mod mirror;
+pub mod new_edges;
+pub mod notify;
+pub mod output;
+pub mod presence;
+pub mod provenance;
+pub mod reproducer;
+pub mod target_reach;
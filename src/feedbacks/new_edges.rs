@@ -0,0 +1,101 @@
+use std::{borrow::Cow, marker::PhantomData};
+
+use libafl::{
+    corpus::{HasCurrentCorpusId, Testcase},
+    executors::ExitKind,
+    feedbacks::{Feedback, StateInitializer},
+    inputs::HasTargetBytes,
+    observers::MapObserver,
+    Error,
+};
+use libafl_bolts::{
+    tuples::{Handle, Handled, MatchNameRef},
+    Named,
+};
+
+/// Prints `new input: +N edges, size M` for every new corpus entry, see `--verbose`.
+/// The edges are the map entries no previous corpus entry covered, an entry only adding a hitcount bucket adds none.
+/// It never reports an input as interesting on its own.
+#[derive(Debug)]
+pub struct NewEdgesLogFeedback<C, O> {
+    map_handle: Handle<C>,
+    enabled: bool,
+    // The map entries covered by the corpus so far
+    covered: Vec<bool>,
+    phantom: PhantomData<O>,
+}
+
+impl<C, O> NewEdgesLogFeedback<C, O>
+where
+    C: AsRef<O> + Named,
+{
+    pub fn new(map_observer: &C, enabled: bool) -> Self {
+        Self {
+            map_handle: map_observer.handle(),
+            enabled,
+            covered: Vec::new(),
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<C, EM, I, O, OT, S> Feedback<EM, I, OT, S> for NewEdgesLogFeedback<C, O>
+where
+    C: AsRef<O>,
+    O: MapObserver<Entry = u8>,
+    OT: MatchNameRef,
+    I: HasTargetBytes,
+    S: HasCurrentCorpusId,
+{
+    fn is_interesting(
+        &mut self,
+        _state: &mut S,
+        _manager: &mut EM,
+        _input: &I,
+        _observers: &OT,
+        _exit_kind: &ExitKind,
+    ) -> Result<bool, Error> {
+        Ok(false)
+    }
+
+    fn append_metadata(
+        &mut self,
+        state: &mut S,
+        _manager: &mut EM,
+        observers: &OT,
+        testcase: &mut Testcase<I>,
+    ) -> Result<(), Error> {
+        if !self.enabled {
+            return Ok(());
+        }
+
+        let map = observers[&self.map_handle].as_ref().to_vec();
+        if self.covered.len() < map.len() {
+            self.covered.resize(map.len(), false);
+        }
+        let mut new_edges = 0;
+        for (covered, hits) in self.covered.iter_mut().zip(&map) {
+            if *hits != 0 && !*covered {
+                *covered = true;
+                new_edges += 1;
+            }
+        }
+
+        let size = testcase.input().as_ref().map_or(0, |input| input.target_bytes().len());
+        let provenance = match state.current_corpus_id()? {
+            Some(parent) => format!("mutated from {parent}"),
+            None => "seed".to_string(),
+        };
+        println!("new input: +{new_edges} edges, size {size}, {provenance}");
+        Ok(())
+    }
+}
+
+impl<C, O, S> StateInitializer<S> for NewEdgesLogFeedback<C, O> {}
+
+impl<C, O> Named for NewEdgesLogFeedback<C, O> {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("NewEdgesLogFeedback");
+        &NAME
+    }
+}
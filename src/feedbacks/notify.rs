@@ -0,0 +1,117 @@
+use std::{borrow::Cow, fs, path::PathBuf};
+
+use libafl::{
+    corpus::Testcase,
+    executors::ExitKind,
+    feedbacks::{Feedback, StateInitializer},
+    inputs::{HasTargetBytes, Input},
+    state::HasExecutions,
+    Error, HasMetadata,
+};
+use libafl_bolts::Named;
+
+use crate::{
+    modules::CrashInfo,
+    notify::{EventInfo, EventKind, EventSink},
+};
+
+/// Tells the [`EventSink`]s about every new entry of the corpus saved in `dir`, of the `kind` it holds.
+/// It never reports an input as interesting on its own, it only runs once the rest of the (objective) feedback did.
+///
+/// It runs before the corpus saves the entry, so it pins the entry's name and saves the input itself
+/// for the sinks to find it, the corpus then writes the same file.
+#[derive(Debug)]
+pub struct EventSinkFeedback {
+    sinks: Vec<Box<dyn EventSink>>,
+    kind: EventKind,
+    dir: PathBuf,
+    client: usize,
+    exit_kind: ExitKind,
+}
+
+impl EventSinkFeedback {
+    pub fn new(sinks: Vec<Box<dyn EventSink>>, kind: EventKind, dir: PathBuf, client: usize) -> Self {
+        Self {
+            sinks,
+            kind,
+            dir,
+            client,
+            exit_kind: ExitKind::Ok,
+        }
+    }
+}
+
+impl<EM, I, OT, S> Feedback<EM, I, OT, S> for EventSinkFeedback
+where
+    I: Input + HasTargetBytes,
+    S: HasExecutions,
+{
+    fn is_interesting(
+        &mut self,
+        _state: &mut S,
+        _manager: &mut EM,
+        _input: &I,
+        _observers: &OT,
+        _exit_kind: &ExitKind,
+    ) -> Result<bool, Error> {
+        self.exit_kind = *_exit_kind;
+        Ok(false)
+    }
+
+    fn append_metadata(
+        &mut self,
+        _state: &mut S,
+        _manager: &mut EM,
+        _observers: &OT,
+        testcase: &mut Testcase<I>,
+    ) -> Result<(), Error> {
+        if self.sinks.is_empty() {
+            return Ok(());
+        }
+        let Some(input) = testcase.input().clone() else {
+            return Ok(());
+        };
+        let name = testcase
+            .filename()
+            .clone()
+            .unwrap_or_else(|| input.generate_name(None));
+        *testcase.filename_mut() = Some(name.clone());
+
+        fs::create_dir_all(&self.dir)?;
+        let path = self.dir.join(name);
+        if !path.exists() {
+            input.to_file(&path)?;
+        }
+
+        let info = EventInfo {
+            kind: self.kind,
+            path,
+            client: self.client,
+            exit_kind: self.exit_kind,
+            executions: *_state.executions(),
+            len: input.target_bytes().len(),
+            // Attached by the `CrashInfoFeedback`, earlier in the objective
+            crash: testcase.metadata::<CrashInfo>().ok().cloned(),
+        };
+        for sink in &mut self.sinks {
+            let result = match self.kind {
+                EventKind::Solution => sink.on_new_solution(&info),
+                EventKind::Corpus => sink.on_new_corpus(&info),
+            };
+            // A broken sink must not stop the campaign
+            if let Err(e) = result {
+                log::warn!("Event sink {sink:?} failed on {:?}: {e}", info.path);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<S> StateInitializer<S> for EventSinkFeedback {}
+
+impl Named for EventSinkFeedback {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("EventSinkFeedback");
+        &NAME
+    }
+}
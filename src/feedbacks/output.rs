@@ -0,0 +1,64 @@
+use std::{borrow::Cow, path::PathBuf};
+
+use libafl::{
+    executors::ExitKind,
+    feedbacks::{Feedback, StateInitializer},
+    inputs::Input,
+    Error, HasMetadata,
+};
+use libafl_bolts::Named;
+use regex::bytes::Regex;
+
+use crate::{feedbacks::finding::save_finding, modules::CapturedOutput};
+
+/// Saves the inputs whose stdout (see [`CapturedOutput`]) matches `regex` as findings, see `--crash-on-output`.
+/// Like the other findings, they are neither corpus entries nor crash solutions.
+#[derive(Debug)]
+pub struct OutputMatchFeedback {
+    regex: Option<Regex>,
+    dir: PathBuf,
+}
+
+impl OutputMatchFeedback {
+    pub fn new(regex: Option<Regex>, dir: PathBuf) -> Self {
+        Self { regex, dir }
+    }
+}
+
+impl<EM, I, OT, S> Feedback<EM, I, OT, S> for OutputMatchFeedback
+where
+    S: HasMetadata,
+    I: Input,
+{
+    fn is_interesting(
+        &mut self,
+        _state: &mut S,
+        _manager: &mut EM,
+        _input: &I,
+        _observers: &OT,
+        _exit_kind: &ExitKind,
+    ) -> Result<bool, Error> {
+        let Some(regex) = &self.regex else {
+            return Ok(false);
+        };
+
+        let matched = _state
+            .metadata_map()
+            .get::<CapturedOutput>()
+            .is_some_and(|output| regex.is_match(&output.bytes));
+        if matched {
+            save_finding(&self.dir, _input, &format!("output matching {regex}"))?;
+        }
+
+        Ok(false)
+    }
+}
+
+impl<S> StateInitializer<S> for OutputMatchFeedback {}
+
+impl Named for OutputMatchFeedback {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("OutputMatchFeedback");
+        &NAME
+    }
+}
@@ -0,0 +1,16 @@
+use libafl::feedbacks::{DifferentIsNovel, MapFeedback, Reducer};
+
+/// Reduces the map history to whether each entry was ever hit, whatever its hitcount bucket
+#[derive(Debug, Clone, Copy)]
+pub struct PresenceReducer;
+
+impl Reducer<u8> for PresenceReducer {
+    #[inline]
+    fn reduce(history: u8, new: u8) -> u8 {
+        history.max(u8::from(new != 0))
+    }
+}
+
+/// Interesting when the run hits a map entry never hit before, see `--coverage-mode edges`.
+/// The history only holds 0 or 1, so the classified hitcounts of the edges observer can't make a known edge novel.
+pub type EdgePresenceFeedback<C, O> = MapFeedback<C, DifferentIsNovel, O, PresenceReducer>;
@@ -0,0 +1,56 @@
+use std::borrow::Cow;
+
+use libafl::{
+    corpus::Testcase,
+    executors::ExitKind,
+    feedbacks::{Feedback, StateInitializer},
+    Error, HasMetadata,
+};
+use libafl_bolts::Named;
+
+use crate::mutators::provenance::MutationTrail;
+
+/// Attaches the [`MutationTrail`] of the run, if any, to the entry it adds as a
+/// [`Provenance`](crate::mutators::provenance::Provenance) metadata, see `--provenance`.
+/// The seeds and the inputs of other clients have none. It never reports an input as interesting on its own.
+#[derive(Debug, Default)]
+pub struct ProvenanceFeedback;
+
+impl<EM, I, OT, S> Feedback<EM, I, OT, S> for ProvenanceFeedback
+where
+    S: HasMetadata,
+{
+    fn is_interesting(
+        &mut self,
+        _state: &mut S,
+        _manager: &mut EM,
+        _input: &I,
+        _observers: &OT,
+        _exit_kind: &ExitKind,
+    ) -> Result<bool, Error> {
+        Ok(false)
+    }
+
+    fn append_metadata(
+        &mut self,
+        state: &mut S,
+        _manager: &mut EM,
+        _observers: &OT,
+        testcase: &mut Testcase<I>,
+    ) -> Result<(), Error> {
+        // Both the corpus and the objective feedbacks attach it, the trail is only cleared after the run
+        if let Some(trail) = state.metadata_map().get::<MutationTrail>() {
+            testcase.add_metadata(trail.0.clone());
+        }
+        Ok(())
+    }
+}
+
+impl<S> StateInitializer<S> for ProvenanceFeedback {}
+
+impl Named for ProvenanceFeedback {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("ProvenanceFeedback");
+        &NAME
+    }
+}
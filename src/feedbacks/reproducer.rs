@@ -0,0 +1,104 @@
+use std::{
+    borrow::Cow,
+    env,
+    fs::{self, Permissions},
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
+};
+
+use libafl::{
+    corpus::Testcase,
+    executors::ExitKind,
+    feedbacks::{Feedback, StateInitializer},
+    inputs::Input,
+    Error,
+};
+use libafl_bolts::Named;
+
+use crate::triage::child_args;
+
+/// Options of the fuzzing campaign that must not be forwarded to a replay
+const FUZZ_ONLY_ARGS: [(&str, bool); 1] = [("--tui", false)];
+
+/// Writes a `<solution>.sh` next to every new solution in `dir`, replaying it with this fuzzer's own command line and `-r`.
+/// It never reports an input as interesting on its own, it only runs once the rest of the objective did.
+#[derive(Debug)]
+pub struct ReproducerFeedback {
+    dir: Option<PathBuf>,
+}
+
+impl ReproducerFeedback {
+    pub fn new(dir: Option<PathBuf>) -> Self {
+        Self { dir }
+    }
+
+    fn script(solution: &Path) -> Result<String, Error> {
+        let mut command = vec![env::current_exe()?.to_string_lossy().into_owned()];
+        command.extend(child_args(
+            &FUZZ_ONLY_ARGS,
+            ["-r".to_string(), solution.to_string_lossy().into_owned()],
+        ));
+
+        let cwd = env::current_dir()?;
+        Ok(format!(
+            "#!/bin/sh\n# Replays {}\ncd {} || exit 1\nexec {}\n",
+            solution.display(),
+            shell_words::quote(&cwd.to_string_lossy()),
+            shell_words::join(command)
+        ))
+    }
+}
+
+impl<EM, I, OT, S> Feedback<EM, I, OT, S> for ReproducerFeedback
+where
+    I: Input,
+{
+    fn is_interesting(
+        &mut self,
+        _state: &mut S,
+        _manager: &mut EM,
+        _input: &I,
+        _observers: &OT,
+        _exit_kind: &ExitKind,
+    ) -> Result<bool, Error> {
+        Ok(false)
+    }
+
+    fn append_metadata(
+        &mut self,
+        _state: &mut S,
+        _manager: &mut EM,
+        _observers: &OT,
+        testcase: &mut Testcase<I>,
+    ) -> Result<(), Error> {
+        let Some(dir) = &self.dir else {
+            return Ok(());
+        };
+        let Some(name) = testcase
+            .filename()
+            .clone()
+            .or_else(|| testcase.input().as_ref().map(|input| input.generate_name(None)))
+        else {
+            return Ok(());
+        };
+        // Pin the name, so that the solutions corpus saves the input where the script expects it
+        *testcase.filename_mut() = Some(name.clone());
+
+        fs::create_dir_all(dir)?;
+        let solution = dir.join(&name);
+        let script = dir.join(format!("{name}.sh"));
+        fs::write(&script, Self::script(&solution)?)?;
+        fs::set_permissions(&script, Permissions::from_mode(0o755))?;
+        log::info!("Reproducer written to {script:?}");
+        Ok(())
+    }
+}
+
+impl<S> StateInitializer<S> for ReproducerFeedback {}
+
+impl Named for ReproducerFeedback {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("ReproducerFeedback");
+        &NAME
+    }
+}
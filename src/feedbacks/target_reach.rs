@@ -0,0 +1,70 @@
+use std::{borrow::Cow, mem};
+
+use libafl::{
+    corpus::Testcase,
+    executors::ExitKind,
+    feedbacks::{Feedback, StateInitializer},
+    Error, HasMetadata,
+};
+use libafl_bolts::Named;
+
+use crate::{modules::ExecMeta, schedulers::PriorityMetadata};
+
+/// Gives the corpus entries reaching the target (see [`ExecMeta::reached_target`]) a [`PriorityMetadata`] of `weight`,
+/// so that they get more mutations. It never reports an input as interesting on its own.
+#[derive(Debug)]
+pub struct TargetReachFeedback {
+    weight: f64,
+    reached: bool,
+}
+
+impl TargetReachFeedback {
+    pub fn new(weight: f64) -> Self {
+        Self {
+            weight,
+            reached: false,
+        }
+    }
+}
+
+impl<EM, I, OT, S> Feedback<EM, I, OT, S> for TargetReachFeedback
+where
+    S: HasMetadata,
+{
+    fn is_interesting(
+        &mut self,
+        _state: &mut S,
+        _manager: &mut EM,
+        _input: &I,
+        _observers: &OT,
+        _exit_kind: &ExitKind,
+    ) -> Result<bool, Error> {
+        self.reached = _state
+            .metadata_map_mut()
+            .get_mut::<ExecMeta>()
+            .is_some_and(|exec_meta| mem::take(&mut exec_meta.reached_target));
+        Ok(false)
+    }
+
+    fn append_metadata(
+        &mut self,
+        _state: &mut S,
+        _manager: &mut EM,
+        _observers: &OT,
+        testcase: &mut Testcase<I>,
+    ) -> Result<(), Error> {
+        if mem::take(&mut self.reached) {
+            testcase.add_metadata(PriorityMetadata::new(self.weight));
+        }
+        Ok(())
+    }
+}
+
+impl<S> StateInitializer<S> for TargetReachFeedback {}
+
+impl Named for TargetReachFeedback {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("TargetReachFeedback");
+        &NAME
+    }
+}
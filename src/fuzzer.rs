@@ -1,14 +1,21 @@
+use core::time::Duration;
 use std::{
     cell::RefCell,
+    env,
     fs::{File, OpenOptions},
     io::{self, Write},
+    os::unix::process::CommandExt,
+    process::Command,
 };
 
 use clap::Parser;
 #[cfg(feature = "simplemgr")]
 use libafl::events::SimpleEventManager;
 #[cfg(not(feature = "simplemgr"))]
-use libafl::events::{EventConfig, Launcher, MonitorTypedEventManager};
+use libafl::{
+    events::{EventConfig, Launcher, ManagerKind, MonitorTypedEventManager, RestartingMgr, StdLlmpEventHook},
+    inputs::BytesInput,
+};
 use libafl::{
     events::{ClientDescription, LlmpEventManager, LlmpRestartingEventManager},
     monitors::{tui::TuiMonitor, Monitor, MultiMonitor},
@@ -20,13 +27,19 @@ use libafl_bolts::{
     shmem::{ShMemProvider, StdShMemProvider},
     staterestore::StateRestorer,
 };
+#[cfg(not(feature = "simplemgr"))]
+use nix::unistd::{dup2, fork, ForkResult};
 #[cfg(unix)]
 use {
+    nix::sys::personality::{self, Persona},
     nix::unistd::dup,
     std::os::unix::io::{AsRawFd, FromRawFd},
 };
 
-use crate::{client::Client, options::FuzzerOptions};
+use crate::{
+    client::Client, debug::DebugConfig, diff::CoverageDiff, health::HealthMonitor, merge::CorpusMerge,
+    options::FuzzerOptions, regress::Regression, selftest::SelfTest, triage::Triage,
+};
 use env_logger;
 
 pub struct Fuzzer {
@@ -45,15 +58,53 @@ impl Fuzzer {
         // println! will print log into stdout
         env_logger::init();
 
+        if self.options.no_aslr {
+            Self::reexec_without_aslr()?;
+        }
+
         log::info!("Starting fuzzer with options: {:?}", self.options);
 
+        if self.options.self_test.is_some() {
+            return SelfTest::new(&self.options).run();
+        }
+
+        if self.options.triage_dir.is_some() {
+            return Triage::new(&self.options).run();
+        }
+
+        if self.options.diff_binary.is_some() {
+            return CoverageDiff::new(&self.options).run();
+        }
+
+        if self.options.regress.is_some() {
+            return Regression::new(&self.options).run();
+        }
+
+        if !self.options.merge_corpora.is_empty() {
+            return CorpusMerge::new(&self.options).run();
+        }
+
+        if self.options.emit_debug_config.is_some() {
+            return DebugConfig::new(&self.options).run();
+        }
+
+        // Running once (or standing by) isn't a campaign, it must not overwrite the options of the one it comes from
+        if !self.options.runs_once() && !self.options.standby_broker {
+            let options_json = self.options.write_campaign_options()?;
+            log::info!("Wrote the campaign options to {options_json:?}");
+        }
+
         if self.options.tui {
             let monitor = TuiMonitor::builder()
                 .title("H1K0 QEMU Launcher")
                 .version("0.14.1")
                 .enhanced_graphics(true)
                 .build();
-            self.launch(monitor)
+            self.launch(HealthMonitor::new(
+                monitor,
+                self.options.min_exec_rate,
+                self.options.min_exec_rate_window,
+            ))
         } else {
             // TODO: config log file for both stdout and stderr
             // These logs are used by LLMPManager to write overall fuzzing logs, not client stdout/stderr
@@ -83,11 +134,134 @@ impl Fuzzer {
                     writeln!(log.borrow_mut(), "{:?} {}", current_time(), s).unwrap();
                 }
             });
-            self.launch(monitor)
+            self.launch(HealthMonitor::new(
+                monitor,
+                self.options.min_exec_rate,
+                self.options.min_exec_rate_window,
+            ))
         }
     }
 
-    fn launch<M>(&self, monitor: M) -> Result<(), Error>
+    /// qemu-user maps the guest (the target if it is PIE, its libraries, its heap and stack) wherever the host's mmap puts it,
+    /// so the host's randomization is turned off like `setarch -R` does, for this process and the clients it spawns.
+    /// The layout is chosen at exec, so the fuzzer restarts itself once with the `ADDR_NO_RANDOMIZE` personality.
+    fn reexec_without_aslr() -> Result<(), Error> {
+        let persona = personality::get()
+            .map_err(|e| Error::unknown(format!("Failed to get the personality: {e}")))?;
+        if persona.contains(Persona::ADDR_NO_RANDOMIZE) {
+            log::info!("ASLR is disabled");
+            return Ok(());
+        }
+        if let Err(e) = personality::set(persona | Persona::ADDR_NO_RANDOMIZE) {
+            // e.g. forbidden by a seccomp profile, the addresses stay random
+            log::warn!("Failed to disable ASLR, running with it: {e}");
+            return Ok(());
+        }
+
+        let exe = env::current_exe()?;
+        log::info!("Restarting {exe:?} with ASLR disabled");
+        let err = Command::new(exe).args(env::args_os().skip(1)).exec();
+        Err(Error::unknown(format!("Failed to restart without ASLR: {err}")))
+    }
+
+    /// The core of the single in-process client: with `--profile`, the first of `--cores`, so that
+    /// `--asan-cores`/`--cmplog-cores` pick its modules like they would for a launched client
+    fn profile_core(&self) -> CoreId {
+        // A benchmark is pinned like a profiled client, for stable numbers
+        if self.options.profile || self.options.bench.is_some() {
+            self.options.cores.ids.first().copied().unwrap_or(CoreId(0))
+        } else {
+            CoreId(0)
+        }
+    }
+
+    /// A broker on `--port` without clients of its own, for the clients of a campaign started with
+    /// `--standby-port` to fail over to when their broker dies. It relays and reports like the launcher's.
+    #[cfg(not(feature = "simplemgr"))]
+    fn run_standby_broker<M>(&self, shmem_provider: StdShMemProvider, monitor: HealthMonitor<M>) -> Result<(), Error>
+    where
+        M: Monitor + Clone,
+    {
+        let mut broker = LlmpBroker::create_attach_to_tcp(
+            shmem_provider,
+            tuple_list!(StdLlmpEventHook::<BytesInput, _>::new(monitor)?),
+            self.options.port,
+        )?;
+        println!(
+            "Standby broker listening on port {}, waiting for clients to fail over",
+            self.options.port
+        );
+        broker.loop_with_timeouts(Duration::from_secs(30), Some(Duration::from_millis(5)));
+        Ok(())
+    }
+
+    /// The clients of `--no-affinity`, forked like the launcher's but never pinned to their core: the launcher's
+    /// clients always pin themselves, which fails on hosts restricting the affinity (e.g. some containers).
+    /// The broker listens before the clients are forked, so that they connect to it rather than becoming one.
+    #[cfg(not(feature = "simplemgr"))]
+    fn launch_unpinned<M>(
+        &self,
+        client: &Client,
+        mut shmem_provider: StdShMemProvider,
+        monitor: HealthMonitor<M>,
+        stdout: Option<&str>,
+        stderr: Option<&str>,
+    ) -> Result<(), Error>
+    where
+        M: Monitor + Clone,
+    {
+        let mut broker = LlmpBroker::create_attach_to_tcp(
+            shmem_provider.clone(),
+            tuple_list!(StdLlmpEventHook::<BytesInput, _>::new(monitor)?),
+            self.options.port,
+        )?;
+
+        for (index, core_id) in self.options.cores.ids.iter().enumerate() {
+            shmem_provider.pre_fork()?;
+            match unsafe { fork() }? {
+                ForkResult::Parent { child } => {
+                    shmem_provider.post_fork(false)?;
+                    log::info!("Client {index} spawned for {core_id:?} as {child}, not pinned");
+                }
+                ForkResult::Child => {
+                    shmem_provider.post_fork(true)?;
+                    Self::redirect_output(stdout, io::stdout().as_raw_fd())?;
+                    Self::redirect_output(stderr, io::stderr().as_raw_fd())?;
+
+                    // Any kind is a client once the port is taken, and only the client kind pins
+                    let (state, mgr) = RestartingMgr::builder()
+                        .shmem_provider(shmem_provider.clone())
+                        .broker_port(self.options.port)
+                        .kind(ManagerKind::Any)
+                        .configuration(EventConfig::from_build_id())
+                        .monitor(None::<HealthMonitor<M>>)
+                        .hooks(tuple_list!())
+                        .build()
+                        .launch()?;
+                    return client.run(
+                        state,
+                        MonitorTypedEventManager::<_, HealthMonitor<M>>::new(mgr),
+                        ClientDescription::new(index, 0, *core_id),
+                    );
+                }
+            }
+        }
+
+        broker.loop_with_timeouts(Duration::from_secs(30), Some(Duration::from_millis(5)));
+        Ok(())
+    }
+
+    /// Point `fd` to the file at `path`, as the launcher does with the clients' output
+    #[cfg(not(feature = "simplemgr"))]
+    fn redirect_output(path: Option<&str>, fd: i32) -> Result<(), Error> {
+        if let Some(path) = path {
+            let file = OpenOptions::new().append(true).create(true).open(path)?;
+            dup2(file.as_raw_fd(), fd)?;
+        }
+        Ok(())
+    }
+
+    fn launch<M>(&self, monitor: HealthMonitor<M>) -> Result<(), Error>
     where
         M: Monitor + Clone,
     {
@@ -116,12 +290,19 @@ impl Fuzzer {
         #[cfg(not(feature = "simplemgr"))]
         log::info!("Client stderr: {:?}", stderr);
 
+        #[cfg(not(feature = "simplemgr"))]
+        if self.options.standby_broker {
+            return self.run_standby_broker(shmem_provider, monitor);
+        }
+
         let client = Client::new(&self.options);
 
         #[cfg(not(feature = "simplemgr"))]
-        if self.options.rerun_input.is_some() {
+        if self.options.runs_once() || self.options.profile {
             // If we want to rerun a single input but we use a restarting mgr, we'll have to create a fake restarting mgr that doesn't actually restart.
-            // It's not pretty but better than recompiling with simplemgr.
+            // It's not pretty but better than recompiling with simplemgr. The other one-shot modes run the same way,
+            // once, with their report on this process' stdout.
+            // A profiled client fuzzes behind the same fake mgr, in this process, so perf sees the fuzzer and not the launcher.
 
             // Just a random number, let's hope it's free :)
             let broker_port = 13120;
@@ -135,7 +316,7 @@ impl Fuzzer {
             // To rerun an input, instead of using a launcher, we create dummy parameters and run the client directly.
             return client.run(
                 None,
-                MonitorTypedEventManager::<_, M>::new(LlmpRestartingEventManager::new(
+                MonitorTypedEventManager::<_, HealthMonitor<M>>::new(LlmpRestartingEventManager::new(
                     LlmpEventManager::builder()
                         .build_on_port(
                             shmem_provider.clone(),
@@ -146,7 +327,7 @@ impl Fuzzer {
                         .unwrap(),
                     StateRestorer::new(shmem_provider.new_shmem(0x1000).unwrap()),
                 )),
-                ClientDescription::new(0, 0, CoreId(0)),
+                ClientDescription::new(0, 0, self.profile_core()),
             );
         }
 
@@ -154,9 +335,20 @@ impl Fuzzer {
         return client.run(
             None,
             SimpleEventManager::new(monitor),
-            ClientDescription::new(0, 0, CoreId(0)),
+            ClientDescription::new(0, 0, self.profile_core()),
         );
 
+        // The broker runs in this process, within the launcher
+        #[cfg(not(feature = "simplemgr"))]
+        if let Some(timeout) = self.options.broker_stall_timeout {
+            monitor.spawn_watchdog(timeout);
+        }
+
+        #[cfg(not(feature = "simplemgr"))]
+        if self.options.no_affinity {
+            return self.launch_unpinned(&client, shmem_provider, monitor, stdout, stderr);
+        }
+
         // Build and run the Launcher / fuzzer.
         #[cfg(not(feature = "simplemgr"))]
         match Launcher::builder()
@@ -164,7 +356,9 @@ impl Fuzzer {
             .broker_port(self.options.port)
             .configuration(EventConfig::from_build_id())
             .monitor(monitor)
-            .run_client(|s, m, c| client.run(s, MonitorTypedEventManager::<_, M>::new(m), c))
+            .run_client(|s, m, c| {
+                client.run(s, MonitorTypedEventManager::<_, HealthMonitor<M>>::new(m), c)
+            })
             .cores(&self.options.cores)
             .stdout_file(stdout)
             .stderr_file(stderr)
@@ -1,21 +1,54 @@
+use core::{cell::Cell, fmt::Debug, time::Duration};
 use libafl::{
     executors::ExitKind,
-    inputs::{BytesInput, HasTargetBytes},
-    Error,
+    inputs::{BytesInput, HasTargetBytes, Input},
+    Error, HasMetadata,
 };
-use libafl_bolts::AsSlice;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::{self, abort},
+    thread,
+};
+
+use libafl_bolts::{core_affinity::CoreId, AsSlice};
 use libafl_qemu::{
-    elf::EasyElf, ArchExtras, CallingConvention, GuestAddr, GuestReg, MmapPerms, Qemu, QemuExitReason, Regs
+    elf::EasyElf, ArchExtras, GuestAddr, GuestReg, MmapPerms, Qemu, QemuExitReason, Regs
 };
+use serde::Serialize;
+use strum::IntoEnumIterator;
+
+use crate::{
+    modules::{ExecMeta, RegInput},
+    options::{FuzzerOptions, MappingsFormat},
+};
+
+/// A guest mapping, as printed by `--print-mappings`
+#[derive(Debug, Serialize)]
+struct GuestMapping {
+    start: GuestAddr,
+    end: GuestAddr,
+    perms: String,
+    path: String,
+}
 
 pub struct Harness {
     qemu: Qemu,
     pub input_addr: GuestAddr,
     abort_addr: GuestAddr,
+    // The runs in a row that failed in qemu itself
+    qemu_errors: Cell<u32>,
+    // The target's own TMPDIR, emptied before every run, see `--isolate-tmp`
+    tmp_dir: Option<PathBuf>,
 }
 
 pub const MAX_INPUT_SIZE: usize = 1_048_576; // 1MB
 
+/// After this many runs in a row failing in qemu itself, the client restarts with a fresh emulator
+const MAX_QEMU_ERRORS: u32 = 16;
+/// From this many runs in a row failing in qemu on, each failure is followed by a growing pause
+const QEMU_ERROR_BACKOFF: u32 = 4;
+
 impl Harness {
     /// Change environment
     #[inline]
@@ -33,12 +66,113 @@ impl Harness {
             .map_err(|e| Error::unknown(format!("Failed to read memory@{addr:#x}: {e:?}")))
     }
 
-    /// Initialize the emulator, run to the entrypoint (or jump there) and return the [`Harness`] struct
-    pub fn init(qemu: Qemu) -> Result<Harness, Error> {
+    /// Resolve the first of `symbols` found in the target, `what` being the harness entry or exit
+    fn resolve_entry(
+        elf: &EasyElf,
+        load_addr: GuestAddr,
+        symbols: &[String],
+        what: &str,
+    ) -> Result<GuestAddr, Error> {
+        for symbol in symbols {
+            if let Some(addr) = elf.resolve_symbol(symbol, load_addr) {
+                log::info!("Using harness {what} {symbol} @ {addr:#x}");
+                return Ok(addr);
+            }
+            log::debug!("Harness {what} {symbol} not found");
+        }
+        Err(Error::empty_optional(format!(
+            "None of the harness {what} symbols were found: {}",
+            symbols.join(", ")
+        )))
+    }
+
+    /// The register names of the guest architecture, lowercase, plus `pc` and `sp`
+    fn reg_names() -> Vec<(String, Regs)> {
+        Regs::iter()
+            .map(|reg| (format!("{reg:?}").to_lowercase(), reg))
+            .chain([("pc".to_string(), Regs::Pc), ("sp".to_string(), Regs::Sp)])
+            .collect()
+    }
+
+    /// Parse a `--reg-input` mapping `REG:OFFSET:SIZE`, the register name as in [`Harness::parse_init_regs`],
+    /// the offset and the size (1 to the register width) in bytes, decimal or hexadecimal with a `0x` prefix
+    pub fn parse_reg_input(src: &str) -> Result<RegInput, Error> {
+        let invalid = |what: String| Error::illegal_argument(format!("Invalid register input {src}: {what}"));
+        let parse = |value: &str| match value.strip_prefix("0x") {
+            Some(hex) => usize::from_str_radix(hex, 16),
+            None => value.parse(),
+        };
+
+        let [name, offset, size] = src.split(':').collect::<Vec<&str>>()[..] else {
+            return Err(invalid("expected REG:OFFSET:SIZE".to_string()));
+        };
+        let name = name.trim().to_lowercase();
+        let names = Self::reg_names();
+        let reg = names
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, reg)| *reg)
+            .ok_or_else(|| {
+                let valid = names.iter().map(|(n, _)| n.as_str()).collect::<Vec<&str>>();
+                invalid(format!("unknown register {name}, expected one of {}", valid.join(", ")))
+            })?;
+        let offset = parse(offset.trim()).map_err(|e| invalid(format!("invalid offset: {e}")))?;
+        let size = parse(size.trim()).map_err(|e| invalid(format!("invalid size: {e}")))?;
+        if size == 0 || size > size_of::<GuestReg>() {
+            return Err(invalid(format!(
+                "the size must be between 1 and {} bytes",
+                size_of::<GuestReg>()
+            )));
+        }
+        Ok(RegInput { reg, offset, size })
+    }
+
+    /// Parse an `--init-regs` file of `REG=VALUE` lines, `#` starts a comment.
+    /// Register names are the ones of the guest architecture (case insensitive, plus `pc` and `sp`),
+    /// values are decimal or hexadecimal with a `0x` prefix.
+    pub fn parse_init_regs(path: &Path) -> Result<Vec<(Regs, GuestReg)>, Error> {
+        let names = Self::reg_names();
+
+        let content = fs::read_to_string(path)?;
+        let mut regs = Vec::new();
+        for (i, line) in content.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let invalid = |what: String| {
+                Error::illegal_argument(format!("{}:{}: {what}", path.display(), i + 1))
+            };
+
+            let (name, value) = line
+                .split_once('=')
+                .ok_or_else(|| invalid(format!("expected REG=VALUE, got {line}")))?;
+            let (name, value) = (name.trim().to_lowercase(), value.trim());
+            let reg = names
+                .iter()
+                .find(|(n, _)| *n == name)
+                .map(|(_, reg)| *reg)
+                .ok_or_else(|| {
+                    let valid = names.iter().map(|(n, _)| n.as_str()).collect::<Vec<&str>>();
+                    invalid(format!("unknown register {name}, expected one of {}", valid.join(", ")))
+                })?;
+            let value = match value.strip_prefix("0x") {
+                Some(hex) => GuestReg::from_str_radix(hex, 16),
+                None => value.parse(),
+            }
+            .map_err(|e| invalid(format!("invalid value {value} for {name}: {e}")))?;
+            regs.push((reg, value));
+        }
+        Ok(regs)
+    }
+
+    /// Initialize the emulator, run to the entrypoint (or jump there) and return the [`Harness`] struct.
+    /// The target runs on `core_id`, whose `TMPDIR` the client set in the guest environment with `--isolate-tmp`.
+    pub fn init(qemu: Qemu, options: &FuzzerOptions, core_id: CoreId) -> Result<Harness, Error> {
         println!("Initializing harness ...");
 
         let mut elf_buffer = Vec::new();
-        let elf = EasyElf::from_file(qemu.binary_path(), &mut elf_buffer)?;
+        let elf = EasyElf::from_file(options.target_path(qemu), &mut elf_buffer)?;
 
         let load_addr = qemu.load_addr();
         println!("load_addr = {load_addr:#x}");
@@ -46,9 +180,13 @@ impl Harness {
         // AArch64 ...
         #[cfg(feature = "aarch64")]
         let (tiff_cleanup_addr, start_pc, end_pc) = {
+            // Only the bundled tiffinfo has it, not e.g. the `--self-test` target
             let tiff_cleanup_addr = elf
                 .resolve_symbol("TIFFCleanup", qemu.load_addr())
-                .ok_or_else(|| Error::empty_optional("Symbol TIFFCleanup not found"))?;
+                .unwrap_or_else(|| {
+                    log::debug!("Symbol TIFFCleanup not found");
+                    0
+                });
             let start_pc = load_addr + 0x3634;
             let end_pc = load_addr + 0x3738;
             (tiff_cleanup_addr, start_pc, end_pc)
@@ -61,6 +199,18 @@ impl Harness {
             (0, start_pc, end_pc)
         };
 
+        let start_pc = if options.harness_entry.is_empty() {
+            start_pc
+        } else {
+            Self::resolve_entry(&elf, load_addr, &options.harness_entry, "entry")?
+        };
+        // Every run ends at the first instruction of the exit function
+        let end_pc = if options.harness_exit.is_empty() {
+            end_pc
+        } else {
+            Self::resolve_entry(&elf, load_addr, &options.harness_exit, "exit")?
+        };
+
         println!("start_pc @ {start_pc:#x}");
         println!("end_pc @ {end_pc:#x}");
 
@@ -78,11 +228,36 @@ impl Harness {
                         .map_err(|e| Error::unknown(format!("Failed to read PC: {e:?}")))?;
                     println!("PC = {pc:#x}");
                 }
-                _ => panic!("Unexpected QEMU exit."),
+                other => {
+                    return Err(Error::illegal_state(format!(
+                        "Unexpected QEMU exit while running to start_pc {start_pc:#x}: {other:?}"
+                    )))
+                }
             }
         }
         qemu.remove_breakpoint(start_pc);
 
+        let setup_addr = match (&options.setup_symbol, options.setup_addr) {
+            (Some(symbol), _) => Some(
+                elf.resolve_symbol(symbol, load_addr)
+                    .ok_or_else(|| Error::empty_optional(format!("Setup symbol {symbol} not found")))?,
+            ),
+            (None, Some(offset)) => Some(load_addr + offset),
+            (None, None) => None,
+        };
+        if let Some(setup_addr) = setup_addr {
+            Self::call_setup(qemu, setup_addr, start_pc)?;
+        }
+
+        // Every run starts from here, as the register reset module saves the registers after init
+        if let Some(path) = &options.init_regs {
+            for (reg, value) in Self::parse_init_regs(path)? {
+                log::info!("Setting {reg:?} = {value:#x}");
+                qemu.write_reg(reg, value)
+                    .map_err(|e| Error::unknown(format!("Failed to write {reg:?}: {e:?}")))?;
+            }
+        }
+
         let input_addr = qemu
             .map_private(0, MAX_INPUT_SIZE, MmapPerms::ReadWrite)
             .map_err(|e| Error::unknown(format!("Failed to map input buffer: {e:}")))?;
@@ -90,12 +265,101 @@ impl Harness {
         println!("Harness initialized");
 
         // All libraries are loaded only after the qemu.run() is called, or only the ld-linux.so is loaded
-        // let mappings = qemu.mappings();
-        // for mapping in mappings {
-        //     log::info!("{:?}", mapping);
-        // }
+        if options.print_mappings {
+            Self::print_mappings(qemu, options.format)?;
+            process::exit(0);
+        }
+
+        Ok(Harness {
+            qemu,
+            input_addr,
+            abort_addr: tiff_cleanup_addr,
+            qemu_errors: Cell::new(0),
+            tmp_dir: options.isolate_tmp.then(|| options.tmp_dir(core_id)),
+        })
+    }
+
+    /// Call the setup function at `addr` once, returning to `start_pc`, see `--setup-symbol`.
+    /// Its effects on memory stay, the registers are restored to their values at the harness entry.
+    fn call_setup(qemu: Qemu, addr: GuestAddr, start_pc: GuestAddr) -> Result<(), Error> {
+        let read_err = |e| Error::unknown(format!("Failed to read the registers: {e:?}"));
+        let write_err = |e| Error::unknown(format!("Failed to set up the call to {addr:#x}: {e:?}"));
+
+        let saved = Regs::iter()
+            .map(|reg| Ok((reg, qemu.read_reg(reg).map_err(read_err)?)))
+            .collect::<Result<Vec<(Regs, GuestReg)>, Error>>()?;
+
+        // Below the harness entry's frame, aligned as at a call site
+        let sp: GuestReg = qemu.read_reg(Regs::Sp).map_err(read_err)?;
+        let sp = (sp - 0x100) & !0xf;
+        // The return address is pushed on x86, the other architectures take it in a register
+        #[cfg(any(feature = "x86_64", feature = "i386"))]
+        let sp = sp - size_of::<GuestReg>() as GuestReg;
+        qemu.write_reg(Regs::Sp, sp).map_err(write_err)?;
+        qemu.write_return_address(start_pc).map_err(write_err)?;
+        qemu.write_reg(Regs::Pc, addr).map_err(write_err)?;
+
+        println!("Calling setup @ {addr:#x}");
+        qemu.set_breakpoint(start_pc);
+        let result = unsafe { qemu.run() };
+        qemu.remove_breakpoint(start_pc);
+        match result {
+            Ok(QemuExitReason::Breakpoint(_)) => println!("Setup returned"),
+            other => {
+                return Err(Error::illegal_state(format!(
+                    "Unexpected QEMU exit while running the setup @ {addr:#x}: {other:?}"
+                )))
+            }
+        }
 
-        Ok(Harness { qemu, input_addr, abort_addr: tiff_cleanup_addr })
+        for (reg, value) in saved {
+            qemu.write_reg(reg, value)
+                .map_err(|e| Error::unknown(format!("Failed to restore {reg:?}: {e:?}")))?;
+        }
+        Ok(())
+    }
+
+    /// Print every guest mapping, to pick `--include`/`--exclude` ranges, see `--print-mappings`
+    fn print_mappings(qemu: Qemu, format: MappingsFormat) -> Result<(), Error> {
+        let mappings = qemu
+            .mappings()
+            .map(|mapping| {
+                let perms = mapping.flags();
+                GuestMapping {
+                    start: mapping.start(),
+                    end: mapping.end(),
+                    perms: format!(
+                        "{}{}{}",
+                        if perms.readable() { 'r' } else { '-' },
+                        if perms.writable() { 'w' } else { '-' },
+                        if perms.executable() { 'x' } else { '-' },
+                    ),
+                    path: mapping.path().cloned().unwrap_or_default(),
+                }
+            })
+            .collect::<Vec<GuestMapping>>();
+
+        match format {
+            MappingsFormat::Table => {
+                println!("{:<18} {:<18} {:>10} {:<5} path", "start", "end", "size", "perms");
+                for mapping in &mappings {
+                    println!(
+                        "{:<#18x} {:<#18x} {:>#10x} {:<5} {}",
+                        mapping.start,
+                        mapping.end,
+                        mapping.end - mapping.start,
+                        mapping.perms,
+                        mapping.path
+                    );
+                }
+            }
+            MappingsFormat::Json => {
+                let json = serde_json::to_string_pretty(&mappings)
+                    .map_err(|e| Error::serialize(format!("Failed to serialize the mappings: {e}")))?;
+                println!("{json}");
+            }
+        }
+        Ok(())
     }
 
     /// If we need to do extra work after forking, we can do that here.
@@ -104,9 +368,13 @@ impl Harness {
     pub fn post_fork(&self) {}
 
     // We didn't do much here, because input has been injected by Custom EmulatorModules
-    pub fn run(&self, _qemu: Qemu) -> ExitKind {
+    pub fn run<S: HasMetadata>(&self, _qemu: Qemu, state: &mut S, input: &BytesInput) -> ExitKind {
         println!("Harness Start running");
 
+        if let Some(tmp_dir) = &self.tmp_dir {
+            Self::clear_dir(tmp_dir);
+        }
+
         unsafe {
             match _qemu.run() {
                 // It seems that the control will back after the inst at breakpoint addr is executed
@@ -117,41 +385,59 @@ impl Harness {
                         .expect("Failed to read PC");
                     println!("PC = {pc:#x}");
                 }
-                _ => panic!("Unexpected QEMU exit."),
+                Err(e) => return self.on_qemu_error(state, input, &e),
+                // Any other exit (e.g. the end of the guest, a sync exit) didn't go through the harness end either
+                Ok(reason) => return self.on_qemu_error(state, input, &reason),
             }
         }
+        self.qemu_errors.set(0);
         
         ExitKind::Ok
     }
 
-    // No need to call reset here because the target will crash at first run.
-    fn reset(&self, input: &BytesInput) -> Result<(), Error> {
-        let target = input.target_bytes();
-        let mut buf = target.as_slice();
-        let mut len = buf.len();
-        if len > MAX_INPUT_SIZE {
-            buf = &buf[0..MAX_INPUT_SIZE];
-            len = MAX_INPUT_SIZE;
+    /// Remove what the previous runs left in `dir`, so that no run sees the files of another
+    fn clear_dir(dir: &Path) {
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(e) => {
+                log::warn!("Failed to read the temp directory {dir:?}: {e}");
+                return;
+            }
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let removed = match entry.file_type() {
+                Ok(file_type) if file_type.is_dir() => fs::remove_dir_all(&path),
+                _ => fs::remove_file(&path),
+            };
+            if let Err(e) = removed {
+                log::warn!("Failed to remove {path:?} from the temp directory: {e}");
+            }
         }
-        let len = len as GuestReg;
+    }
 
-        self.qemu.write_mem(self.input_addr, buf).map_err(|e| {
-            Error::unknown(format!(
-                "Failed to write to memory@{:#x}: {e:?}",
-                self.input_addr
-            ))
-        })?;
+    /// A run failed in qemu itself (e.g. a bad translation), not in the target: it's discarded rather than
+    /// reported as a crash. If it keeps happening, back off and eventually restart the client, and with it qemu.
+    fn on_qemu_error<S: HasMetadata>(&self, state: &mut S, input: &BytesInput, error: &impl Debug) -> ExitKind {
+        let errors = self.qemu_errors.get() + 1;
+        self.qemu_errors.set(errors);
+        log::error!(
+            "qemu failed to run input {} ({} bytes, {errors} in a row): {error:?}",
+            input.generate_name(None),
+            input.target_bytes().len()
+        );
 
-        self.qemu
-            .write_function_argument(CallingConvention::Cdecl, 0, self.input_addr)
-            .map_err(|e| Error::unknown(format!("Failed to write argument 0: {e:?}")))?;
+        // Neither a corpus entry nor a solution
+        state.metadata_or_insert_with(ExecMeta::new).ignore = true;
 
-        self.qemu
-            .write_function_argument(CallingConvention::Cdecl, 1, len)
-            .map_err(|e| Error::unknown(format!("Failed to write argument 1: {e:?}")))?;
-        unsafe {
-            let _ = self.qemu.run();
-        };
-        Ok(())
+        if errors >= MAX_QEMU_ERRORS {
+            log::error!("qemu failed {errors} runs in a row, restarting the client");
+            // The crash handler saves the state and the client is respawned, the run is ignored as above
+            abort();
+        }
+        if errors >= QEMU_ERROR_BACKOFF {
+            thread::sleep(Duration::from_millis(50 << (errors - QEMU_ERROR_BACKOFF).min(5)));
+        }
+        ExitKind::Ok
     }
 }
@@ -0,0 +1,140 @@
+use core::time::Duration;
+use std::{
+    fs, process,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    thread,
+};
+
+use libafl::monitors::{ClientStats, Monitor};
+use libafl_bolts::{current_time, ClientId};
+
+/// How often the broker watchdog checks the last activity
+const WATCHDOG_PERIOD: Duration = Duration::from_secs(5);
+
+/// The resident set size of this process in bytes, `None` if `/proc` can't tell
+pub fn resident_memory() -> Option<u64> {
+    let status = fs::read_to_string("/proc/self/status").ok()?;
+    let kb = status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmRSS:"))?
+        .trim()
+        .strip_suffix("kB")?
+        .trim()
+        .parse::<u64>()
+        .ok()?;
+    Some(kb * 1024)
+}
+
+/// Wraps the broker's monitor and warns when the global exec/sec stays below `min_rate` for `window`,
+/// e.g. because of corpus bloat or a pathological input. Without `min_rate` it only forwards to the inner monitor.
+///
+/// It also records when the broker last relayed a message, see [`HealthMonitor::spawn_watchdog`].
+#[derive(Debug, Clone)]
+pub struct HealthMonitor<M> {
+    inner: M,
+    min_rate: Option<f64>,
+    window: Duration,
+    // Since when the rate has been too low, if it is
+    low_since: Option<Duration>,
+    // Seconds timestamp of the last message, shared by all the clones of the monitor
+    last_activity: Arc<AtomicU64>,
+}
+
+impl<M> HealthMonitor<M> {
+    pub fn new(inner: M, min_rate: Option<f64>, window: Duration) -> Self {
+        Self {
+            inner,
+            min_rate,
+            window,
+            low_since: None,
+            last_activity: Arc::new(AtomicU64::new(current_time().as_secs())),
+        }
+    }
+
+    /// Exit with a diagnostic if the broker doesn't relay any message for `timeout`.
+    /// Clients report their stats periodically, so a silent broker is a stalled one,
+    /// better an error than a campaign hanging forever.
+    pub fn spawn_watchdog(&self, timeout: Duration) {
+        let last_activity = self.last_activity.clone();
+        thread::spawn(move || loop {
+            thread::sleep(WATCHDOG_PERIOD);
+            let last = Duration::from_secs(last_activity.load(Ordering::Relaxed));
+            let silent = current_time().saturating_sub(last);
+            if silent >= timeout {
+                log::error!(
+                    "The broker relayed no message for {silent:?} (timeout {timeout:?}), it looks stalled. \
+                     Check that the clients are alive and that the broker port is not shared with another campaign."
+                );
+                eprintln!("Broker stalled for {silent:?}, exiting");
+                process::exit(1);
+            }
+        });
+    }
+}
+
+impl<M> HealthMonitor<M>
+where
+    M: Monitor,
+{
+    fn check(&mut self) {
+        let Some(min_rate) = self.min_rate else {
+            return;
+        };
+        // Nothing ran yet, the clients are still starting up
+        if self.inner.total_execs() == 0 {
+            return;
+        }
+
+        let rate = self.inner.execs_per_sec();
+        if rate >= min_rate {
+            self.low_since = None;
+            return;
+        }
+
+        let now = current_time();
+        let low_since = *self.low_since.get_or_insert(now);
+        if now.saturating_sub(low_since) >= self.window {
+            log::warn!(
+                "Exec rate {rate:.1}/sec below {min_rate}/sec for {:?}, check the corpus for slow inputs",
+                now.saturating_sub(low_since)
+            );
+            // Warn again after another window, not at every update
+            self.low_since = Some(now);
+        }
+    }
+}
+
+impl<M> Monitor for HealthMonitor<M>
+where
+    M: Monitor,
+{
+    fn client_stats_mut(&mut self) -> &mut Vec<ClientStats> {
+        self.inner.client_stats_mut()
+    }
+
+    fn client_stats(&self) -> &[ClientStats] {
+        self.inner.client_stats()
+    }
+
+    fn start_time(&self) -> Duration {
+        self.inner.start_time()
+    }
+
+    fn set_start_time(&mut self, time: Duration) {
+        self.inner.set_start_time(time);
+    }
+
+    fn aggregate(&mut self, name: &str) {
+        self.inner.aggregate(name);
+    }
+
+    fn display(&mut self, event_msg: &str, sender_id: ClientId) {
+        self.last_activity
+            .store(current_time().as_secs(), Ordering::Relaxed);
+        self.inner.display(event_msg, sender_id);
+        self.check();
+    }
+}
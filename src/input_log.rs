@@ -0,0 +1,50 @@
+use std::{fs, path::PathBuf};
+
+use libafl::Error;
+use libafl_bolts::core_affinity::CoreId;
+
+/// Writes the first inputs a client executes, whatever becomes of them, see `--log-all-inputs`.
+/// The files are named `core_<core>_<n>`, `n` counting the executions from 0.
+#[derive(Debug)]
+pub struct InputLog {
+    dir: PathBuf,
+    core_id: CoreId,
+    limit: usize,
+    logged: usize,
+}
+
+impl InputLog {
+    /// Log up to `limit` inputs in `dir`. The inputs a previous run of the client (e.g. before a restart)
+    /// already logged there count towards the limit, so the directory never holds more than `limit` of them.
+    pub fn new(dir: PathBuf, core_id: CoreId, limit: usize) -> Result<Self, Error> {
+        fs::create_dir_all(&dir)?;
+        let prefix = format!("core_{:03}_", core_id.0);
+        let logged = fs::read_dir(&dir)?
+            .filter_map(Result::ok)
+            .filter(|entry| entry.file_name().to_string_lossy().starts_with(&prefix))
+            .count();
+        Ok(Self {
+            dir,
+            core_id,
+            limit,
+            logged,
+        })
+    }
+
+    /// Write `input` unless the limit is reached
+    pub fn log(&mut self, input: &[u8]) {
+        if self.logged >= self.limit {
+            return;
+        }
+        let path = self
+            .dir
+            .join(format!("core_{:03}_{:06}", self.core_id.0, self.logged));
+        if let Err(e) = fs::write(&path, input) {
+            log::warn!("Failed to log the input to {}: {e}", path.display());
+        }
+        self.logged += 1;
+        if self.logged == self.limit {
+            log::info!("Logged {} inputs to {}, not logging any more", self.limit, self.dir.display());
+        }
+    }
+}
@@ -1,38 +1,95 @@
-use core::fmt::Debug;
-use std::{fs, marker::PhantomData, ops::Range, path::PathBuf, process};
+use core::{fmt::Debug, mem, slice, time::Duration};
+use std::{
+    borrow::Cow,
+    fs,
+    io::{self, Read},
+    marker::PhantomData,
+    ops::Range,
+    path::{Path, PathBuf},
+    process, thread,
+};
 
 #[cfg(feature = "simplemgr")]
 use libafl::events::SimpleEventManager;
 #[cfg(not(feature = "simplemgr"))]
-use libafl::events::{LlmpRestartingEventManager, MonitorTypedEventManager};
+use libafl::events::{
+    EventConfig, LlmpEventManager, LlmpRestartingEventManager, MonitorTypedEventManager, _ENV_FUZZER_SENDER,
+};
+#[cfg(not(feature = "simplemgr"))]
+use libafl_bolts::staterestorer::StateRestorer;
 use libafl::{
-    corpus::{Corpus, InMemoryOnDiskCorpus, OnDiskCorpus}, events::{ClientDescription, EventRestarter, NopEventManager}, executors::{Executor, ShadowExecutor}, feedback_and_fast, feedback_or, feedback_or_fast, feedbacks::{BoolValueFeedback, CrashFeedback, MaxMapFeedback, TimeFeedback, TimeoutFeedback}, fuzzer::{Evaluator, Fuzzer, StdFuzzer}, inputs::BytesInput, monitors::Monitor, mutators::{
+    corpus::{Corpus, CorpusId, InMemoryOnDiskCorpus, OnDiskCorpus, OnDiskMetadataFormat}, events::{ClientDescription, Event, EventFirer, EventRestarter, NopEventManager, ProgressReporter}, executors::{inprocess::HasInProcessHooks, Executor, HasObservers, ShadowExecutor}, feedback_and_fast, feedback_or, feedback_or_fast, feedbacks::{
+        BoolValueFeedback, ConstFeedback, CrashFeedback, Feedback, MaxMapFeedback,
+        TimeFeedback, TimeoutFeedback,
+    }, fuzzer::{Evaluator, ExecutesInput, Fuzzer, HasFeedback, HasObjective, StdFuzzer, STATS_TIMEOUT_DEFAULT}, inputs::{BytesInput, HasTargetBytes}, monitors::{AggregatorOps, Monitor, UserStats, UserStatsValue}, mutators::{
         havoc_mutations, token_mutations::I2SRandReplace, tokens_mutations, StdMOptMutator,
         StdScheduledMutator, Tokens,
-    }, observers::{CanTrack, HitcountsMapObserver, TimeObserver, VariableMapObserver}, schedulers::{
-        powersched::PowerSchedule, IndexesLenTimeMinimizerScheduler, PowerQueueScheduler,
+    }, observers::{
+        CanTrack, ExplicitTracking, HitcountsMapObserver, MapObserver, StdMapObserver,
+        TimeObserver, VariableMapObserver,
+    }, schedulers::{
+        powersched::PowerSchedule, IndexesLenTimeMinimizerScheduler, PowerQueueScheduler, RemovableScheduler,
     }, stages::{
-        calibrate::CalibrationStage, power::StdPowerMutationalStage, AflStatsStage, IfStage,
+        calibrate::CalibrationStage, power::PowerMutationalStage, AflStatsStage, IfStage,
         ShadowTracingStage, StagesTuple, StdMutationalStage,
-    }, state::{HasCorpus, StdState}, Error, HasMetadata
+    }, state::{HasCorpus, HasExecutions, HasMaxSize, HasRand, HasStartTime, StdState}, Error, HasMetadata, HasScheduler
 };
-#[cfg(not(feature = "simplemgr"))]
-use libafl_bolts::shmem::StdShMemProvider;
 use libafl_bolts::{
-    core_affinity::CoreId, ownedref::OwnedMutSlice, rands::StdRand, tuples::{tuple_list, Merge, Prepend}
+    core_affinity::CoreId,
+    current_nanos, current_time,
+    ownedref::OwnedMutSlice,
+    rands::StdRand,
+    shmem::{ShMemProvider, StdShMemProvider},
+    tuples::{tuple_list, Handle, Handled, Map, MatchNameRef, Merge, Prepend},
 };
 use libafl_qemu::{
     elf::EasyElf,
     modules::{
-        cmplog::CmpLogObserver, edges::EdgeCoverageFullVariant, utils::filters::{NopPageFilter, StdAddressFilter}, AsanModule, EdgeCoverageModule, EmulatorModule, EmulatorModuleTuple, SnapshotModule, StdEdgeCoverageModule
+        cmplog::CmpLogObserver, utils::filters::StdAddressFilter, AsanModule, EmulatorModule,
+        EmulatorModuleTuple, SnapshotModule, StdEdgeCoverageChildModule, StdEdgeCoverageModule,
     },
-    Emulator, GuestAddr, Qemu, QemuExecutor,
+    Emulator, GuestAddr, Qemu, QemuExecutor, QemuForkExecutor,
 };
 use libafl_targets::{edges_map_mut_ptr, EDGES_MAP_DEFAULT_SIZE, MAX_EDGES_FOUND};
 use typed_builder::TypedBuilder;
 
 use crate::{
-    feedbacks::ignore_exit::IgnoreExitFeedback, harness::Harness, modules::{InputInjectorModule, RegisterResetModule}, options::FuzzerOptions
+    attribution::AttributionReport,
+    checkpoint::Checkpointer,
+    coverage::{count_blocks, covered_edges, covered_edges_per_slice, dump_coverage, dump_edges, write_lcov},
+    executors::ScaledTimeoutExecutor,
+    failover::{BrokerFailover, BrokerProbe},
+    feedbacks::{
+        collisions::CollisionFeedback, crash_info::CrashInfoFeedback, exit_code::ExitCodeFeedback, finding::FindingFeedback,
+        ignore_exit::IgnoreExitFeedback, mirror::SolutionMirrorFeedback,
+        new_edges::NewEdgesLogFeedback, notify::EventSinkFeedback, output::OutputMatchFeedback, presence::EdgePresenceFeedback,
+        provenance::ProvenanceFeedback,
+        reproducer::ReproducerFeedback,
+        target_reach::TargetReachFeedback,
+    },
+    harness::{Harness, MAX_INPUT_SIZE},
+    health::resident_memory,
+    input_log::InputLog,
+    modules::{
+        determinism::add_determinism_handlers, BlockCoverage, BlockCoverageModule, BlockTraceModule, CollisionSampleModule,
+        CollisionStats, CoverageRegion, CrashClassifierModule, CrashInfo, DataResetModule,
+        EdgeMaskModule, FindingDetectorModule, HeapCheckModule, HeapFn, InjectionTime, InputInjectorModule,
+        LibCoverageModule, OutputCaptureModule, RegisterResetModule, SplitCoverageModule, SymbolTarget, SyscallStats,
+        SyscallStatsModule, TargetReachModule, SPLIT_MAP_SIZE,
+    },
+    mutators::{
+        InputPredicate, MaxGrowMutator, MutationLog, MutationLogMutator, PredicateMutator, ProvenanceMutator,
+        TrailMapper,
+    },
+    notify::{CommandSink, CrashReportSink, EventKind, EventSink},
+    options::{CoverageMode, ExecutorKind, FuzzerOptions},
+    schedulers::{FifoScheduler, PriorityTestcaseScore},
+    schema::Schema,
+    script::Script,
+    seeds::{greedy_order, prefetch, ImportScanMetadata, SeenSeeds},
+    stability::StabilityReport,
+    stages::{AutoTrimStage, CorpusEvictionStage, RecalibrationStage, SingleCalibrationStage},
+    triage::Triage,
 };
 
 pub type ClientState =
@@ -46,6 +103,47 @@ pub type ClientMgr<M> = MonitorTypedEventManager<
     M,
 >;
 
+/// One run out of this many is sampled by `--coverage-diagnostics`
+const COLLISION_SAMPLE_INTERVAL: u64 = 64;
+/// Above this estimated collision rate, `--coverage-diagnostics` recommends shrinking the instrumented code
+const HIGH_COLLISION_RATE: f64 = 0.05;
+
+/// How often the client reads the `--control-file`, also while paused
+const CONTROL_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How often the client checks its resident memory against `--mem-limit`
+const MEM_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// What the `--control-file` asks the clients to do
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Control {
+    Run,
+    Pause,
+    Stop,
+}
+
+impl Control {
+    /// The command in the file at `path`, running if it's missing, empty or unknown
+    fn read(path: &Path) -> Self {
+        let Ok(content) = fs::read_to_string(path) else {
+            return Self::Run;
+        };
+        match content.trim().to_lowercase().as_str() {
+            "pause" => Self::Pause,
+            "stop" => Self::Stop,
+            "" | "resume" => Self::Run,
+            other => {
+                log::warn!("Unknown command {other:?} in the control file {path:?}, expected pause, resume or stop");
+                Self::Run
+            }
+        }
+    }
+}
+
+/// The coverage observer, over the edges map
+type EdgesObserver =
+    ExplicitTracking<HitcountsMapObserver<VariableMapObserver<'static, u8>>, true, false>;
+
 #[derive(TypedBuilder)]
 pub struct Instance<'a, M: Monitor> {
     options: &'a FuzzerOptions,
@@ -54,6 +152,12 @@ pub struct Instance<'a, M: Monitor> {
     client_description: ClientDescription,
     #[builder(default)]
     extra_tokens: Vec<String>,
+    /// The estimated number of basic blocks in the coverage region, see `--coverage-estimate`
+    #[builder(default)]
+    total_blocks: Option<usize>,
+    /// The names of the `--split-coverage` regions, in map order
+    #[builder(default)]
+    split_regions: Vec<String>,
     #[builder(default=PhantomData)]
     phantom: PhantomData<M>,
 }
@@ -61,46 +165,219 @@ pub struct Instance<'a, M: Monitor> {
 impl<M: Monitor> Instance<'_, M> {
     fn coverage_filter(&self, qemu: Qemu) -> Result<StdAddressFilter, Error> {
         /* Conversion is required on 32-bit targets, but not on 64-bit ones */
-        if let Some(includes) = &self.options.include {
+        if let Some(excludes) = &self.options.exclude {
             #[cfg_attr(target_pointer_width = "64", allow(clippy::useless_conversion))]
-            let rules = includes
+            let rules = excludes
                 .iter()
                 .map(|x| Range {
                     start: x.start.into(),
                     end: x.end.into(),
                 })
                 .collect::<Vec<Range<GuestAddr>>>();
-            Ok(StdAddressFilter::allow_list(rules))
-        } else if let Some(excludes) = &self.options.exclude {
+            Ok(StdAddressFilter::deny_list(rules))
+        } else {
+            Ok(StdAddressFilter::allow_list(self.coverage_ranges(qemu)?))
+        }
+    }
+
+    /// The code ranges covered by an allow list: the `--include` ranges, or the target's `.text`
+    fn coverage_ranges(&self, qemu: Qemu) -> Result<Vec<Range<GuestAddr>>, Error> {
+        if let Some(includes) = &self.options.include {
             #[cfg_attr(target_pointer_width = "64", allow(clippy::useless_conversion))]
-            let rules = excludes
+            let rules = includes
                 .iter()
                 .map(|x| Range {
                     start: x.start.into(),
                     end: x.end.into(),
                 })
                 .collect::<Vec<Range<GuestAddr>>>();
-            Ok(StdAddressFilter::deny_list(rules))
+            Ok(rules)
         } else {
             let mut elf_buffer = Vec::new();
-            let elf = EasyElf::from_file(qemu.binary_path(), &mut elf_buffer)?;
+            let elf = EasyElf::from_file(self.options.target_path(qemu), &mut elf_buffer)?;
             let range = elf
                 .get_section(".text", qemu.load_addr())
                 .ok_or_else(|| Error::key_not_found("Failed to find .text section"))?;
-            Ok(StdAddressFilter::allow_list(vec![range]))
+            Ok(vec![range])
         }
     }
 
+    /// The regions of `--split-coverage`: the covered code of the target, then every other executable file mapping,
+    /// i.e. the libraries loaded by the time the harness is reached
+    fn split_regions(&self, qemu: Qemu) -> Result<Vec<CoverageRegion>, Error> {
+        let target = self.options.target_path(qemu);
+        let file_name = |path: &Path| {
+            path.file_name()
+                .map_or_else(|| path.display().to_string(), |name| name.to_string_lossy().into_owned())
+        };
+
+        let mut regions = vec![CoverageRegion {
+            name: file_name(&target),
+            ranges: self.coverage_ranges(qemu)?,
+        }];
+        for mapping in qemu.mappings() {
+            let Some(path) = mapping.path() else {
+                continue;
+            };
+            // Skip the anonymous and special mappings, e.g. [vdso]
+            if !mapping.flags().executable() || path.is_empty() || path.starts_with('[') {
+                continue;
+            }
+            let name = file_name(Path::new(path));
+            if name == regions[0].name {
+                continue;
+            }
+            let range = mapping.start()..mapping.end();
+            match regions.iter_mut().find(|region| region.name == name) {
+                Some(region) => region.ranges.push(range),
+                None => regions.push(CoverageRegion {
+                    name,
+                    ranges: vec![range],
+                }),
+            }
+        }
+        Ok(regions)
+    }
+
+    /// Write the blocks of the target run by the replayed input to `--lcov-out`
+    fn write_lcov(&self, qemu: Qemu, state: &ClientState, path: &Path) -> Result<(), Error> {
+        let Some(coverage) = state.metadata_map().get::<BlockCoverage>() else {
+            return Err(Error::key_not_found("No block coverage in the state"));
+        };
+        let target = self.options.target_path(qemu);
+        let mut elf_buffer = Vec::new();
+        let elf = EasyElf::from_file(&target, &mut elf_buffer)?;
+        let text = elf
+            .get_section(".text", qemu.load_addr())
+            .ok_or_else(|| Error::key_not_found("Failed to find .text section"))?;
+        let bias = if elf.is_pic() { qemu.load_addr() } else { 0 };
+
+        let (written, with_lines) = write_lcov(qemu, &target, bias, &text, &coverage.blocks, path)?;
+        if with_lines {
+            log::info!("Wrote {written} covered lines to {}", path.display());
+        } else {
+            log::info!("Wrote {written} covered blocks to {}", path.display());
+        }
+        Ok(())
+    }
+
     fn asan_filter(&self, qemu: Qemu) -> Result<StdAddressFilter, Error> {
         let mut elf_buffer = Vec::new();
-        let elf = EasyElf::from_file(qemu.binary_path(), &mut elf_buffer)?;
+        let elf = EasyElf::from_file(self.options.target_path(qemu), &mut elf_buffer)?;
         let range = elf
             .get_section(".text", qemu.load_addr())
             .ok_or_else(|| Error::key_not_found("Failed to find .text section"))?;
         Ok(StdAddressFilter::allow_list(vec![range]))
     }
 
-    #[expect(clippy::too_many_lines)]
+    /// The writable ranges restored by the [`DataResetModule`], empty unless `--data-reset` is set
+    fn data_reset_ranges(&self, qemu: Qemu) -> Result<Vec<Range<GuestAddr>>, Error> {
+        if !self.options.data_reset {
+            return Ok(Vec::new());
+        }
+        if !self.options.data_reset_ranges.is_empty() {
+            return Ok(self.options.data_reset_ranges.clone());
+        }
+
+        let mut elf_buffer = Vec::new();
+        let elf = EasyElf::from_file(self.options.target_path(qemu), &mut elf_buffer)?;
+        let ranges = [".data", ".bss"]
+            .iter()
+            .filter_map(|name| elf.get_section(name, qemu.load_addr()))
+            .collect::<Vec<Range<GuestAddr>>>();
+        if ranges.is_empty() {
+            return Err(Error::key_not_found("Failed to find .data or .bss section"));
+        }
+        Ok(ranges)
+    }
+
+    /// The globals of `--inject-symbol` and `--inject-len-symbol`, resolved once the target is loaded
+    fn symbol_target(&self, qemu: Qemu) -> Result<Option<SymbolTarget>, Error> {
+        let Some(symbol) = &self.options.inject_symbol else {
+            return Ok(None);
+        };
+        let mut elf_buffer = Vec::new();
+        let elf = EasyElf::from_file(self.options.target_path(qemu), &mut elf_buffer)?;
+        let resolve = |name: &str| -> Result<(GuestAddr, usize), Error> {
+            let addr = elf
+                .resolve_symbol(name, qemu.load_addr())
+                .ok_or_else(|| Error::key_not_found(format!("Injection symbol {name} not found")))?;
+            let size = elf
+                .goblin()
+                .syms
+                .iter()
+                .find(|sym| elf.goblin().strtab.get_at(sym.st_name) == Some(name))
+                .map_or(0, |sym| sym.st_size as usize);
+            Ok((addr, size))
+        };
+
+        let (addr, mut size) = resolve(symbol)?;
+        if size == 0 {
+            log::warn!("The size of {symbol} is unknown, writing up to {MAX_INPUT_SIZE} bytes there");
+            size = MAX_INPUT_SIZE;
+        }
+        let len = match &self.options.inject_len_symbol {
+            Some(name) => {
+                let (len_addr, len_size) = resolve(name)?;
+                if !matches!(len_size, 1 | 2 | 4 | 8) {
+                    return Err(Error::illegal_argument(format!(
+                        "Length symbol {name} is {len_size} bytes, expected an integer of 1, 2, 4 or 8 bytes"
+                    )));
+                }
+                Some((len_addr, len_size))
+            }
+            None => None,
+        };
+        Ok(Some(SymbolTarget { addr, size, len }))
+    }
+
+    /// The entry points of the allocator functions for `--heap-check`: the target's own for a static target,
+    /// else the ones of the libc it loaded. Libraries are usually stripped, their exported symbols are looked up.
+    fn heap_functions(&self, qemu: Qemu) -> Result<Vec<(GuestAddr, HeapFn)>, Error> {
+        let mut elf_buffer = Vec::new();
+        let elf = EasyElf::from_file(self.options.target_path(qemu), &mut elf_buffer)?;
+        let functions = HeapFn::ALL
+            .iter()
+            .filter_map(|function| {
+                elf.resolve_symbol(function.symbol(), qemu.load_addr())
+                    .map(|addr| (addr, *function))
+            })
+            .collect::<Vec<_>>();
+        if !functions.is_empty() {
+            return Ok(functions);
+        }
+
+        // The lowest mapping of a library is its first page, i.e. where it is loaded
+        let libc = qemu
+            .mappings()
+            .filter_map(|mapping| {
+                let path = mapping.path()?;
+                let name = Path::new(path).file_name()?.to_str()?;
+                (name.starts_with("libc.so") || name.starts_with("libc-"))
+                    .then(|| (path.to_string(), mapping.start()))
+            })
+            .min_by_key(|(_, start)| *start)
+            .ok_or_else(|| Error::key_not_found("--heap-check found no allocator in the target nor a loaded libc"))?;
+        let (path, load_addr) = libc;
+        let mut lib_buffer = Vec::new();
+        let lib = EasyElf::from_file(&path, &mut lib_buffer)?;
+        let goblin = lib.goblin();
+        let functions = HeapFn::ALL
+            .iter()
+            .filter_map(|function| {
+                goblin
+                    .dynsyms
+                    .iter()
+                    .find(|sym| sym.st_value != 0 && goblin.dynstrtab.get_at(sym.st_name) == Some(function.symbol()))
+                    .map(|sym| (load_addr + sym.st_value as GuestAddr, *function))
+            })
+            .collect::<Vec<_>>();
+        if functions.is_empty() {
+            return Err(Error::key_not_found(format!("--heap-check found no allocator in {path}")));
+        }
+        Ok(functions)
+    }
+
     pub fn run<ET>(
         &mut self,
         args: Vec<String>,
@@ -112,60 +389,221 @@ impl<M: Monitor> Instance<'_, M> {
     where
         ET: EmulatorModuleTuple<BytesInput, ClientState> + Debug,
     {
-        // Create an observation channel using the coverage map
-        let mut edges_observer = unsafe {
-            HitcountsMapObserver::new(VariableMapObserver::from_mut_slice(
-                "edges",
-                OwnedMutSlice::from_raw_parts_mut(edges_map_mut_ptr(), EDGES_MAP_DEFAULT_SIZE),
-                &raw mut MAX_EDGES_FOUND,
-            ))
-            .track_indices()
-        };
+        match self.options.executor_kind() {
+            ExecutorKind::Inprocess => {
+                // Create an observation channel using the coverage map
+                let mut edges_observer = unsafe { Self::edges_observer(edges_map_mut_ptr()) };
+                let edge_coverage_module = StdEdgeCoverageModule::builder()
+                    .map_observer(edges_observer.as_mut())
+                    .build()?;
+                self.run_with::<_, StdEdgeCoverageModule>(
+                    args,
+                    modules.prepend(edge_coverage_module),
+                    edges_observer,
+                    state,
+                    options,
+                    core_id,
+                )
+            }
+            ExecutorKind::Fork => {
+                // The children write their coverage here, and the edge ids are hashes:
+                // the ids the full variant assigns in a child would be lost with it
+                let mut edges_shmem = StdShMemProvider::new()?.new_shmem(EDGES_MAP_DEFAULT_SIZE)?;
+                let mut edges_observer = unsafe {
+                    MAX_EDGES_FOUND = EDGES_MAP_DEFAULT_SIZE;
+                    Self::edges_observer(edges_shmem.as_mut_ptr())
+                };
+                let edge_coverage_module = StdEdgeCoverageChildModule::builder()
+                    .map_observer(edges_observer.as_mut())
+                    .build()?;
+                self.run_with::<_, StdEdgeCoverageChildModule>(
+                    args,
+                    modules.prepend(edge_coverage_module),
+                    edges_observer,
+                    state,
+                    options,
+                    core_id,
+                )
+            }
+        }
+    }
+
+    /// A copy of the edges map of the in-process executor, up to the edges found so far, e.g. for a harness test
+    /// to check the edges an input covered. It holds the hitcounts of the last run, which the next one resets.
+    /// The fork executor's children write to their own map, which this doesn't see.
+    #[expect(dead_code, reason = "For embedding and tests, the fuzzer reads the map through its observer")]
+    pub fn coverage_snapshot() -> Vec<u8> {
+        unsafe {
+            let len = MAX_EDGES_FOUND.min(EDGES_MAP_DEFAULT_SIZE);
+            slice::from_raw_parts(edges_map_mut_ptr(), len).to_vec()
+        }
+    }
+
+    /// The coverage observer over the edges map at `map`, which must hold [`EDGES_MAP_DEFAULT_SIZE`] bytes
+    unsafe fn edges_observer(map: *mut u8) -> EdgesObserver {
+        HitcountsMapObserver::new(VariableMapObserver::from_mut_slice(
+            "edges",
+            OwnedMutSlice::from_raw_parts_mut(map, EDGES_MAP_DEFAULT_SIZE),
+            &raw mut MAX_EDGES_FOUND,
+        ))
+        .track_indices()
+    }
 
+    /// Run with `modules` starting with the edge coverage module `EC`, see `--executor`
+    #[expect(clippy::too_many_lines)]
+    fn run_with<ET, EC>(
+        &mut self,
+        args: Vec<String>,
+        modules: ET,
+        edges_observer: EdgesObserver,
+        state: Option<ClientState>,
+        options: &FuzzerOptions,
+        core_id: CoreId,
+    ) -> Result<(), Error>
+    where
+        ET: EmulatorModuleTuple<BytesInput, ClientState> + Debug,
+        EC: EmulatorModule<BytesInput, ClientState, ModuleAddressFilter = StdAddressFilter>,
+    {
         /*
            Initialize the EmulatorModules and pass them into the Emulator
         */
-        let edge_coverage_module = StdEdgeCoverageModule::builder()
-            .map_observer(edges_observer.as_mut())
-            .build()?;
-
-        let reg_reset_module = RegisterResetModule::new();
+        let mut reg_reset_module = RegisterResetModule::new();
+        reg_reset_module.set_reg_inputs(
+            self.options
+                .reg_input
+                .iter()
+                .map(|src| Harness::parse_reg_input(src))
+                .collect::<Result<Vec<_>, Error>>()?,
+        );
+        let data_reset_module = DataResetModule::new();
         // // custom snapshot module and make `SnapshotModule` as its inner field is not supported and will cause a panic
         let snapshot_module = SnapshotModule::new();
-        let input_injector_module = InputInjectorModule::new();
+        let mut input_injector_module = InputInjectorModule::new();
+        input_injector_module.set_eof_behavior(self.options.eof_behavior);
+        if self.options.multipart {
+            input_injector_module.set_multipart();
+        }
+        if self.options.auto_pipe {
+            input_injector_module.set_auto_pipe();
+        }
+        if self.options.determinize {
+            add_determinism_handlers(&mut input_injector_module);
+        }
+        if let Some(encodings) = &self.options.header_selector {
+            input_injector_module.set_selector(encodings.clone());
+        }
+        if let Some(script) = &self.options.script {
+            input_injector_module.set_script(Script::from_file(script)?);
+        }
+        if let Some(schema) = &self.options.schema {
+            input_injector_module.set_schema(Schema::from_file(schema)?);
+        }
+        if let Some(path) = &self.options.mmap_file {
+            input_injector_module.set_mmap_file(path);
+        }
+        if let Some(size) = self.options.mmap_window {
+            input_injector_module.set_mmap_window(size);
+        }
+        if self.options.shm_inject {
+            input_injector_module.set_shm_inject(self.options.shm_key);
+        }
+        if self.options.ignore_aborts {
+            input_injector_module.ignore_aborts();
+        }
+        if self.options.executor_kind() == ExecutorKind::Fork {
+            input_injector_module.set_forked();
+        }
+        let crash_classifier_module = CrashClassifierModule::new();
+        let block_trace_module = BlockTraceModule::new(self.options.crash_trace_depth);
+        let block_coverage_module = BlockCoverageModule::new(self.options.lcov_out.is_some());
+        let mut finding_detector_module =
+            FindingDetectorModule::new(self.options.finding_pattern.as_deref());
+        if self.options.canary_fill {
+            input_injector_module.set_canary_fill();
+            finding_detector_module.detect_canary();
+        }
+        let target_reach_module = TargetReachModule::new();
+        // Tracks nothing unless --heap-check is given, the allocator is only loaded once the target runs
+        let heap_check_module = HeapCheckModule::new();
+        // Covers nothing unless --split-coverage is given, its regions are only known once the libraries are loaded
+        let split_coverage_module = SplitCoverageModule::new();
+        let syscall_stats_module = SyscallStatsModule::new(self.options.syscall_stats);
+        // Covers no library unless --cover-lib is given, they are only mapped once the target runs
+        let lib_coverage_module = LibCoverageModule::new(&self.options.cover_lib);
+        let collision_sample_module = CollisionSampleModule::new(
+            self.options
+                .coverage_diagnostics
+                .then_some(COLLISION_SAMPLE_INTERVAL),
+        );
+        let output_capture_module = OutputCaptureModule::new(self.options.crash_on_output.is_some());
+        // Masks nothing unless --ignore-edges is given
+        let edge_mask_module = match &self.options.ignore_edges {
+            Some(path) => EdgeMaskModule::from_file(path)?,
+            None => EdgeMaskModule::default(),
+        };
 
         // Be careful the order of the modules ...
         let modules = modules
-            .prepend(edge_coverage_module)
+            .prepend(edge_mask_module)
+            .prepend(split_coverage_module)
+            .prepend(lib_coverage_module)
+            .prepend(syscall_stats_module)
+            .prepend(collision_sample_module)
+            .prepend(target_reach_module)
+            .prepend(block_trace_module)
+            .prepend(block_coverage_module)
+            .prepend(crash_classifier_module)
+            .prepend(heap_check_module)
+            .prepend(finding_detector_module)
+            .prepend(output_capture_module)
             .prepend(input_injector_module)
             .prepend(reg_reset_module)
+            .prepend(data_reset_module)
             .prepend(snapshot_module);
 
         /*
            Initialize the Emulator, Qemu (initialized in emulator) and Harness
         */
         log::info!("Qemu Parameters: {:?}", args);
+        let qemu_args = args.clone();
         let mut emulator = Emulator::empty()
             .qemu_parameters(args)
             .modules(modules)
-            .build()?;
+            .build()
+            .map_err(|e| {
+                Error::illegal_state(format!(
+                    "Failed to initialize qemu with parameters {qemu_args:?}: {e:?}\n\
+                     Hint: check that the fuzzer was built for the target's architecture (current: {}), \
+                     that the target binary exists and that its libraries can be found (e.g. with -L <rootfs>)",
+                    env!("CPU_TARGET")
+                ))
+            })?;
 
         let qemu = emulator.qemu();
-        let harness = Harness::init(qemu).expect("Error setting up harness.");
+        let harness = Harness::init(qemu, self.options, self.client_description.core_id())
+            .map_err(|e| Error::illegal_state(format!("Error setting up harness: {e}")))?;
 
         /*
            Post-update the EmulatorModules after Qemu has been initialized
         */
         // update address filter after qemu has been initialized
-        <EdgeCoverageModule<StdAddressFilter, NopPageFilter, EdgeCoverageFullVariant, false, 0> 
-            as EmulatorModule<BytesInput, ClientState>>::update_address_filter(
-                emulator.modules_mut()
-                    .get_mut::<EdgeCoverageModule<StdAddressFilter, NopPageFilter, EdgeCoverageFullVariant, false, 0>>()
-                    .expect("Could not find back the edge module"), 
-                qemu,
-                self.coverage_filter(qemu)?
+        <EC as EmulatorModule<BytesInput, ClientState>>::update_address_filter(
+            emulator
+                .modules_mut()
+                .get_mut::<EC>()
+                .expect("Could not find back the edge module"),
+            qemu,
+            self.coverage_filter(qemu)?,
         );
 
+        // The denominator of the coverage estimate, excluded ranges are not subtracted from .text
+        if self.options.coverage_estimate {
+            let ranges = self.coverage_ranges(qemu)?;
+            let blocks = count_blocks(qemu, &ranges)?;
+            log::info!("Estimated {blocks} basic blocks in {ranges:#x?}");
+            self.total_blocks = Some(blocks);
+        }
+
         if self.options.is_asan_core(core_id) {
             // update address filter after qemu has been initialized
             <AsanModule as EmulatorModule<BytesInput, ClientState>>::update_address_filter(
@@ -185,12 +623,86 @@ impl<M: Monitor> Instance<'_, M> {
             .expect("Could not find back the register reset module")
             .save(qemu);
 
-        // Set the input address for the input injector module
+        // Writable data is saved at the first execution, i.e. in the same state as the registers
+        let data_reset_ranges = self.data_reset_ranges(qemu)?;
+        log::info!("Data reset ranges: {:#x?}", data_reset_ranges);
         emulator
+            .modules_mut()
+            .get_mut::<DataResetModule>()
+            .expect("Could not find back the data reset module")
+            .set_ranges(data_reset_ranges);
+
+        if let Some(symbol) = &self.options.priority_target {
+            let mut elf_buffer = Vec::new();
+            let elf = EasyElf::from_file(self.options.target_path(qemu), &mut elf_buffer)?;
+            let addr = elf
+                .resolve_symbol(symbol, qemu.load_addr())
+                .ok_or_else(|| Error::key_not_found(format!("Priority target {symbol} not found")))?;
+            log::info!("Priority target {symbol} @ {addr:#x}");
+            emulator
+                .modules_mut()
+                .get_mut::<TargetReachModule>()
+                .expect("Could not find back the target reach module")
+                .set_target(addr);
+        }
+
+        if self.options.heap_check {
+            let functions = self.heap_functions(qemu)?;
+            for (addr, function) in &functions {
+                log::info!("Heap check: {} @ {addr:#x}", function.symbol());
+            }
+            emulator
+                .modules_mut()
+                .get_mut::<HeapCheckModule>()
+                .expect("Could not find back the heap check module")
+                .set_functions(functions);
+        }
+
+        let split_map = if self.options.split_coverage {
+            let regions = self.split_regions(qemu)?;
+            for region in &regions {
+                log::info!("Split coverage region {}: {:#x?}", region.name, region.ranges);
+            }
+            self.split_regions = regions.iter().map(|region| region.name.clone()).collect();
+            emulator
+                .modules_mut()
+                .get_mut::<SplitCoverageModule>()
+                .expect("Could not find back the split coverage module")
+                .set_regions(regions)
+        } else {
+            &mut []
+        };
+        // An empty map when --split-coverage is not given, the observer is still needed for the types to match
+        let split_observer = unsafe {
+            HitcountsMapObserver::new(StdMapObserver::from_mut_ptr(
+                "split_edges",
+                split_map.as_mut_ptr(),
+                split_map.len(),
+            ))
+        };
+
+        // Set the input address for the input injector module
+        let input_injector = emulator
             .modules_mut()
             .get_mut::<InputInjectorModule>()
-            .expect("Could not find back the input injector module")
-            .set_input_addr(harness.input_addr);
+            .expect("Could not find back the input injector module");
+        input_injector.set_input_addr(harness.input_addr);
+        if let Some(size) = self.options.fixed_size {
+            input_injector.set_max_size(size);
+        }
+        if let Some(min_size) = self.options.min_input_size() {
+            input_injector.set_min_size(min_size);
+        }
+        if self.options.bench.is_some() {
+            input_injector.set_timed();
+        }
+        if let Some(conv) = self.options.calling_convention {
+            input_injector.set_entry_args(conv);
+        }
+        if let Some(target) = self.symbol_target(qemu)? {
+            log::info!("Injecting the input to {target:#x?}");
+            input_injector.set_symbol_target(target);
+        }
 
         /*
          * Add Other Fuzzer Components
@@ -198,12 +710,57 @@ impl<M: Monitor> Instance<'_, M> {
         // Create an observation channel to keep track of the execution time
         let time_observer = TimeObserver::new("time");
 
-        let map_feedback = MaxMapFeedback::new(&edges_observer);
+        // Edge presence ignores hitcount bucket changes, trading sensitivity for a smaller corpus.
+        // Both feedbacks are built and gated so that the feedback type doesn't depend on the mode.
+        let edges_only = self.options.coverage_mode == CoverageMode::Edges;
+        let hitcounts_feedback = MaxMapFeedback::new(&edges_observer);
+        let edges_feedback =
+            EdgePresenceFeedback::with_name("edges_presence", &edges_observer);
 
         // If this input should not be ignored, `is_interesting` will return true
         let ignore_exit_feedback = IgnoreExitFeedback;
 
-        let calibration = CalibrationStage::new(&map_feedback);
+        let calibration = if edges_only {
+            CalibrationStage::new(&edges_feedback)
+        } else {
+            CalibrationStage::new(&hitcounts_feedback)
+        };
+        // The power schedule needs the exec time and the map size of every entry, so with
+        // --skip-calibration they're recorded from a single run instead of the calibration runs
+        let skip_calibration = self.options.skip_calibration;
+        let calibration = IfStage::new(move |_, _, _, _| Ok(!skip_calibration), tuple_list!(calibration));
+        let single_calibration = IfStage::new(
+            move |_, _, _, _| Ok(skip_calibration),
+            tuple_list!(SingleCalibrationStage::new(&edges_observer)),
+        );
+
+        // Not short-circuited, the split coverage history must be updated along the main one
+        let map_feedback = feedback_or!(
+            feedback_or_fast!(
+                feedback_and_fast!(ConstFeedback::new(edges_only), edges_feedback),
+                feedback_and_fast!(ConstFeedback::new(!edges_only), hitcounts_feedback)
+            ),
+            feedback_and_fast!(
+                ConstFeedback::new(self.options.split_coverage),
+                MaxMapFeedback::new(&split_observer)
+            )
+        );
+
+        let auto_trim = self.options.auto_trim;
+        let auto_trim_stage = IfStage::new(
+            move |_, _, _, _| Ok(auto_trim),
+            tuple_list!(AutoTrimStage::new(&edges_observer)),
+        );
+
+        // Evicts nothing unless --max-corpus is given
+        let evict_stage = CorpusEvictionStage::new(self.options.max_corpus);
+
+        // Recalibrates nothing unless --recalibrate-interval is given
+        let recalibration_stage = RecalibrationStage::new(
+            &edges_observer,
+            self.options.recalibrate_interval,
+            self.options.recalibrate_sample,
+        );
 
         let stats_stage = IfStage::new(
             |_, _, _, _| Ok(self.options.tui),
@@ -214,21 +771,82 @@ impl<M: Monitor> Instance<'_, M> {
         );
 
         // Feedback to rate the interestingness of an input
-        // This one is composed by two Feedbacks in OR
+        // This one is composed by four Feedbacks in OR
         let mut feedback = feedback_or!(
-            // New maximization map feedback linked to the edges observer and the feedback state
+            // New maximization map feedback linked to the edges observer and the feedback state,
+            // never evaluated when fuzzing blind
             feedback_and_fast!(
+                ConstFeedback::new(!self.options.blind),
                 map_feedback,
                 ignore_exit_feedback
             ),
             // Time feedback, this one does not need a feedback state
-            TimeFeedback::new(&time_observer)
+            TimeFeedback::new(&time_observer),
+            // Findings go to their own directory, independently of the corpus and the crashes
+            FindingFeedback::new(self.options.findings_dir(self.client_description.clone())),
+            OutputMatchFeedback::new(
+                self.options.crash_on_output.clone(),
+                self.options.findings_dir(self.client_description.clone())
+            ),
+            // Only tags the new corpus entries reaching the priority target
+            TargetReachFeedback::new(self.options.priority_weight),
+            // Only tags the new entries with their parent, with --provenance
+            ProvenanceFeedback,
+            // Only prints the edges each new corpus entry adds, when verbose
+            NewEdgesLogFeedback::new(&edges_observer, self.options.verbose),
+            // Only counts the map slots of the runs sampled by --coverage-diagnostics
+            CollisionFeedback::new(&edges_observer),
+            EventSinkFeedback::new(
+                self.event_sinks(),
+                EventKind::Corpus,
+                self.options.queue_dir(self.client_description.clone()),
+                self.client_description.id()
+            )
         );
 
         // A feedback to choose if an input is a solution or not
-        let mut objective = feedback_and_fast!(
-            CrashFeedback::new(),
-            MaxMapFeedback::new(&edges_observer));
+        // The crash info and exit code feedbacks never mark a solution themselves, they only
+        // classify it or save it as a finding, the reproducer and the mirror only handle the saved solution.
+        // A clean exit is turned into an abort by the injector, it must not count as a crash,
+        // neither must a guest signal outside of --crash-signals.
+        // A crash is only kept with new coverage, unless --all-crashes is given.
+        let mut objective = feedback_or!(
+            CrashInfoFeedback::new(self.options.crash_info.clone())
+                .descriptive_names(self.options.descriptive_crash_names)
+                .crash_signals(self.options.crash_signals.clone()),
+            ExitCodeFeedback::new(
+                self.options.objective_exit_code,
+                self.options.findings_dir(self.client_description.clone())
+            ),
+            feedback_and_fast!(
+                CrashFeedback::new(),
+                IgnoreExitFeedback,
+                feedback_or_fast!(
+                    ConstFeedback::new(self.options.all_crashes),
+                    MaxMapFeedback::new(&edges_observer)
+                )
+            ),
+            ProvenanceFeedback,
+            ReproducerFeedback::new(
+                self.options
+                    .reproducers
+                    .then(|| self.options.crashes_dir(self.client_description.clone()))
+            ),
+            SolutionMirrorFeedback::new(
+                self.options.solutions_dir.clone(),
+                self.client_description.core_id()
+            ),
+            EventSinkFeedback::new(
+                self.event_sinks(),
+                EventKind::Solution,
+                self.options.crashes_dir(self.client_description.clone()),
+                self.client_description.id()
+            )
+        );
+
+        // A recorded or replayed campaign starts from a known seed, see `--record-mutations`
+        let mutation_log = self.mutation_log(state.is_none())?;
+        let seed = mutation_log.as_ref().and_then(MutationLog::seed);
 
         // // If not restarting, create a State from scratch
         let mut state = match state {
@@ -236,10 +854,12 @@ impl<M: Monitor> Instance<'_, M> {
             None => {
                 StdState::new(
                     // RNG
-                    StdRand::new(),
-                    // Corpus that will be evolved, we keep it in memory for performance
-                    InMemoryOnDiskCorpus::no_meta(
+                    seed.map_or_else(StdRand::new, StdRand::with_seed),
+                    // Corpus that will be evolved, we keep it in memory for performance.
+                    // Its metadata is only written next to the entries for --provenance
+                    InMemoryOnDiskCorpus::with_meta_format(
                         self.options.queue_dir(self.client_description.clone()),
+                        self.options.provenance.then_some(OnDiskMetadataFormat::JsonPretty),
                     )?,
                     // Corpus in which we store solutions (crashes in this example),
                     // on disk so the user can get them after stopping the fuzzer
@@ -253,13 +873,23 @@ impl<M: Monitor> Instance<'_, M> {
             }
         };
 
-        // A minimization+queue policy to get testcasess from the corpus
-        let scheduler = IndexesLenTimeMinimizerScheduler::new(
-            &edges_observer,
-            PowerQueueScheduler::new(&mut state, &edges_observer, PowerSchedule::fast()),
+        // Keep the mutators from growing inputs past what the target meaningfully processes
+        if let Some(max_size) = self.options.max_input_size() {
+            state.set_max_size(max_size);
+        }
+
+        // A minimization+queue policy to get testcasess from the corpus,
+        // bypassed for the insertion order with --fifo-schedule
+        let scheduler = FifoScheduler::new(
+            IndexesLenTimeMinimizerScheduler::new(
+                &edges_observer,
+                PowerQueueScheduler::new(&mut state, &edges_observer, PowerSchedule::fast()),
+            ),
+            self.options.fifo_schedule,
         );
 
-        let observers = tuple_list!(edges_observer, time_observer);
+        let edges_handle = edges_observer.handle();
+        let observers = tuple_list!(edges_observer, time_observer, split_observer);
 
         let mut tokens = Tokens::new();
 
@@ -277,18 +907,47 @@ impl<M: Monitor> Instance<'_, M> {
 
         harness.post_fork();
         
+        let mut input_log = match &self.options.log_all_inputs {
+            Some(dir) => {
+                log::warn!(
+                    "Logging the first {} executed inputs to {}, up to {} MB of disk",
+                    self.options.log_all_count,
+                    dir.display(),
+                    self.options.log_all_count * MAX_INPUT_SIZE as u64 >> 20
+                );
+                Some(InputLog::new(
+                    dir.clone(),
+                    self.client_description.core_id(),
+                    self.options.log_all_count as usize,
+                )?)
+            }
+            None => None,
+        };
+
         // For current testing, the harness only needs to run once, so we do not need to reset the program state.
         let mut harness = |_emulator: &mut Emulator<_, _, _, _, _, _, _>,
-                           _state: &mut _,
-                           input: &BytesInput| harness.run(_emulator.qemu());
+                           _state: &mut ClientState,
+                           input: &BytesInput| {
+            if let Some(input_log) = &mut input_log {
+                input_log.log(&input.target_bytes());
+            }
+            harness.run(_emulator.qemu(), _state, input)
+        };
 
         // A fuzzer with feedbacks and a corpus scheduler
         let mut fuzzer = StdFuzzer::new(scheduler, feedback, objective);
 
-        if let Some(rerun_input) = &self.options.rerun_input {
+        if self.options.replays_input() {
             // TODO: We might want to support non-bytes inputs at some point?
-            let bytes = fs::read(rerun_input)
-                .unwrap_or_else(|_| panic!("Could not load file {rerun_input:?}"));
+            let bytes = match &self.options.rerun_input {
+                Some(rerun_input) => fs::read(rerun_input)
+                    .unwrap_or_else(|_| panic!("Could not load file {rerun_input:?}")),
+                None => {
+                    let mut bytes = Vec::new();
+                    io::stdin().read_to_end(&mut bytes)?;
+                    bytes
+                }
+            };
             let input = BytesInput::new(bytes);
 
             let mut executor = QemuExecutor::new(
@@ -301,7 +960,7 @@ impl<M: Monitor> Instance<'_, M> {
                 self.options.timeout,
             )?;
 
-            executor
+            let exit_kind = executor
                 .run_target(
                     &mut fuzzer,
                     &mut state,
@@ -309,11 +968,182 @@ impl<M: Monitor> Instance<'_, M> {
                     &input,
                 )
                 .expect("Error running target");
+
+            // Crashes and timeouts never come back here, they are classified by the objective
+            if let Some(crash_info) = &self.options.crash_info {
+                let json = serde_json::to_string(&CrashInfo::new(exit_kind))
+                    .map_err(|e| Error::serialize(format!("Failed to serialize crash info: {e}")))?;
+                fs::write(crash_info, json)?;
+            }
+            if let Some(edges_out) = &self.options.edges_out {
+                let map = executor.observers()[&edges_handle].to_vec();
+                dump_edges(&state, &map, qemu.load_addr(), edges_out)?;
+            }
+            if let Some(lcov_out) = &self.options.lcov_out {
+                self.write_lcov(qemu, &state, lcov_out)?;
+            }
             // We're done :)
             process::exit(0);
         }
 
-        if self
+        if let Some(seed) = &self.options.stability_check {
+            let bytes =
+                fs::read(seed).unwrap_or_else(|_| panic!("Could not load file {seed:?}"));
+            let input = BytesInput::new(bytes);
+
+            let mut executor = QemuExecutor::new(
+                emulator,
+                &mut harness,
+                observers,
+                &mut fuzzer,
+                &mut state,
+                &mut self.mgr,
+                self.options.timeout,
+            )?;
+
+            // Unlike `run_target`, `execute_input` resets the observers between runs
+            let mut maps = Vec::new();
+            for _ in 0..self.options.stability_runs {
+                fuzzer.execute_input(&mut state, &mut executor, &mut self.mgr, &input)?;
+                maps.push(executor.observers()[&edges_handle].to_vec());
+            }
+
+            StabilityReport::from_maps(&maps).print();
+            process::exit(0);
+        }
+
+        if let Some(path) = &self.options.byte_attribution {
+            let bytes =
+                fs::read(path).unwrap_or_else(|_| panic!("Could not load file {path:?}"));
+
+            let mut executor = QemuExecutor::new(
+                emulator,
+                &mut harness,
+                observers,
+                &mut fuzzer,
+                &mut state,
+                &mut self.mgr,
+                self.options.timeout,
+            )?;
+            let mut map_of = |bytes: Vec<u8>| -> Result<Vec<u8>, Error> {
+                fuzzer.execute_input(&mut state, &mut executor, &mut self.mgr, &BytesInput::new(bytes))?;
+                Ok(executor.observers()[&edges_handle].to_vec())
+            };
+
+            // Two runs of the input tell the entries that change anyway
+            let first = map_of(bytes.clone())?;
+            let second = map_of(bytes.clone())?;
+            let mut report = AttributionReport::new(bytes.len(), first, &second);
+            for region in AttributionReport::regions(bytes.len(), self.options.attribution_regions as usize) {
+                let mut flipped = bytes.clone();
+                flipped[region.clone()].iter_mut().for_each(|b| *b ^= 0xff);
+                let flip = report.changed(&map_of(flipped)?);
+                let mut zeroed = bytes.clone();
+                zeroed[region.clone()].fill(0);
+                let zero = report.changed(&map_of(zeroed)?);
+                report.add(region, flip, zero);
+            }
+
+            report.print();
+            if let Some(json) = &self.options.attribution_json {
+                report.write_json(json)?;
+            }
+            process::exit(0);
+        }
+
+        if let Some(runs) = self.options.bench {
+            let mut executor = QemuExecutor::new(
+                emulator,
+                &mut harness,
+                observers,
+                &mut fuzzer,
+                &mut state,
+                &mut self.mgr,
+                self.options.timeout,
+            )?;
+            let input = BytesInput::new(Vec::new());
+
+            // The first run translates the blocks, it would dominate the average
+            fuzzer.execute_input(&mut state, &mut executor, &mut self.mgr, &input)?;
+            state.metadata_map_mut().remove::<InjectionTime>();
+
+            // The executions alone, then each one followed by the feedbacks. The feedbacks only rate the run,
+            // nothing is added to the corpus and their history doesn't change, so every run costs the same.
+            let start = current_time();
+            for _ in 0..runs {
+                fuzzer.execute_input(&mut state, &mut executor, &mut self.mgr, &input)?;
+            }
+            let exec_time = current_time().saturating_sub(start);
+
+            let start = current_time();
+            for _ in 0..runs {
+                let exit_kind = fuzzer.execute_input(&mut state, &mut executor, &mut self.mgr, &input)?;
+                let observers = executor.observers();
+                fuzzer.feedback_mut().is_interesting(&mut state, &mut self.mgr, &input, &*observers, &exit_kind)?;
+                fuzzer.objective_mut().is_interesting(&mut state, &mut self.mgr, &input, &*observers, &exit_kind)?;
+            }
+            let eval_time = current_time().saturating_sub(start);
+
+            let injection = state
+                .metadata::<InjectionTime>()
+                .map(|time| time.total / time.runs.max(1) as u32)
+                .unwrap_or_default();
+            let exec = exec_time / runs;
+            let feedback = (eval_time / runs).saturating_sub(exec);
+            println!("Benchmark of {runs} runs of an empty input");
+            println!("  exec/sec:  {:.1}", runs as f64 / exec_time.as_secs_f64());
+            println!("  per run:   {exec:?}");
+            println!("    injection: {injection:?}");
+            println!("    qemu run:  {:?} (the target and the executor)", exec.saturating_sub(injection));
+            println!("  feedbacks: {feedback:?} per run, {:.1} exec/sec with them", runs as f64 / eval_time.as_secs_f64());
+            process::exit(0);
+        }
+
+        if self.options.executor_kind() == ExecutorKind::Fork {
+            if self.options.is_cmplog_core(self.client_description.core_id()) {
+                log::warn!("The cmplog map of a forked child is lost with it, running without cmplog");
+            }
+
+            // Create a QEMU fork executor, every run happens in a child forked from this process
+            let mut executor = QemuForkExecutor::new(
+                emulator,
+                &mut harness,
+                observers,
+                &mut fuzzer,
+                &mut state,
+                &mut self.mgr,
+                StdShMemProvider::new()?,
+                self.options.timeout,
+            )?;
+
+            // Setup an havoc mutator with a mutational stage
+            let mutator = ProvenanceMutator::new(
+                MutationLogMutator::new(
+                    PredicateMutator::new(
+                        MaxGrowMutator::new(
+                            StdScheduledMutator::with_max_stack_pow(
+                                havoc_mutations().merge(tokens_mutations()).map(TrailMapper),
+                                self.options.havoc_stack_pow,
+                            ),
+                            self.options.max_grow,
+                        ),
+                        self.input_predicate(),
+                    ),
+                    mutation_log,
+                ),
+                self.options.provenance,
+            );
+            let mut stages =
+                tuple_list!(evict_stage, recalibration_stage, auto_trim_stage, StdMutationalStage::new(mutator));
+
+            self.fuzz(
+                &mut state,
+                &mut fuzzer,
+                &mut executor,
+                &mut stages,
+                &edges_handle,
+            )
+        } else if self
             .options
             .is_cmplog_core(self.client_description.core_id())
         {
@@ -327,6 +1157,14 @@ impl<M: Monitor> Instance<'_, M> {
                 &mut self.mgr,
                 self.options.timeout,
             )?;
+            // Scale the timeout with the input size, see `--timeout-per-kb`
+            let executor = ScaledTimeoutExecutor::new(
+                executor,
+                |executor| executor.inner_mut().inprocess_hooks_mut().timer_mut(),
+                self.options.timeout,
+                self.options.timeout_per_kb,
+                self.options.timeout_max,
+            );
 
             // Create an observation channel using cmplog map
             let cmplog_observer = CmpLogObserver::new("cmplog", true);
@@ -336,28 +1174,58 @@ impl<M: Monitor> Instance<'_, M> {
             let tracing = ShadowTracingStage::new(&mut executor);
 
             // Setup a randomic Input2State stage
-            let i2s = StdMutationalStage::new(StdScheduledMutator::new(tuple_list!(
-                I2SRandReplace::new()
-            )));
+            let i2s = StdMutationalStage::new(ProvenanceMutator::new(
+                StdScheduledMutator::new(tuple_list!(I2SRandReplace::new()).map(TrailMapper)),
+                self.options.provenance,
+            ));
 
             // Setup a MOPT mutator
-            let mutator = StdMOptMutator::new(
-                &mut state,
-                havoc_mutations().merge(tokens_mutations()),
-                7,
-                5,
-            )?;
+            let mutator = ProvenanceMutator::new(
+                MutationLogMutator::new(
+                    PredicateMutator::new(
+                        MaxGrowMutator::new(
+                            StdMOptMutator::new(
+                                &mut state,
+                                havoc_mutations().merge(tokens_mutations()).map(TrailMapper),
+                                self.options.havoc_stack_pow,
+                                5,
+                            )?,
+                            self.options.max_grow,
+                        ),
+                        self.input_predicate(),
+                    ),
+                    mutation_log,
+                ),
+                self.options.provenance,
+            );
 
-            let power: StdPowerMutationalStage<_, _, BytesInput, _, _, _> =
-                StdPowerMutationalStage::new(mutator);
+            // The power schedule is weighted by the entries' priority, see `--priority-target`
+            let power: PowerMutationalStage<_, PriorityTestcaseScore, _, BytesInput, _, _, _> =
+                PowerMutationalStage::new(mutator);
 
             // The order of the stages matter!
-            let mut stages = tuple_list!(calibration, tracing, i2s, power, stats_stage);
+            let mut stages = tuple_list!(
+                evict_stage,
+                calibration,
+                single_calibration,
+                recalibration_stage,
+                auto_trim_stage,
+                tracing,
+                i2s,
+                power,
+                stats_stage
+            );
 
-            self.fuzz(&mut state, &mut fuzzer, &mut executor, &mut stages)
+            self.fuzz(
+                &mut state,
+                &mut fuzzer,
+                &mut executor,
+                &mut stages,
+                &edges_handle,
+            )
         } else {
             // Create a QEMU in-process executor
-            let mut executor = QemuExecutor::new(
+            let executor = QemuExecutor::new(
                 emulator,
                 &mut harness,
                 observers,
@@ -366,13 +1234,468 @@ impl<M: Monitor> Instance<'_, M> {
                 &mut self.mgr,
                 self.options.timeout,
             )?;
+            // Scale the timeout with the input size, see `--timeout-per-kb`
+            let mut executor = ScaledTimeoutExecutor::new(
+                executor,
+                |executor| executor.inner_mut().inprocess_hooks_mut().timer_mut(),
+                self.options.timeout,
+                self.options.timeout_per_kb,
+                self.options.timeout_max,
+            );
 
             // Setup an havoc mutator with a mutational stage
-            let mutator = StdScheduledMutator::new(havoc_mutations().merge(tokens_mutations()));
-            let mut stages = tuple_list!(StdMutationalStage::new(mutator));
+            let mutator = ProvenanceMutator::new(
+                MutationLogMutator::new(
+                    PredicateMutator::new(
+                        MaxGrowMutator::new(
+                            StdScheduledMutator::with_max_stack_pow(
+                                havoc_mutations().merge(tokens_mutations()).map(TrailMapper),
+                                self.options.havoc_stack_pow,
+                            ),
+                            self.options.max_grow,
+                        ),
+                        self.input_predicate(),
+                    ),
+                    mutation_log,
+                ),
+                self.options.provenance,
+            );
+            let mut stages =
+                tuple_list!(evict_stage, recalibration_stage, auto_trim_stage, StdMutationalStage::new(mutator));
+
+            self.fuzz(
+                &mut state,
+                &mut fuzzer,
+                &mut executor,
+                &mut stages,
+                &edges_handle,
+            )
+        }
+    }
+
+    /// The log of `--record-mutations` or `--replay-mutations`, `fresh` if the state isn't restored
+    fn mutation_log(&self, fresh: bool) -> Result<Option<MutationLog>, Error> {
+        if let Some(path) = &self.options.record_mutations {
+            return MutationLog::record(path, fresh).map(Some);
+        }
+        match &self.options.replay_mutations {
+            Some(path) => MutationLog::replay(path).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// The `--input-predicate`, already checked by the options validation
+    fn input_predicate(&self) -> Option<InputPredicate> {
+        self.options
+            .input_predicate
+            .as_deref()
+            .map(|predicate| InputPredicate::parse(predicate).expect("Invalid input predicate"))
+    }
+
+    /// The sinks told about the new solutions and corpus entries
+    fn event_sinks(&self) -> Vec<Box<dyn EventSink>> {
+        let mut sinks: Vec<Box<dyn EventSink>> = Vec::new();
+        if let Some(command) = &self.options.on_crash_cmd {
+            sinks.push(Box::new(CommandSink::new(command.clone())));
+        }
+        if let Some(path) = &self.options.crash_report {
+            sinks.push(Box::new(CrashReportSink::new(path.clone())));
+        }
+        sinks
+    }
+
+    fn dump_coverage(&self, state: &ClientState) -> Result<(), Error> {
+        if let Some(path) = self.options.coverage_out(self.client_description.clone()) {
+            let covered = dump_coverage(state, self.options.map_feedback_name(), &path)?;
+            println!("Wrote {covered} covered edges to {path:?}");
+        }
+        Ok(())
+    }
+
+    /// Report the most frequent syscalls per execution, as the `syscalls` user stat, see `--syscall-stats`
+    fn report_syscall_stats(&mut self, state: &mut ClientState) -> Result<(), Error> {
+        if !self.options.syscall_stats {
+            return Ok(());
+        }
+        let executions = (*state.executions()).max(1) as f64;
+        let top = state
+            .metadata_map()
+            .get::<SyscallStats>()
+            .map(|stats| stats.top(5))
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(num, count)| format!("{} {:.1}", SyscallStats::name(num), count as f64 / executions))
+            .collect::<Vec<String>>();
+        self.mgr.fire(
+            state,
+            Event::UpdateUserStats {
+                name: Cow::Borrowed("syscalls"),
+                value: UserStats::new(UserStatsValue::String(Cow::Owned(top.join(", "))), AggregatorOps::None),
+                phantom: PhantomData,
+            },
+        )
+    }
+
+    /// Print the syscalls of the campaign, most frequent first, when it ends
+    fn print_syscall_stats(&self, state: &ClientState) {
+        if !self.options.syscall_stats {
+            return;
+        }
+        let Some(stats) = state.metadata_map().get::<SyscallStats>() else {
+            return;
+        };
+        let executions = (*state.executions()).max(1) as f64;
+        println!("Syscalls over {} executions:", state.executions());
+        for (num, count) in stats.top(20) {
+            println!(
+                "{:>14} {:>12} {:>10.2}/exec",
+                SyscallStats::name(num),
+                count,
+                count as f64 / executions
+            );
+        }
+    }
+
+    /// Report the estimated share of colliding edges of the `--coverage-diagnostics` samples, as the `collisions` user stat
+    fn report_collisions(&mut self, state: &mut ClientState) -> Result<(), Error> {
+        if !self.options.coverage_diagnostics {
+            return Ok(());
+        }
+        let Some(rate) = state.metadata_map().get::<CollisionStats>().and_then(CollisionStats::rate) else {
+            return Ok(());
+        };
+        self.mgr.fire(
+            state,
+            Event::UpdateUserStats {
+                name: Cow::Borrowed("collisions"),
+                value: UserStats::new(
+                    UserStatsValue::Ratio((rate * 1000.0) as u64, 1000),
+                    AggregatorOps::Avg,
+                ),
+                phantom: PhantomData,
+            },
+        )
+    }
+
+    /// Print the `--coverage-diagnostics` estimate when the campaign ends, and what to do if it's high
+    fn print_collisions(&self, state: &ClientState) {
+        if !self.options.coverage_diagnostics {
+            return;
+        }
+        let Some(stats) = state.metadata_map().get::<CollisionStats>() else {
+            return;
+        };
+        let Some(rate) = stats.rate() else {
+            return;
+        };
+        println!(
+            "Coverage map collisions: ~{:.1}% of the edges share a map slot ({} sampled runs, {:.0} edges and {:.0} slots per run, map of {EDGES_MAP_DEFAULT_SIZE} slots)",
+            rate * 100.0,
+            stats.runs,
+            stats.transitions as f64 / stats.runs.max(1) as f64,
+            stats.slots as f64 / stats.runs.max(1) as f64,
+        );
+        if rate > HIGH_COLLISION_RATE {
+            println!(
+                "The coverage map merges edges: restrict the instrumented code with --include/--exclude, \
+                 or rebuild with a larger edges map (EDGES_MAP_DEFAULT_SIZE)"
+            );
+        }
+    }
+
+    /// Print the `--slow-report` slowest corpus entries. Their execution time is the one recorded by the
+    /// `TimeFeedback` when they were added, averaged over the calibration runs on the cmplog cores.
+    fn print_slow_report(&self, state: &ClientState) -> Result<(), Error> {
+        let Some(n) = self.options.slow_report else {
+            return Ok(());
+        };
+        let mut entries = Vec::new();
+        for id in state.corpus().ids() {
+            let testcase = state.corpus().get(id)?.borrow();
+            if let Some(time) = *testcase.exec_time() {
+                let name = testcase
+                    .file_path()
+                    .as_ref()
+                    .map_or_else(|| id.to_string(), |path| path.display().to_string());
+                entries.push((time, name));
+            }
+        }
+        entries.sort_by(|a, b| b.0.cmp(&a.0));
+
+        println!("Slowest of {} corpus entries:", state.corpus().count());
+        for (time, name) in entries.iter().take(n) {
+            println!("{:>12.3}ms {name}", time.as_secs_f64() * 1000.0);
+        }
+        Ok(())
+    }
+
+    /// Report the edges found over the estimated basic blocks, as the `coverage_estimate` user stat
+    fn report_coverage_estimate(&mut self, state: &mut ClientState) -> Result<(), Error> {
+        if let Some(total) = self.total_blocks {
+            let found = covered_edges(state, self.options.map_feedback_name())?;
+            self.mgr.fire(
+                state,
+                Event::UpdateUserStats {
+                    name: Cow::Borrowed("coverage_estimate"),
+                    value: UserStats::new(
+                        UserStatsValue::Ratio(found as u64, total as u64),
+                        AggregatorOps::Avg,
+                    ),
+                    phantom: PhantomData,
+                },
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Report the edges found in every `--split-coverage` region, as the `coverage_<region>` user stats
+    fn report_split_coverage(&mut self, state: &mut ClientState) -> Result<(), Error> {
+        if self.split_regions.is_empty() {
+            return Ok(());
+        }
+        let found = covered_edges_per_slice(state, "split_edges", SPLIT_MAP_SIZE)?;
+        for (name, found) in self.split_regions.iter().zip(found) {
+            self.mgr.fire(
+                state,
+                Event::UpdateUserStats {
+                    name: Cow::Owned(format!("coverage_{name}")),
+                    value: UserStats::new(UserStatsValue::Number(found as u64), AggregatorOps::Max),
+                    phantom: PhantomData,
+                },
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Evaluate the seeds `inputs`. With `--load-threads` above 1 their files are read ahead on that many threads,
+    /// the executions themselves stay on this one. With `--blind` no seed is interesting, they are all added.
+    fn load_inputs<Z, E>(
+        &mut self,
+        state: &mut ClientState,
+        fuzzer: &mut Z,
+        executor: &mut E,
+        inputs: &[PathBuf],
+    ) -> Result<(), Error>
+    where
+        Z: Evaluator<E, ClientMgr<M>, BytesInput, ClientState>,
+    {
+        if self.options.load_threads <= 1 {
+            return if self.options.blind {
+                state.load_initial_inputs_by_filenames_forced(fuzzer, executor, &mut self.mgr, inputs)
+            } else {
+                state.load_initial_inputs_by_filenames(fuzzer, executor, &mut self.mgr, inputs)
+            };
+        }
+
+        for (path, bytes) in prefetch(inputs, self.options.load_threads) {
+            match bytes {
+                Ok(bytes) if self.options.blind => {
+                    fuzzer.add_input(state, executor, &mut self.mgr, BytesInput::new(bytes))?;
+                }
+                Ok(bytes) => {
+                    fuzzer.evaluate_input(state, executor, &mut self.mgr, BytesInput::new(bytes))?;
+                }
+                Err(e) => log::warn!("Skipping seed {path:?}: {e}"),
+            }
+        }
+        Ok(())
+    }
+
+    /// The order to import `seeds` in: as given, or with `--smart-import` the greedy order of their coverage,
+    /// the seed adding the most edges first, so that an interrupted import already has the most useful seeds.
+    /// Every seed is run once more to get its coverage, a seed crashing the client is left out of the import
+    /// (see [`ImportScanMetadata`]). The order is written to `<output>/client_<id>/import_order`.
+    fn import_order<Z, E>(
+        &mut self,
+        state: &mut ClientState,
+        fuzzer: &mut Z,
+        executor: &mut E,
+        edges_handle: &Handle<EdgesObserver>,
+        seeds: Vec<PathBuf>,
+    ) -> Result<Vec<PathBuf>, Error>
+    where
+        Z: ExecutesInput<E, ClientMgr<M>, BytesInput, ClientState>,
+        E: HasObservers,
+        E::Observers: MatchNameRef,
+    {
+        if !self.options.smart_import {
+            return Ok(seeds);
+        }
+
+        let start = current_time();
+        let scan = state.metadata_or_insert_with(ImportScanMetadata::default);
+        if scan.seeds != seeds {
+            scan.seeds.clone_from(&seeds);
+            scan.coverage.clear();
+        }
+        for (i, seed) in seeds.iter().enumerate().skip(scan.coverage.len()) {
+            // Marked as not returned first, a crash restarts the client with it in the saved state
+            state.metadata_mut::<ImportScanMetadata>()?.coverage.push(None);
+            let input = BytesInput::new(fs::read(seed)?);
+            let covered = self.covered_entries(state, fuzzer, executor, edges_handle, &input)?;
+            state.metadata_mut::<ImportScanMetadata>()?.coverage[i] = Some(covered);
+        }
+
+        let scan = state.metadata::<ImportScanMetadata>()?;
+        let mut scanned = Vec::with_capacity(seeds.len());
+        let mut coverage = Vec::with_capacity(seeds.len());
+        for (seed, covered) in seeds.iter().zip(&scan.coverage) {
+            match covered {
+                Some(covered) => {
+                    scanned.push(seed.clone());
+                    coverage.push(covered.clone());
+                }
+                None => log::warn!("Skipping seed {seed:?}, it crashed the client while ordering the seeds"),
+            }
+        }
+        let seeds = scanned;
+        let order = greedy_order(&coverage);
+
+        let path = self
+            .options
+            .output_dir(self.client_description.clone())
+            .join("import_order");
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let report = order
+            .iter()
+            .map(|(i, gain)| format!("{gain} {}\n", seeds[*i].display()))
+            .collect::<String>();
+        fs::write(&path, report)?;
 
-            self.fuzz(&mut state, &mut fuzzer, &mut executor, &mut stages)
+        let useful = order.iter().take_while(|(_, gain)| *gain > 0).count();
+        println!(
+            "Ordered {} seeds by coverage in {:?}, {useful} of them add edges, the order is in {path:?}",
+            seeds.len(),
+            current_time().saturating_sub(start)
+        );
+        for (i, gain) in order.iter().take(10) {
+            log::info!("Importing {:?} (+{gain} edges)", seeds[*i]);
         }
+        Ok(order.into_iter().map(|(i, _)| seeds[i].clone()).collect())
+    }
+
+    /// The map entries `input` covers, running it once
+    fn covered_entries<Z, E>(
+        &mut self,
+        state: &mut ClientState,
+        fuzzer: &mut Z,
+        executor: &mut E,
+        edges_handle: &Handle<EdgesObserver>,
+        input: &BytesInput,
+    ) -> Result<Vec<usize>, Error>
+    where
+        Z: ExecutesInput<E, ClientMgr<M>, BytesInput, ClientState>,
+        E: HasObservers,
+        E::Observers: MatchNameRef,
+    {
+        fuzzer.execute_input(state, executor, &mut self.mgr, input)?;
+        let map = executor.observers()[edges_handle].to_vec();
+        Ok(map
+            .iter()
+            .enumerate()
+            .filter(|(_, hits)| **hits != 0)
+            .map(|(idx, _)| idx)
+            .collect())
+    }
+
+    /// Drop the corpus entries whose coverage the others already have, see `--cull-on-resume`:
+    /// every entry is run once more and taken in the greedy order of `--smart-import`, the entries adding no edge
+    /// to the ones before them are removed from the corpus and the queue. Returns how many were removed.
+    fn cull_corpus<Z, E>(
+        &mut self,
+        state: &mut ClientState,
+        fuzzer: &mut Z,
+        executor: &mut E,
+        edges_handle: &Handle<EdgesObserver>,
+    ) -> Result<usize, Error>
+    where
+        Z: ExecutesInput<E, ClientMgr<M>, BytesInput, ClientState> + HasScheduler<BytesInput, ClientState>,
+        Z::Scheduler: RemovableScheduler<BytesInput, ClientState>,
+        E: HasObservers,
+        E::Observers: MatchNameRef,
+    {
+        let ids = state.corpus().ids().collect::<Vec<CorpusId>>();
+        let mut coverage = Vec::with_capacity(ids.len());
+        for id in &ids {
+            let input = state.corpus().cloned_input_for_id(*id)?;
+            coverage.push(self.covered_entries(state, fuzzer, executor, edges_handle, &input)?);
+        }
+
+        let redundant = greedy_order(&coverage)
+            .into_iter()
+            .filter(|(_, gain)| *gain == 0)
+            .map(|(i, _)| ids[i])
+            .collect::<Vec<CorpusId>>();
+        for id in &redundant {
+            let testcase = state.corpus_mut().remove(*id)?;
+            fuzzer.scheduler_mut().on_remove(state, *id, &Some(testcase))?;
+        }
+        Ok(redundant.len())
+    }
+
+    /// Run every corpus entry `--warmup` times, only for qemu to translate their blocks.
+    /// The runs are not evaluated, they can't add to the corpus, so the early exec/sec and calibration
+    /// are not slowed down by the translation of the paths the corpus already takes.
+    fn warmup<Z, E>(
+        &mut self,
+        state: &mut ClientState,
+        fuzzer: &mut Z,
+        executor: &mut E,
+    ) -> Result<(), Error>
+    where
+        Z: ExecutesInput<E, ClientMgr<M>, BytesInput, ClientState>,
+    {
+        let start = current_time();
+        let ids = state.corpus().ids().collect::<Vec<_>>();
+        for _ in 0..self.options.warmup {
+            for id in &ids {
+                let input = state.corpus().cloned_input_for_id(*id)?;
+                fuzzer.execute_input(state, executor, &mut self.mgr, &input)?;
+            }
+        }
+        log::info!(
+            "Warmed up with {} runs of {} corpus entries in {:?}",
+            self.options.warmup,
+            ids.len(),
+            current_time().saturating_sub(start)
+        );
+        Ok(())
+    }
+
+    /// Attach to the standby broker on `port`, the broker lost. The new manager keeps the state restorer
+    /// shared with the respawner, so a restart goes on with the standby: the restorer saves the state
+    /// along with the manager's new broker connection.
+    #[cfg(not(feature = "simplemgr"))]
+    fn fail_over(&mut self, state: &mut ClientState, port: u16) -> Result<(), Error> {
+        println!(
+            "The broker on port {} is gone after {} executions, failing over to the standby on port {port}",
+            self.options.port,
+            state.executions()
+        );
+        let mut shmem_provider = StdShMemProvider::new()?;
+        let llmp_mgr = LlmpEventManager::builder().build_on_port(
+            shmem_provider.clone(),
+            port,
+            EventConfig::from_build_id(),
+            None,
+        )?;
+        let staterestorer = StateRestorer::from_env(&mut shmem_provider, _ENV_FUZZER_SENDER)?;
+        let old = mem::replace(
+            &mut self.mgr,
+            MonitorTypedEventManager::new(LlmpRestartingEventManager::new(llmp_mgr, staterestorer)),
+        );
+        // Dropping it would release the restorer's page, which the respawner and the new manager still use
+        mem::forget(old);
+        state.add_metadata(BrokerFailover { port });
+        Ok(())
+    }
+
+    /// No broker to fail over with the simple manager, `--standby-port` is rejected
+    #[cfg(feature = "simplemgr")]
+    fn fail_over(&mut self, _state: &mut ClientState, _port: u16) -> Result<(), Error> {
+        Err(Error::illegal_argument("--standby-port needs the launcher, not the simplemgr build"))
     }
 
     fn fuzz<Z, E, ST>(
@@ -381,33 +1704,287 @@ impl<M: Monitor> Instance<'_, M> {
         fuzzer: &mut Z,
         executor: &mut E,
         stages: &mut ST,
+        edges_handle: &Handle<EdgesObserver>,
     ) -> Result<(), Error>
     where
         Z: Fuzzer<E, ClientMgr<M>, BytesInput, ClientState, ST>
-        + Evaluator<E, ClientMgr<M>, BytesInput, ClientState>,
+        + Evaluator<E, ClientMgr<M>, BytesInput, ClientState>
+        + ExecutesInput<E, ClientMgr<M>, BytesInput, ClientState>
+        + HasScheduler<BytesInput, ClientState>,
+        Z::Scheduler: RemovableScheduler<BytesInput, ClientState>,
+        E: HasObservers,
+        E::Observers: MatchNameRef,
         ST: StagesTuple<E, ClientMgr<M>, ClientState, Z>,
     {
         let corpus_dirs = [self.options.input_dir()];
 
         if state.must_load_initial_inputs() {
-            state
-                .load_initial_inputs(fuzzer, executor, &mut self.mgr, &corpus_dirs)
-                .unwrap_or_else(|_| {
-                    println!("Failed to load initial corpus at {corpus_dirs:?}");
-                    process::exit(0);
-                });
-            println!("We imported {} inputs from disk.", state.corpus().count());
+            // A previous run of this client left its queue, flushed there with --corpus-tmpfs
+            let resumed = fs::read_dir(self.options.persistent_queue_dir(self.client_description.clone()))
+                .is_ok_and(|mut entries| entries.next().is_some());
+            let mut seen_seeds = if self.options.no_dedup_seeds {
+                None
+            } else {
+                Some(SeenSeeds::load(
+                    self.options.seen_seeds_file(self.client_description.clone()),
+                )?)
+            };
+            // The seeds skipped as already tested, reloaded if nothing else is left
+            let mut skipped = Vec::new();
+            let start = current_time();
+
+            let seeds = SeenSeeds::files(&corpus_dirs[0]).unwrap_or_else(|_| {
+                println!("Failed to load initial corpus at {corpus_dirs:?}");
+                process::exit(0);
+            });
+            if resumed {
+                // The previous queue holds the seeds that added coverage, so skipping those already tested loses none
+                let queue = Triage::inputs(&self.options.persistent_queue_dir(self.client_description.clone()))?;
+                self.load_inputs(state, fuzzer, executor, &queue)?;
+                println!("We imported {} entries of the previous queue.", state.corpus().count());
+            }
+
+            let before = state.corpus().count();
+            if let (true, Some(seen_seeds)) = (resumed, &mut seen_seeds) {
+                let (unseen, seen) = seen_seeds.split(&seeds)?;
+                let unseen = self.import_order(state, fuzzer, executor, edges_handle, unseen)?;
+                self.load_inputs(state, fuzzer, executor, &unseen)?;
+                println!(
+                    "We imported {} inputs from disk, skipping {} already tested.",
+                    state.corpus().count() - before,
+                    seen.len()
+                );
+                skipped.extend(seen);
+            } else {
+                // Recorded all the same, for the next start once the queue holds their coverage
+                if let Some(seen_seeds) = &mut seen_seeds {
+                    seen_seeds.record(&seeds)?;
+                }
+                let seeds = self.import_order(state, fuzzer, executor, edges_handle, seeds)?;
+                self.load_inputs(state, fuzzer, executor, &seeds)?;
+                println!("We imported {} inputs from disk.", state.corpus().count() - before);
+            }
+
+            if let Some(afl_queue) = self.options.afl_queue_dir() {
+                // AFL's file names only carry metadata, the inputs are the raw bytes.
+                // Listing the files ourselves skips the `.state` directory and its links to the queue entries.
+                let mut inputs = Triage::inputs(&afl_queue)?;
+                let total = inputs.len();
+                match (resumed, &mut seen_seeds) {
+                    (true, Some(seen_seeds)) => {
+                        let (unseen, seen) = seen_seeds.split(&inputs)?;
+                        inputs = unseen;
+                        skipped.extend(seen);
+                    }
+                    (false, Some(seen_seeds)) => seen_seeds.record(&inputs)?,
+                    (_, None) => {}
+                }
+                let before = state.corpus().count();
+                self.load_inputs(state, fuzzer, executor, &inputs)?;
+                println!(
+                    "We imported {} of {total} inputs from the AFL queue at {afl_queue:?}.",
+                    state.corpus().count() - before,
+                );
+            }
+
+            if let Some(seen_seeds) = &mut seen_seeds {
+                if state.corpus().count() == 0 && !skipped.is_empty() {
+                    log::warn!("Every seed was already tested, loading them again");
+                    self.load_inputs(state, fuzzer, executor, &skipped)?;
+                }
+                seen_seeds.save()?;
+            }
+            log::info!(
+                "Loaded the initial corpus in {:?}",
+                current_time().saturating_sub(start)
+            );
+            if self.options.cull_on_resume && resumed {
+                let start = current_time();
+                let total = state.corpus().count();
+                let culled = self.cull_corpus(state, fuzzer, executor, edges_handle)?;
+                println!(
+                    "Culled {culled} of {total} corpus entries adding no coverage in {:?}",
+                    current_time().saturating_sub(start)
+                );
+            }
+            self.print_slow_report(state)?;
         }
 
-        if let Some(iters) = self.options.iterations {
-            fuzzer.fuzz_loop_for(stages, executor, state, &mut self.mgr, iters)?;
+        // Every client process starts with an empty translation cache, restarted ones included
+        if self.options.warmup > 0 {
+            self.warmup(state, fuzzer, executor)?;
+        }
 
-            // It's important, that we store the state before restarting!
-            // Else, the parent will not respawn a new child and quit.
-            self.mgr.on_restart(state)?;
-        } else {
-            log::info!("Ready go into fuzzloop ...");
-            fuzzer.fuzz_loop(stages, executor, state, &mut self.mgr)?;
+        let queue_dir = self.options.queue_dir(self.client_description.clone());
+        let mut checkpointer = None;
+        if self.options.corpus_tmpfs.is_some() || self.options.checkpoint_interval.is_some() {
+            let mut new = Checkpointer::new(queue_dir);
+            if self.options.corpus_tmpfs.is_some() {
+                new = new.mirror(
+                    self.options.persistent_queue_dir(self.client_description.clone()),
+                    self.options.corpus_flush_interval,
+                );
+            }
+            if let Some(interval) = self.options.checkpoint_interval {
+                new = new.snapshots(
+                    self.options.checkpoints_dir(self.client_description.clone()),
+                    self.options.checkpoint_keep as usize,
+                    interval,
+                );
+            }
+            checkpointer = Some(new);
+        }
+
+        log::info!("Ready go into fuzzloop ...");
+        let mut iters = 0;
+        let mut last_estimate = current_time();
+        let mut mem_limit = self.options.mem_limit.map(|mb| mb * 1024 * 1024);
+        if let Some(limit) = mem_limit {
+            // A client starting above the limit would be restarted over and over
+            let rss = resident_memory().unwrap_or_default();
+            if rss >= limit {
+                log::warn!(
+                    "Resident memory {} MB already above --mem-limit at startup, not enforcing it",
+                    rss >> 20
+                );
+                mem_limit = None;
+            }
+        }
+        let mut last_mem_check = current_time();
+        let mut last_control_poll = current_time();
+        let mut control = Control::Run;
+        let mut corpus_size = state.corpus().count();
+        let mut last_new_entry = current_time();
+        // A client that already failed over stays with the standby
+        let mut broker_probe = self
+            .options
+            .standby_port
+            .filter(|_| !state.has_metadata::<BrokerFailover>())
+            .map(|_| BrokerProbe::new(self.options.port));
+        loop {
+            if let Some(path) = &self.options.control_file {
+                if current_time().saturating_sub(last_control_poll) >= CONTROL_POLL_INTERVAL {
+                    last_control_poll = current_time();
+                    control = Control::read(path);
+                    if control == Control::Pause {
+                        println!("Paused by {path:?} after {} executions", state.executions());
+                        // No execution, but the stats keep flowing so that the broker and the monitor see us alive
+                        while control == Control::Pause {
+                            thread::sleep(CONTROL_POLL_INTERVAL);
+                            self.mgr.maybe_report_progress(state, STATS_TIMEOUT_DEFAULT)?;
+                            control = Control::read(path);
+                        }
+                        last_control_poll = current_time();
+                        if control == Control::Run {
+                            println!("Resumed by {path:?}");
+                        }
+                    }
+                }
+            }
+
+            if let Some(port) = self.options.standby_port {
+                if broker_probe.as_mut().is_some_and(BrokerProbe::lost) {
+                    self.fail_over(state, port)?;
+                    broker_probe = None;
+                }
+            }
+
+            if self.options.iterations.is_some_and(|max| iters >= max) {
+                self.mgr.report_progress(state)?;
+                self.dump_coverage(state)?;
+                self.print_syscall_stats(state);
+                self.print_collisions(state);
+                self.print_slow_report(state)?;
+                if let Some(checkpointer) = &checkpointer {
+                    checkpointer.flush()?;
+                }
+
+                // It's important, that we store the state before restarting!
+                // Else, the parent will not respawn a new child and quit.
+                self.mgr.on_restart(state)?;
+                break;
+            }
+
+            // The start time is part of the state, so the budget survives client restarts
+            let elapsed = current_time().saturating_sub(*state.start_time());
+            let time_up = self.options.max_time.is_some_and(|max| elapsed >= max);
+            if time_up || control == Control::Stop {
+                if time_up {
+                    println!(
+                        "Time limit reached after {elapsed:?} and {} executions",
+                        state.executions()
+                    );
+                } else {
+                    println!(
+                        "Stopped by the control file after {elapsed:?} and {} executions",
+                        state.executions()
+                    );
+                }
+                self.dump_coverage(state)?;
+                self.print_syscall_stats(state);
+                self.print_collisions(state);
+                self.print_slow_report(state)?;
+                if let Some(checkpointer) = &checkpointer {
+                    checkpointer.flush()?;
+                }
+
+                // Tell the parent we are done on purpose, so it doesn't respawn us
+                self.mgr.send_exiting()?;
+                break;
+            }
+
+            fuzzer.fuzz_one(stages, executor, state, &mut self.mgr)?;
+            self.mgr.maybe_report_progress(state, STATS_TIMEOUT_DEFAULT)?;
+            iters += 1;
+
+            if let Some(plateau) = self.options.plateau_restart {
+                // The entries of the other clients count too, they share the coverage
+                if state.corpus().count() != corpus_size {
+                    corpus_size = state.corpus().count();
+                    last_new_entry = current_time();
+                } else if current_time().saturating_sub(last_new_entry) >= plateau {
+                    let seed = current_nanos();
+                    *state.rand_mut() = StdRand::with_seed(seed);
+                    println!(
+                        "No new corpus entry for {plateau:?}, restarting the RNG with seed {seed} after {} executions",
+                        state.executions()
+                    );
+                    last_new_entry = current_time();
+                }
+            }
+
+            if current_time().saturating_sub(last_estimate) >= STATS_TIMEOUT_DEFAULT {
+                self.report_coverage_estimate(state)?;
+                self.report_split_coverage(state)?;
+                self.report_syscall_stats(state)?;
+                self.report_collisions(state)?;
+                last_estimate = current_time();
+            }
+
+            if let Some(checkpointer) = checkpointer.as_mut() {
+                checkpointer.maybe_checkpoint()?;
+            }
+
+            if let Some(limit) = mem_limit {
+                if current_time().saturating_sub(last_mem_check) >= MEM_CHECK_INTERVAL {
+                    last_mem_check = current_time();
+                    let rss = resident_memory().unwrap_or_default();
+                    if rss >= limit {
+                        println!(
+                            "Resident memory {} MB above --mem-limit after {} executions, restarting",
+                            rss >> 20,
+                            state.executions()
+                        );
+                        if let Some(checkpointer) = &checkpointer {
+                            checkpointer.flush()?;
+                        }
+
+                        // The parent respawns us with the stored state, in a fresh process
+                        self.mgr.on_restart(state)?;
+                        break;
+                    }
+                }
+            }
         }
 
         Ok(())
@@ -1,16 +1,60 @@
 //! A libfuzzer-like fuzzer using qemu for binary-only coverage
 #[cfg(target_os = "linux")]
+mod attribution;
+#[cfg(target_os = "linux")]
+mod checkpoint;
+#[cfg(target_os = "linux")]
 mod client;
 #[cfg(target_os = "linux")]
+mod coverage;
+#[cfg(target_os = "linux")]
+mod debug;
+#[cfg(target_os = "linux")]
+mod diff;
+#[cfg(target_os = "linux")]
+mod encoding;
+#[cfg(target_os = "linux")]
+mod executors;
+#[cfg(target_os = "linux")]
+mod failover;
+#[cfg(target_os = "linux")]
 mod fuzzer;
 #[cfg(target_os = "linux")]
 mod harness;
 #[cfg(target_os = "linux")]
+mod health;
+#[cfg(target_os = "linux")]
+mod input_log;
+#[cfg(target_os = "linux")]
 mod instance;
+#[cfg(target_os = "linux")]
+mod merge;
 mod modules;
 #[cfg(target_os = "linux")]
+mod mutators;
+#[cfg(target_os = "linux")]
+mod notify;
+#[cfg(target_os = "linux")]
 mod options;
 #[cfg(target_os = "linux")]
+mod regress;
+#[cfg(target_os = "linux")]
+mod schedulers;
+#[cfg(target_os = "linux")]
+mod schema;
+#[cfg(target_os = "linux")]
+mod script;
+#[cfg(target_os = "linux")]
+mod seeds;
+#[cfg(target_os = "linux")]
+mod selftest;
+#[cfg(target_os = "linux")]
+mod stability;
+#[cfg(target_os = "linux")]
+mod stages;
+#[cfg(target_os = "linux")]
+mod triage;
+#[cfg(target_os = "linux")]
 mod version;
 mod feedbacks;
 
@@ -0,0 +1,133 @@
+use std::{
+    collections::{HashMap, HashSet},
+    env, fs,
+    path::Path,
+    process::{self, Command, Stdio},
+};
+
+use libafl::Error;
+use sha2::{Digest, Sha256};
+
+use crate::{
+    diff::{CoverageDiff, Edge},
+    options::FuzzerOptions,
+    seeds::greedy_order,
+    triage::{child_args, Triage},
+};
+
+/// Options that only make sense for the merge driver and must not be forwarded to the replays
+const MERGE_ONLY_ARGS: [(&str, bool); 3] = [("--merge-corpora", true), ("--out", true), ("--tui", false)];
+
+/// Merges several corpora into the smallest set of their inputs covering the same edges, see `--merge-corpora`.
+///
+/// Identical inputs are deduplicated by hash first, then every input left is replayed once,
+/// like the [`CoverageDiff`] does, by a child instance of this fuzzer running with `-r` and writing its edges.
+/// The inputs are kept in the greedy order of [`greedy_order`], until no input adds an edge.
+/// Inputs crashing the target have no coverage, they are dropped.
+pub struct CorpusMerge<'a> {
+    options: &'a FuzzerOptions,
+}
+
+impl<'a> CorpusMerge<'a> {
+    pub fn new(options: &'a FuzzerOptions) -> Self {
+        Self { options }
+    }
+
+    pub fn run(&self) -> Result<(), Error> {
+        let out = self
+            .options
+            .merge_out
+            .as_ref()
+            .ok_or_else(|| Error::empty_optional("No output directory for the merged corpus provided"))?;
+
+        let mut total = 0;
+        let mut hashes = HashSet::new();
+        let mut inputs = Vec::new();
+        for dir in &self.options.merge_corpora {
+            let files = Triage::inputs(dir)?;
+            total += files.len();
+            for file in files {
+                let hash = format!("{:x}", Sha256::digest(fs::read(&file)?));
+                if hashes.insert(hash.clone()) {
+                    inputs.push((file, hash));
+                }
+            }
+        }
+        println!(
+            "Loaded {total} inputs from {} corpora, {} of them unique",
+            self.options.merge_corpora.len(),
+            inputs.len()
+        );
+
+        // Every distinct edge gets an index, for the greedy order over sorted index lists
+        let mut indices = HashMap::<Edge, usize>::new();
+        let mut coverage = Vec::with_capacity(inputs.len());
+        for (i, (input, _)) in inputs.iter().enumerate() {
+            let mut edges = self
+                .edges(input)?
+                .into_iter()
+                .map(|edge| {
+                    let next = indices.len();
+                    *indices.entry(edge).or_insert(next)
+                })
+                .collect::<Vec<usize>>();
+            edges.sort_unstable();
+            edges.dedup();
+            log::info!("[{}/{}] {input:?}: {} edges", i + 1, inputs.len(), edges.len());
+            coverage.push(edges);
+        }
+
+        fs::create_dir_all(out)?;
+        let mut kept = 0;
+        for (i, gain) in greedy_order(&coverage) {
+            if gain == 0 {
+                break;
+            }
+            let (input, hash) = &inputs[i];
+            fs::copy(input, out.join(hash))?;
+            kept += 1;
+        }
+
+        println!(
+            "Kept {kept} of {} unique inputs in {out:?}, covering {} edges",
+            inputs.len(),
+            indices.len()
+        );
+        Ok(())
+    }
+
+    /// The edges hit by `input`, none if the replay crashed
+    fn edges(&self, input: &Path) -> Result<Vec<Edge>, Error> {
+        let edges_file = env::temp_dir().join(format!("h1k0_merge_edges_{}.txt", process::id()));
+        let _ = fs::remove_file(&edges_file);
+        let status = Command::new(env::current_exe()?)
+            .args(Self::replay_args(input, &edges_file))
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()?;
+
+        let edges = match fs::read_to_string(&edges_file) {
+            Ok(content) => CoverageDiff::parse_edges(&content)?,
+            Err(_) => {
+                log::warn!("No coverage for {input:?}, dropping it (child exited with {status})");
+                Vec::new()
+            }
+        };
+        let _ = fs::remove_file(&edges_file);
+        Ok(edges)
+    }
+
+    /// Our own command line with `-r <input>` before the target arguments
+    fn replay_args(input: &Path, edges_file: &Path) -> Vec<String> {
+        child_args(
+            &MERGE_ONLY_ARGS,
+            [
+                "-r".to_string(),
+                input.to_string_lossy().into_owned(),
+                "--edges-out".to_string(),
+                edges_file.to_string_lossy().into_owned(),
+            ],
+        )
+    }
+}
@@ -0,0 +1,158 @@
+use std::collections::HashMap;
+
+use libafl::{executors::ExitKind, observers::ObserversTuple, HasMetadata};
+use libafl_qemu::{
+    modules::{utils::filters::NopAddressFilter, EmulatorModule, EmulatorModuleTuple},
+    EmulatorModules, GuestAddr, GuestUsize, Hook, Qemu,
+};
+use serde::{Deserialize, Serialize};
+
+/// The blocks executed by the last run, as `(start, length, executions)`, see `--lcov-out`
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct BlockCoverage {
+    pub blocks: Vec<(GuestAddr, GuestUsize, u64)>,
+}
+
+libafl_bolts::impl_serdeany!(BlockCoverage);
+
+/// Counts the executions of every block, with the length qemu translated it with, into the
+/// [`BlockCoverage`] of the state. Every block execution is hooked, it's meant for replaying an input,
+/// not for fuzzing. Disabled by default.
+#[derive(Default, Debug)]
+pub struct BlockCoverageModule {
+    enabled: bool,
+    // The length and the executions of every block translated so far, by start address
+    blocks: HashMap<GuestAddr, (GuestUsize, u64)>,
+    filter: NopAddressFilter,
+}
+
+impl BlockCoverageModule {
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            blocks: HashMap::new(),
+            filter: NopAddressFilter,
+        }
+    }
+}
+
+impl<I, S> EmulatorModule<I, S> for BlockCoverageModule
+where
+    S: Unpin + HasMetadata,
+    I: Unpin,
+{
+    type ModuleAddressFilter = NopAddressFilter;
+
+    fn first_exec<ET>(
+        &mut self,
+        qemu: Qemu,
+        _emulator_modules: &mut EmulatorModules<ET, I, S>,
+        _state: &mut S,
+    ) where
+        ET: EmulatorModuleTuple<I, S>,
+    {
+        if !self.enabled {
+            return;
+        }
+
+        log::debug!("BlockCoverageModule::first_exec running ...");
+        let hook_id = _emulator_modules.blocks(
+            Hook::Function(gen_block_id::<ET, I, S>),
+            Hook::Function(on_block_translated::<ET, I, S>),
+            Hook::Function(on_block::<ET, I, S>),
+        );
+        log::debug!("Hook {:?} installed", hook_id);
+        // The blocks translated so far would never reach the hook
+        qemu.flush_jit();
+    }
+
+    fn pre_exec<ET>(
+        &mut self,
+        _qemu: Qemu,
+        _emulator_modules: &mut EmulatorModules<ET, I, S>,
+        _state: &mut S,
+        _input: &I,
+    ) where
+        ET: EmulatorModuleTuple<I, S>,
+    {
+        // The lengths are kept, the blocks stay translated across runs
+        self.blocks.values_mut().for_each(|(_, hits)| *hits = 0);
+    }
+
+    fn post_exec<OT, ET>(
+        &mut self,
+        _qemu: Qemu,
+        _emulator_modules: &mut EmulatorModules<ET, I, S>,
+        _state: &mut S,
+        _input: &I,
+        _observers: &mut OT,
+        _exit_kind: &mut ExitKind,
+    ) where
+        OT: ObserversTuple<I, S>,
+        ET: EmulatorModuleTuple<I, S>,
+    {
+        if self.enabled {
+            _state.metadata_or_insert_with(BlockCoverage::default).blocks = self
+                .blocks
+                .iter()
+                .filter(|(_, (_, hits))| *hits != 0)
+                .map(|(start, (len, hits))| (*start, *len, *hits))
+                .collect();
+        }
+    }
+
+    fn address_filter(&self) -> &Self::ModuleAddressFilter {
+        &self.filter
+    }
+
+    fn address_filter_mut(&mut self) -> &mut Self::ModuleAddressFilter {
+        &mut self.filter
+    }
+}
+
+/// The block id given to the execution hook is simply its address
+fn gen_block_id<ET, I, S>(
+    _qemu: Qemu,
+    _emulator_modules: &mut EmulatorModules<ET, I, S>,
+    _state: Option<&mut S>,
+    pc: GuestAddr,
+) -> Option<u64>
+where
+    S: Unpin + HasMetadata,
+    I: Unpin,
+    ET: EmulatorModuleTuple<I, S>,
+{
+    Some(pc as u64)
+}
+
+/// The length of a block is only known once it is translated
+fn on_block_translated<ET, I, S>(
+    _qemu: Qemu,
+    emulator_modules: &mut EmulatorModules<ET, I, S>,
+    _state: Option<&mut S>,
+    pc: GuestAddr,
+    len: GuestUsize,
+) where
+    S: Unpin + HasMetadata,
+    I: Unpin,
+    ET: EmulatorModuleTuple<I, S>,
+{
+    if let Some(module) = emulator_modules.get_mut::<BlockCoverageModule>() {
+        module.blocks.entry(pc).or_default().0 = len;
+    }
+}
+
+fn on_block<ET, I, S>(
+    _qemu: Qemu,
+    emulator_modules: &mut EmulatorModules<ET, I, S>,
+    _state: Option<&mut S>,
+    id: u64,
+) where
+    S: Unpin + HasMetadata,
+    I: Unpin,
+    ET: EmulatorModuleTuple<I, S>,
+{
+    if let Some(module) = emulator_modules.get_mut::<BlockCoverageModule>() {
+        module.blocks.entry(id as GuestAddr).or_default().1 += 1;
+    }
+}
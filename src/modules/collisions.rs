@@ -0,0 +1,205 @@
+use std::collections::HashSet;
+
+use libafl::{executors::ExitKind, observers::ObserversTuple, HasMetadata};
+use libafl_qemu::{
+    modules::{
+        utils::filters::{AddressFilter, NopAddressFilter},
+        EmulatorModule, EmulatorModuleTuple, StdEdgeCoverageChildModule, StdEdgeCoverageModule,
+    },
+    EmulatorModules, GuestAddr, Hook, Qemu,
+};
+use serde::{Deserialize, Serialize};
+
+/// The distinct block transitions of the current run, set if the run was sampled by the [`CollisionSampleModule`]
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct CollisionSample {
+    pub transitions: Option<usize>,
+}
+
+libafl_bolts::impl_serdeany!(CollisionSample);
+
+/// The distinct block transitions of the sampled runs and the coverage map slots they used, see `--coverage-diagnostics`
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct CollisionStats {
+    pub runs: u64,
+    pub transitions: u64,
+    pub slots: u64,
+}
+
+libafl_bolts::impl_serdeany!(CollisionStats);
+
+impl CollisionStats {
+    /// The estimated share of the edges sharing a map slot with another one, none before the first sample
+    pub fn rate(&self) -> Option<f64> {
+        (self.transitions > 0)
+            .then(|| 1.0 - (self.slots.min(self.transitions) as f64 / self.transitions as f64))
+    }
+}
+
+/// Records the distinct block transitions (previous block, current block) of one run every `interval`,
+/// into the [`CollisionSample`] of the state, to compare them with the map slots the run used.
+/// Like the edge module, it only counts the transitions from or to a block its address filter allows,
+/// the others never reach the map. Every block execution is hooked, so it's a diagnostic. Disabled without an `interval`.
+#[derive(Default, Debug)]
+pub struct CollisionSampleModule {
+    interval: Option<u64>,
+    runs: u64,
+    sampling: bool,
+    prev: GuestAddr,
+    prev_covered: bool,
+    transitions: HashSet<(GuestAddr, GuestAddr)>,
+    // The blocks translated so far that the edge module covers
+    covered: HashSet<GuestAddr>,
+    filter: NopAddressFilter,
+}
+
+impl CollisionSampleModule {
+    pub fn new(interval: Option<u64>) -> Self {
+        Self {
+            interval,
+            ..Default::default()
+        }
+    }
+
+    fn record(&mut self, pc: GuestAddr) {
+        if self.sampling {
+            let covered = self.covered.contains(&pc);
+            if covered || self.prev_covered {
+                self.transitions.insert((self.prev, pc));
+            }
+            self.prev = pc;
+            self.prev_covered = covered;
+        }
+    }
+}
+
+impl<I, S> EmulatorModule<I, S> for CollisionSampleModule
+where
+    S: Unpin + HasMetadata,
+    I: Unpin,
+{
+    type ModuleAddressFilter = NopAddressFilter;
+
+    fn first_exec<ET>(
+        &mut self,
+        qemu: Qemu,
+        _emulator_modules: &mut EmulatorModules<ET, I, S>,
+        _state: &mut S,
+    ) where
+        ET: EmulatorModuleTuple<I, S>,
+    {
+        if self.interval.is_none() {
+            return;
+        }
+
+        log::debug!("CollisionSampleModule::first_exec running ...");
+        let hook_id = _emulator_modules.blocks(
+            Hook::Function(gen_block_id::<ET, I, S>),
+            Hook::Empty,
+            Hook::Function(on_block::<ET, I, S>),
+        );
+        log::debug!("Hook {:?} installed", hook_id);
+        // The blocks translated so far would never reach the hook
+        qemu.flush_jit();
+    }
+
+    fn pre_exec<ET>(
+        &mut self,
+        _qemu: Qemu,
+        _emulator_modules: &mut EmulatorModules<ET, I, S>,
+        _state: &mut S,
+        _input: &I,
+    ) where
+        ET: EmulatorModuleTuple<I, S>,
+    {
+        let Some(interval) = self.interval else {
+            return;
+        };
+        self.runs += 1;
+        self.sampling = self.runs % interval == 0;
+        self.transitions.clear();
+        self.prev = 0;
+        self.prev_covered = false;
+        _state.metadata_or_insert_with(CollisionSample::default).transitions = None;
+    }
+
+    fn post_exec<OT, ET>(
+        &mut self,
+        _qemu: Qemu,
+        _emulator_modules: &mut EmulatorModules<ET, I, S>,
+        _state: &mut S,
+        _input: &I,
+        _observers: &mut OT,
+        _exit_kind: &mut ExitKind,
+    ) where
+        OT: ObserversTuple<I, S>,
+        ET: EmulatorModuleTuple<I, S>,
+    {
+        if self.sampling {
+            _state.metadata_or_insert_with(CollisionSample::default).transitions = Some(self.transitions.len());
+        }
+    }
+
+    fn address_filter(&self) -> &Self::ModuleAddressFilter {
+        &self.filter
+    }
+
+    fn address_filter_mut(&mut self) -> &mut Self::ModuleAddressFilter {
+        &mut self.filter
+    }
+}
+
+/// Whether the edge module's address filter allows `pc`, whichever variant the executor uses.
+/// With `--cover-lib` the filter grows at runtime, the JIT is then flushed so the blocks are translated again.
+fn edge_filter_allows<ET, I, S>(emulator_modules: &EmulatorModules<ET, I, S>, pc: GuestAddr) -> bool
+where
+    S: Unpin + HasMetadata,
+    I: Unpin,
+    ET: EmulatorModuleTuple<I, S>,
+{
+    if let Some(module) = emulator_modules.get::<StdEdgeCoverageModule>() {
+        EmulatorModule::<I, S>::address_filter(module).allowed(&pc)
+    } else if let Some(module) = emulator_modules.get::<StdEdgeCoverageChildModule>() {
+        EmulatorModule::<I, S>::address_filter(module).allowed(&pc)
+    } else {
+        true
+    }
+}
+
+/// The block id given to the execution hook is simply its address, whether it's covered is looked up once here
+fn gen_block_id<ET, I, S>(
+    _qemu: Qemu,
+    emulator_modules: &mut EmulatorModules<ET, I, S>,
+    _state: Option<&mut S>,
+    pc: GuestAddr,
+) -> Option<u64>
+where
+    S: Unpin + HasMetadata,
+    I: Unpin,
+    ET: EmulatorModuleTuple<I, S>,
+{
+    let covered = edge_filter_allows(emulator_modules, pc);
+    if let Some(module) = emulator_modules.get_mut::<CollisionSampleModule>() {
+        if covered {
+            module.covered.insert(pc);
+        } else {
+            module.covered.remove(&pc);
+        }
+    }
+    Some(pc as u64)
+}
+
+fn on_block<ET, I, S>(
+    _qemu: Qemu,
+    emulator_modules: &mut EmulatorModules<ET, I, S>,
+    _state: Option<&mut S>,
+    id: u64,
+) where
+    S: Unpin + HasMetadata,
+    I: Unpin,
+    ET: EmulatorModuleTuple<I, S>,
+{
+    if let Some(module) = emulator_modules.get_mut::<CollisionSampleModule>() {
+        module.record(id as GuestAddr);
+    }
+}
@@ -0,0 +1,126 @@
+use std::sync::Mutex;
+
+use lazy_static::lazy_static;
+use libafl::executors::ExitKind;
+use libafl_qemu::{
+    modules::{utils::filters::NopAddressFilter, EmulatorModule, EmulatorModuleTuple},
+    EmulatorModules, GuestAddr, Qemu, Regs,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::modules::BlockTraceModule;
+
+/// What the crash hook saw of a guest crash
+#[derive(Debug)]
+struct LastCrash {
+    signal: i32,
+    pc: Option<GuestAddr>,
+    trace: Vec<GuestAddr>,
+}
+
+lazy_static! {
+    // The crash hook runs inside qemu's signal path and the objective is evaluated by the
+    // executor's crash handler, neither of which can reach the other, so share the last crash here.
+    static ref LAST_CRASH: Mutex<Option<LastCrash>> = Mutex::new(None);
+}
+
+/// Classification of a single execution: how it ended, and where the guest faulted if it did.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrashInfo {
+    pub exit_kind: ExitKind,
+    pub signal: Option<i32>,
+    pub pc: Option<GuestAddr>,
+    /// The last blocks executed before the crash, oldest first, see `--crash-trace-depth`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub trace: Vec<GuestAddr>,
+}
+
+impl CrashInfo {
+    /// Build the info for an execution that ended with `exit_kind`, consuming the last recorded crash.
+    pub fn new(exit_kind: ExitKind) -> Self {
+        match LAST_CRASH.lock().unwrap().take() {
+            Some(last) => Self {
+                exit_kind,
+                signal: Some(last.signal),
+                pc: last.pc,
+                trace: last.trace,
+            },
+            None => Self {
+                exit_kind,
+                signal: None,
+                pc: None,
+                trace: Vec::new(),
+            },
+        }
+    }
+}
+
+libafl_bolts::impl_serdeany!(CrashInfo);
+
+/// Records the guest signal and faulting PC of every crash, see [`CrashInfo`].
+#[derive(Default, Debug)]
+pub struct CrashClassifierModule {
+    filter: NopAddressFilter,
+}
+
+impl CrashClassifierModule {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<I, S> EmulatorModule<I, S> for CrashClassifierModule
+where
+    S: Unpin,
+    I: Unpin,
+{
+    type ModuleAddressFilter = NopAddressFilter;
+
+    fn post_qemu_init<ET>(&mut self, _qemu: Qemu, emulator_modules: &mut EmulatorModules<ET, I, S>)
+    where
+        ET: EmulatorModuleTuple<I, S>,
+    {
+        log::debug!("CrashClassifierModule::post_qemu_init running ...");
+        emulator_modules.crash_function(on_crash::<ET, I, S>);
+    }
+
+    fn pre_exec<ET>(
+        &mut self,
+        _qemu: Qemu,
+        _emulator_modules: &mut EmulatorModules<ET, I, S>,
+        _state: &mut S,
+        _input: &I,
+    ) where
+        ET: EmulatorModuleTuple<I, S>,
+    {
+        // Never attribute a stale crash to the current input
+        LAST_CRASH.lock().unwrap().take();
+    }
+
+    fn address_filter(&self) -> &Self::ModuleAddressFilter {
+        &self.filter
+    }
+
+    fn address_filter_mut(&mut self) -> &mut Self::ModuleAddressFilter {
+        &mut self.filter
+    }
+}
+
+fn on_crash<ET, I, S>(qemu: Qemu, emulator_modules: &mut EmulatorModules<ET, I, S>, target_sig: i32)
+where
+    S: Unpin,
+    I: Unpin,
+    ET: EmulatorModuleTuple<I, S>,
+{
+    let pc: Option<GuestAddr> = qemu.read_reg(Regs::Pc).ok();
+    log::debug!("Guest crashed with signal {target_sig}, pc = {pc:#x?}");
+    let trace = emulator_modules
+        .get::<BlockTraceModule>()
+        .map(BlockTraceModule::trace)
+        .unwrap_or_default();
+    *LAST_CRASH.lock().unwrap() = Some(LastCrash {
+        signal: target_sig,
+        pc,
+        trace,
+    });
+}
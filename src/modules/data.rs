@@ -0,0 +1,94 @@
+use std::ops::Range;
+
+use libafl_qemu::{
+    modules::{utils::filters::NopAddressFilter, EmulatorModule, EmulatorModuleTuple},
+    EmulatorModules, GuestAddr, Qemu,
+};
+
+/// Restores writable guest memory ranges (by default `.data` and `.bss`) before every run,
+/// so that global state left by one input can't leak into the next one.
+#[derive(Default, Debug)]
+pub struct DataResetModule {
+    ranges: Vec<Range<GuestAddr>>,
+    saved: Vec<(GuestAddr, Vec<u8>)>,
+    filter: NopAddressFilter,
+}
+
+impl DataResetModule {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ranges to save at the first execution and restore afterwards, nothing is tracked by default
+    pub fn set_ranges(&mut self, ranges: Vec<Range<GuestAddr>>) {
+        self.ranges = ranges;
+    }
+
+    fn save(&mut self, qemu: Qemu) {
+        log::debug!("Saving writable data ranges ...");
+
+        self.saved = self
+            .ranges
+            .iter()
+            .filter_map(|range| {
+                let mut buf = vec![0; (range.end - range.start) as usize];
+                match qemu.read_mem(range.start, &mut buf) {
+                    Ok(()) => Some((range.start, buf)),
+                    Err(e) => {
+                        log::error!("Failed to save data range {range:#x?}: {e:?}, skipping ...");
+                        None
+                    }
+                }
+            })
+            .collect();
+    }
+
+    fn restore(&self, qemu: Qemu) {
+        self.saved.iter().for_each(|(addr, buf)| {
+            if let Err(e) = qemu.write_mem(*addr, buf) {
+                log::error!("Failed to restore data range @{addr:#x}: {e:?}, skipping ...");
+            }
+        });
+    }
+}
+
+impl<I, S> EmulatorModule<I, S> for DataResetModule
+where
+    S: Unpin,
+    I: Unpin,
+{
+    type ModuleAddressFilter = NopAddressFilter;
+
+    fn first_exec<ET>(
+        &mut self,
+        _qemu: Qemu,
+        _emulator_modules: &mut EmulatorModules<ET, I, S>,
+        _state: &mut S,
+    ) where
+        ET: EmulatorModuleTuple<I, S>,
+    {
+        log::debug!("DataResetModule::first_exec running ...");
+        self.save(_qemu);
+    }
+
+    fn pre_exec<ET>(
+        &mut self,
+        _qemu: Qemu,
+        _emulator_modules: &mut EmulatorModules<ET, I, S>,
+        _state: &mut S,
+        _input: &I,
+    ) where
+        ET: EmulatorModuleTuple<I, S>,
+    {
+        log::debug!("DataResetModule::pre_exec running ...");
+        self.restore(_qemu);
+    }
+
+    fn address_filter(&self) -> &Self::ModuleAddressFilter {
+        &self.filter
+    }
+
+    fn address_filter_mut(&mut self) -> &mut Self::ModuleAddressFilter {
+        &mut self.filter
+    }
+}
@@ -0,0 +1,154 @@
+use libafl::{inputs::HasTargetBytes, HasMetadata};
+use libafl_qemu::{
+    modules::{utils::filters::NopAddressFilter, EmulatorModule, EmulatorModuleTuple},
+    EmulatorModules, GuestAddr, Hook, Qemu, SyscallHookResult, SYS_write,
+};
+
+use crate::modules::{ExecMeta, CANARY};
+
+/// Don't scan more than this many bytes of a single write
+const MAX_SCANNED_WRITE: usize = 0x10000;
+
+/// A run of this many canary bytes in the output is taken for uninitialized memory
+const CANARY_RUN: usize = 8;
+
+/// Marks an execution as a finding (see [`ExecMeta::finding`]) when the target writes `pattern`,
+/// or, with [`FindingDetectorModule::detect_canary`], when it leaks the [`CANARY`] filling its memory
+#[derive(Default, Debug)]
+pub struct FindingDetectorModule {
+    pattern: Vec<u8>,
+    canary: bool,
+    // The input itself holds a canary run, its output can't tell apart uninitialized memory from the input
+    input_has_canary: bool,
+    filter: NopAddressFilter,
+}
+
+impl FindingDetectorModule {
+    pub fn new(pattern: Option<&str>) -> Self {
+        Self {
+            pattern: pattern.map(|p| p.as_bytes().to_vec()).unwrap_or_default(),
+            ..Default::default()
+        }
+    }
+
+    /// Flag the writes containing a run of [`CANARY`] bytes and the syscall arguments made of it,
+    /// i.e. the target likely read memory filled by the injector's `--canary-fill` that it never initialized
+    pub fn detect_canary(&mut self) {
+        self.canary = true;
+    }
+
+    fn check(&self, qemu: Qemu, sys_num: i64, args: &[GuestAddr; 6]) -> Option<&'static str> {
+        let canary = self.canary && !self.input_has_canary;
+        if canary && args.contains(&GuestAddr::from_ne_bytes([CANARY; size_of::<GuestAddr>()])) {
+            return Some("canary in syscall argument");
+        }
+        if sys_num != SYS_write {
+            return None;
+        }
+
+        let mut buf = vec![0; (args[2] as usize).min(MAX_SCANNED_WRITE)];
+        if qemu.read_mem(args[1], &mut buf).is_err() {
+            return None;
+        }
+        if !self.pattern.is_empty()
+            && buf
+                .windows(self.pattern.len())
+                .any(|w| w == self.pattern.as_slice())
+        {
+            return Some("output pattern");
+        }
+        if canary && has_canary_run(&buf) {
+            return Some("canary in output");
+        }
+        None
+    }
+}
+
+fn has_canary_run(buf: &[u8]) -> bool {
+    buf.windows(CANARY_RUN).any(|w| w.iter().all(|b| *b == CANARY))
+}
+
+impl<I, S> EmulatorModule<I, S> for FindingDetectorModule
+where
+    S: Unpin + HasMetadata,
+    I: Unpin + HasTargetBytes,
+{
+    type ModuleAddressFilter = NopAddressFilter;
+
+    fn first_exec<ET>(
+        &mut self,
+        _qemu: Qemu,
+        _emulator_modules: &mut EmulatorModules<ET, I, S>,
+        _state: &mut S,
+    ) where
+        ET: EmulatorModuleTuple<I, S>,
+    {
+        if self.pattern.is_empty() && !self.canary {
+            return;
+        }
+
+        log::debug!("FindingDetectorModule::first_exec running ...");
+        if let Some(hook_id) =
+            _emulator_modules.pre_syscalls(Hook::Function(syscall_hooks::<ET, I, S>))
+        {
+            log::debug!("Hook {:?} installed", hook_id);
+        } else {
+            log::error!("Failed to install hook");
+        }
+    }
+
+    fn pre_exec<ET>(
+        &mut self,
+        _qemu: Qemu,
+        _emulator_modules: &mut EmulatorModules<ET, I, S>,
+        _state: &mut S,
+        _input: &I,
+    ) where
+        ET: EmulatorModuleTuple<I, S>,
+    {
+        _state.metadata_or_insert_with(ExecMeta::new).finding = None;
+        self.input_has_canary = self.canary && has_canary_run(&_input.target_bytes());
+    }
+
+    fn address_filter(&self) -> &Self::ModuleAddressFilter {
+        &self.filter
+    }
+
+    fn address_filter_mut(&mut self) -> &mut Self::ModuleAddressFilter {
+        &mut self.filter
+    }
+}
+
+fn syscall_hooks<ET, I, S>(
+    _qemu: Qemu,
+    emulator_modules: &mut EmulatorModules<ET, I, S>,
+    _state: Option<&mut S>,
+    sys_num: i32,
+    a0: GuestAddr,
+    a1: GuestAddr,
+    a2: GuestAddr,
+    a3: GuestAddr,
+    a4: GuestAddr,
+    a5: GuestAddr,
+    _a6: GuestAddr,
+    _a7: GuestAddr,
+) -> SyscallHookResult
+where
+    S: Unpin + HasMetadata,
+    I: Unpin + HasTargetBytes,
+    ET: EmulatorModuleTuple<I, S>,
+{
+    let detector = emulator_modules
+        .get::<FindingDetectorModule>()
+        .expect("Failed to get FindingDetectorModule");
+
+    if let Some(reason) = detector.check(_qemu, sys_num as i64, &[a0, a1, a2, a3, a4, a5]) {
+        log::debug!("Finding: {reason}");
+        if let Some(state) = _state {
+            state.metadata_or_insert_with(ExecMeta::new).finding = Some(reason.to_string());
+        }
+    }
+
+    // Let the syscall happen anyway
+    SyscallHookResult::new(None)
+}
@@ -0,0 +1,77 @@
+use std::mem::size_of;
+
+use libafl_bolts::serdeany::SerdeAnyMap;
+use libafl_qemu::{
+    GuestAddr, Qemu, SyscallHookResult, SYS_clock_gettime, SYS_getrandom, SYS_gettimeofday,
+};
+
+use crate::modules::InputInjectorModule;
+
+/// The time seen by the target, in seconds since the epoch
+const FIXED_TIME: GuestAddr = 1_700_000_000;
+
+/// Register handlers making `getrandom`, `clock_gettime` and `gettimeofday` return fixed values:
+/// zeroed random bytes and a constant clock.
+///
+/// Calls served by the guest vDSO never reach the syscall hooks and stay nondeterministic.
+pub fn add_determinism_handlers(injector: &mut InputInjectorModule) {
+    injector.add_handler(SYS_getrandom, Box::new(handle_getrandom));
+    injector.add_handler(SYS_clock_gettime, Box::new(handle_clock_gettime));
+    injector.add_handler(SYS_gettimeofday, Box::new(handle_gettimeofday));
+}
+
+/// A guest `long` in guest byte order
+fn guest_word(value: GuestAddr) -> Vec<u8> {
+    if cfg!(feature = "be") {
+        value.to_be_bytes().to_vec()
+    } else {
+        value.to_le_bytes().to_vec()
+    }
+}
+
+/// `{ tv_sec = FIXED_TIME, tv_nsec/tv_usec = 0 }` at `addr`
+fn write_fixed_time(qemu: Qemu, addr: GuestAddr) -> GuestAddr {
+    let mut buf = guest_word(FIXED_TIME);
+    buf.resize(2 * size_of::<GuestAddr>(), 0);
+    match qemu.write_mem(addr, &buf) {
+        Ok(()) => 0,
+        // -EFAULT
+        Err(_) => (-14i64) as GuestAddr,
+    }
+}
+
+fn handle_getrandom(
+    _injector: &mut InputInjectorModule,
+    qemu: Qemu,
+    _metadata: Option<&mut SerdeAnyMap>,
+    args: &[GuestAddr; 8],
+) -> Option<SyscallHookResult> {
+    log::debug!("Getrandom syscall intercepted ...");
+    let zeroes = vec![0; args[1] as usize];
+    qemu.write_mem(args[0], &zeroes).ok()?;
+    Some(SyscallHookResult::new(Some(args[1])))
+}
+
+fn handle_clock_gettime(
+    _injector: &mut InputInjectorModule,
+    qemu: Qemu,
+    _metadata: Option<&mut SerdeAnyMap>,
+    args: &[GuestAddr; 8],
+) -> Option<SyscallHookResult> {
+    log::debug!("Clock_gettime syscall intercepted ...");
+    Some(SyscallHookResult::new(Some(write_fixed_time(qemu, args[1]))))
+}
+
+fn handle_gettimeofday(
+    _injector: &mut InputInjectorModule,
+    qemu: Qemu,
+    _metadata: Option<&mut SerdeAnyMap>,
+    args: &[GuestAddr; 8],
+) -> Option<SyscallHookResult> {
+    log::debug!("Gettimeofday syscall intercepted ...");
+    // The timezone argument is obsolete, leave it alone
+    if args[0] == 0 {
+        return Some(SyscallHookResult::new(Some(0)));
+    }
+    Some(SyscallHookResult::new(Some(write_fixed_time(qemu, args[0]))))
+}
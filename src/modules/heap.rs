@@ -0,0 +1,295 @@
+use std::collections::{HashMap, HashSet};
+
+use libafl::HasMetadata;
+use libafl_qemu::{
+    modules::{utils::filters::NopAddressFilter, EmulatorModule, EmulatorModuleTuple},
+    ArchExtras, CallingConvention, EmulatorModules, GuestAddr, Hook, Qemu, Regs,
+};
+
+use crate::modules::ExecMeta;
+
+/// The register holding the value a function returns
+#[cfg(feature = "x86_64")]
+const RETURN_REG: Regs = Regs::Rax;
+#[cfg(feature = "i386")]
+const RETURN_REG: Regs = Regs::Eax;
+#[cfg(feature = "aarch64")]
+const RETURN_REG: Regs = Regs::X0;
+#[cfg(feature = "arm")]
+const RETURN_REG: Regs = Regs::R0;
+#[cfg(feature = "mips")]
+const RETURN_REG: Regs = Regs::V0;
+#[cfg(feature = "ppc")]
+const RETURN_REG: Regs = Regs::R3;
+
+/// An allocator function tracked by the [`HeapCheckModule`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeapFn {
+    Malloc,
+    Calloc,
+    Realloc,
+    Free,
+}
+
+impl HeapFn {
+    pub const ALL: [HeapFn; 4] = [HeapFn::Malloc, HeapFn::Calloc, HeapFn::Realloc, HeapFn::Free];
+
+    /// The symbol of the function
+    pub fn symbol(self) -> &'static str {
+        match self {
+            HeapFn::Malloc => "malloc",
+            HeapFn::Calloc => "calloc",
+            HeapFn::Realloc => "realloc",
+            HeapFn::Free => "free",
+        }
+    }
+}
+
+/// An allocation waiting for its function to return, at `ret`
+#[derive(Debug)]
+struct PendingCall {
+    ret: GuestAddr,
+    size: GuestAddr,
+    // The block `realloc` was given
+    old: Option<GuestAddr>,
+}
+
+/// A lightweight heap checker for `--heap-check`: breakpoints on the allocator's entry points (see
+/// [`HeapCheckModule::set_functions`]) track the blocks allocated during a run, and a `free` (or `realloc`)
+/// of a block already freed by the run, or of a pointer no allocator could have returned, marks it as
+/// a finding (see [`ExecMeta::finding`]).
+///
+/// The blocks allocated before the harness entry are unknown to it, so a pointer that was never seen is only
+/// flagged when it lies inside a known block, in the stack, in a file mapping or in no mapping at all,
+/// otherwise it is taken for such a block.
+/// Its results are best-effort: unlike ASan it sees neither the accesses to freed memory nor the overflows.
+#[derive(Default, Debug)]
+pub struct HeapCheckModule {
+    functions: Vec<(GuestAddr, HeapFn)>,
+    // The return addresses already hooked
+    return_sites: HashSet<GuestAddr>,
+    pending: Vec<PendingCall>,
+    // The blocks allocated during the run, with their size
+    live: HashMap<GuestAddr, GuestAddr>,
+    // The blocks freed during the run and not allocated again since
+    freed: HashSet<GuestAddr>,
+    filter: NopAddressFilter,
+}
+
+impl HeapCheckModule {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The allocator functions to break on, nothing is tracked by default
+    pub fn set_functions(&mut self, functions: Vec<(GuestAddr, HeapFn)>) {
+        self.functions = functions;
+    }
+
+    fn allocated(&mut self, ptr: GuestAddr, size: GuestAddr) {
+        if ptr == 0 {
+            return;
+        }
+        self.freed.remove(&ptr);
+        self.live.insert(ptr, size);
+    }
+
+    /// Release `ptr`, the violation if it can't be released
+    fn release(&mut self, qemu: Qemu, ptr: GuestAddr, what: &str) -> Option<String> {
+        if ptr == 0 {
+            return None;
+        }
+        if self.live.remove(&ptr).is_some() {
+            self.freed.insert(ptr);
+            return None;
+        }
+        if self.freed.contains(&ptr) {
+            return Some(format!("double {what} of {ptr:#x}"));
+        }
+        if let Some((start, _)) = self
+            .live
+            .iter()
+            .find(|(start, size)| (**start..**start + **size).contains(&ptr))
+        {
+            return Some(format!("{what} of {ptr:#x}, inside the block at {start:#x}"));
+        }
+        match qemu.mappings().find(|m| (m.start()..m.end()).contains(&ptr)) {
+            None => Some(format!("{what} of unmapped pointer {ptr:#x}")),
+            Some(mapping) => match mapping.path() {
+                Some("[stack]") => Some(format!("{what} of stack pointer {ptr:#x}")),
+                Some(path) if !path.is_empty() && !path.starts_with('[') => {
+                    Some(format!("{what} of {ptr:#x}, in {path}"))
+                }
+                // Likely allocated before the harness entry, a second free is caught
+                _ => {
+                    self.freed.insert(ptr);
+                    None
+                }
+            },
+        }
+    }
+
+    fn on_entry(&mut self, qemu: Qemu, function: HeapFn) -> Option<String> {
+        let arg = |idx| -> GuestAddr {
+            qemu.read_function_argument(CallingConvention::Cdecl, idx)
+                .unwrap_or_default()
+        };
+        let (size, old) = match function {
+            HeapFn::Free => return self.release(qemu, arg(0), "free"),
+            HeapFn::Malloc => (arg(0), None),
+            HeapFn::Calloc => (arg(0).wrapping_mul(arg(1)), None),
+            HeapFn::Realloc => {
+                let old = arg(0);
+                if old != 0 && self.freed.contains(&old) {
+                    return Some(format!("realloc of freed block {old:#x}"));
+                }
+                (arg(1), Some(old))
+            }
+        };
+        let Ok(ret) = qemu.read_return_address::<GuestAddr>() else {
+            return None;
+        };
+        self.pending.push(PendingCall { ret, size, old });
+        None
+    }
+
+    fn on_return(&mut self, qemu: Qemu, pc: GuestAddr) {
+        let Some(idx) = self.pending.iter().rposition(|call| call.ret == pc) else {
+            // Reached without a call, e.g. a loop back to the return site
+            return;
+        };
+        let call = self.pending.remove(idx);
+        let ptr: GuestAddr = qemu.read_reg(RETURN_REG).unwrap_or_default();
+        if let Some(old) = call.old.filter(|old| *old != 0) {
+            // A failed realloc keeps the old block
+            if ptr == 0 && call.size != 0 {
+                return;
+            }
+            self.live.remove(&old);
+            if ptr != old {
+                self.freed.insert(old);
+            }
+        }
+        self.allocated(ptr, call.size);
+    }
+}
+
+impl<I, S> EmulatorModule<I, S> for HeapCheckModule
+where
+    S: Unpin + HasMetadata,
+    I: Unpin,
+{
+    type ModuleAddressFilter = NopAddressFilter;
+
+    fn first_exec<ET>(
+        &mut self,
+        _qemu: Qemu,
+        emulator_modules: &mut EmulatorModules<ET, I, S>,
+        _state: &mut S,
+    ) where
+        ET: EmulatorModuleTuple<I, S>,
+    {
+        if self.functions.is_empty() {
+            return;
+        }
+
+        log::debug!("HeapCheckModule::first_exec running ...");
+        for (addr, function) in &self.functions {
+            if let Some(hook_id) =
+                emulator_modules.instructions(*addr, Hook::Function(on_function::<ET, I, S>), true)
+            {
+                log::debug!("Hook {:?} installed on {} @ {addr:#x}", hook_id, function.symbol());
+            } else {
+                log::error!("Failed to install hook on {}", function.symbol());
+            }
+        }
+    }
+
+    fn pre_exec<ET>(
+        &mut self,
+        _qemu: Qemu,
+        _emulator_modules: &mut EmulatorModules<ET, I, S>,
+        _state: &mut S,
+        _input: &I,
+    ) where
+        ET: EmulatorModuleTuple<I, S>,
+    {
+        // The snapshot brings the heap back to the harness entry
+        self.pending.clear();
+        self.live.clear();
+        self.freed.clear();
+    }
+
+    fn address_filter(&self) -> &Self::ModuleAddressFilter {
+        &self.filter
+    }
+
+    fn address_filter_mut(&mut self) -> &mut Self::ModuleAddressFilter {
+        &mut self.filter
+    }
+}
+
+/// The entry of an allocator function: check a release, or wait for the return of an allocation
+fn on_function<ET, I, S>(
+    qemu: Qemu,
+    emulator_modules: &mut EmulatorModules<ET, I, S>,
+    state: Option<&mut S>,
+    pc: GuestAddr,
+) where
+    S: Unpin + HasMetadata,
+    I: Unpin,
+    ET: EmulatorModuleTuple<I, S>,
+{
+    let Some(module) = emulator_modules.get_mut::<HeapCheckModule>() else {
+        return;
+    };
+    let Some(function) = module
+        .functions
+        .iter()
+        .find_map(|(addr, function)| (*addr == pc).then_some(*function))
+    else {
+        return;
+    };
+
+    let violation = module.on_entry(qemu, function);
+    let new_site = module
+        .pending
+        .last()
+        .map(|call| call.ret)
+        .filter(|ret| function != HeapFn::Free && module.return_sites.insert(*ret));
+    // The return site is hooked the first time it is seen, its block may already be translated
+    if let Some(ret) = new_site {
+        if emulator_modules
+            .instructions(ret, Hook::Function(on_return::<ET, I, S>), true)
+            .is_none()
+        {
+            log::error!("Failed to install hook @ {ret:#x}");
+        }
+    }
+
+    if let Some(violation) = violation {
+        log::debug!("Finding: {violation}");
+        if let Some(state) = state {
+            let meta = state.metadata_or_insert_with(ExecMeta::new);
+            // The first violation of the run is the one reported
+            if meta.finding.is_none() {
+                meta.finding = Some(violation);
+            }
+        }
+    }
+}
+
+fn on_return<ET, I, S>(
+    qemu: Qemu,
+    emulator_modules: &mut EmulatorModules<ET, I, S>,
+    _state: Option<&mut S>,
+    pc: GuestAddr,
+) where
+    S: Unpin + HasMetadata,
+    I: Unpin,
+    ET: EmulatorModuleTuple<I, S>,
+{
+    if let Some(module) = emulator_modules.get_mut::<HeapCheckModule>() {
+        module.on_return(qemu, pc);
+    }
+}
@@ -1,37 +1,415 @@
-use std::process::abort;
+use std::{fmt, fs, mem, ops::Range, process::abort, time::Duration};
 
-use libafl::{inputs::HasTargetBytes, HasMetadata};
+use libafl::{executors::ExitKind, inputs::HasTargetBytes, observers::ObserversTuple, HasMetadata};
+use libafl_bolts::{current_time, serdeany::SerdeAnyMap};
 use libafl_qemu::{
-    modules::{utils::filters::NopAddressFilter, EmulatorModule, EmulatorModuleTuple}, EmulatorModules, GuestAddr, Hook, Qemu, SYS_exit, SYS_exit_group, SYS_mmap, SYS_munmap, SYS_read, SyscallHookResult
+    modules::{utils::filters::NopAddressFilter, EmulatorModule, EmulatorModuleTuple}, ArchExtras, CallingConvention, EmulatorModules, GuestAddr, GuestReg, Hook, Qemu, SYS_close, SYS_exit, SYS_exit_group, SYS_kill, SYS_mmap, SYS_munmap, SYS_openat, SYS_pipe2, SYS_read, SYS_tgkill, SYS_tkill, SYS_write, SyscallHookResult
+};
+#[cfg(not(any(feature = "aarch64", feature = "hexagon")))]
+use libafl_qemu::SYS_pipe;
+#[cfg(any(feature = "i386", feature = "x86_64"))]
+use libafl_qemu::Regs;
+use nix::{
+    fcntl::{fcntl, FcntlArg, OFlag},
+    unistd::_exit,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    harness::MAX_INPUT_SIZE,
+    modules::{ExecMeta, CANARY},
+    options::{CallConv, Encoding, EofBehavior},
+    schema::Schema,
+    script::{Script, ScriptStep},
 };
 
-use crate::modules::ExecMeta;
+/// SIGABRT is 6 on every Linux guest we support
+const SIGABRT: GuestAddr = 6;
+/// Same for EIO, which is 5
+const EIO: i64 = 5;
+/// And ENOMEM, which is 12
+const ENOMEM: i64 = 12;
 
-#[derive(Default, Debug)]
+/// The SysV shared memory syscalls, see [`InputInjectorModule::set_shm_inject`].
+/// The generic table (aarch64, hexagon) and x86_64 always had them, arm since EABI.
+/// i386, ppc and mips (o32) only got them in Linux 5.1, with the same numbers, before that
+/// (and still with older libcs) they go through the `ipc` multiplexer, also served.
+#[cfg(any(feature = "aarch64", feature = "hexagon"))]
+mod shm_nr {
+    pub const SHMGET: i64 = 194;
+    pub const SHMCTL: i64 = 195;
+    pub const SHMAT: i64 = 196;
+    pub const SHMDT: i64 = 197;
+}
+#[cfg(feature = "x86_64")]
+mod shm_nr {
+    pub const SHMGET: i64 = 29;
+    pub const SHMAT: i64 = 30;
+    pub const SHMCTL: i64 = 31;
+    pub const SHMDT: i64 = 67;
+}
+#[cfg(feature = "arm")]
+mod shm_nr {
+    pub const SHMAT: i64 = 305;
+    pub const SHMDT: i64 = 306;
+    pub const SHMGET: i64 = 307;
+    pub const SHMCTL: i64 = 308;
+}
+#[cfg(any(feature = "i386", feature = "ppc"))]
+mod shm_nr {
+    pub const IPC: i64 = 117;
+    pub const SHMGET: i64 = 395;
+    pub const SHMCTL: i64 = 396;
+    pub const SHMAT: i64 = 397;
+    pub const SHMDT: i64 = 398;
+}
+#[cfg(feature = "mips")]
+mod shm_nr {
+    pub const IPC: i64 = 4117;
+    pub const SHMGET: i64 = 4395;
+    pub const SHMCTL: i64 = 4396;
+    pub const SHMAT: i64 = 4397;
+    pub const SHMDT: i64 = 4398;
+}
+/// The `ipc` calls of the shared memory functions, the version is in the upper 16 bits
+#[cfg(any(feature = "i386", feature = "ppc", feature = "mips"))]
+mod ipc_call {
+    pub const SHMAT: u64 = 21;
+    pub const SHMDT: u64 = 22;
+    pub const SHMGET: u64 = 23;
+    pub const SHMCTL: u64 = 24;
+}
+/// The id of the segment served the input, not one the kernel hands out in practice
+const SHM_INJECT_ID: GuestAddr = 0x4655_5a5a;
+/// EINVAL, for a segment larger than the input buffer
+const EINVAL: i64 = 22;
+
+/// A syscall handler registered with [`InputInjectorModule::add_handler`].
+///
+/// It is called with the injector (to reach the current input and its guest buffer), the qemu instance,
+/// the state's metadata (absent if qemu has no state at hand) and the 8 raw syscall arguments.
+/// Returning `Some(result)` skips the syscall and gives `result` to the guest,
+/// returning `None` passes the syscall on to the next handler, and eventually to the guest.
+pub type SyscallHandler = Box<
+    dyn FnMut(&mut InputInjectorModule, Qemu, Option<&mut SerdeAnyMap>, &[GuestAddr; 8]) -> Option<SyscallHookResult>,
+>;
+
+/// The time spent injecting the input (writing it to the guest and serving its syscalls) over `runs` runs,
+/// only recorded by a timed injector, see [`InputInjectorModule::set_timed`]
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct InjectionTime {
+    pub total: Duration,
+    pub runs: u64,
+}
+
+libafl_bolts::impl_serdeany!(InjectionTime);
+
+/// A global of the target the input is written to on top of the input buffer, see `--inject-symbol`
+#[derive(Debug, Clone, Copy)]
+pub struct SymbolTarget {
+    pub addr: GuestAddr,
+    /// The size of the global, longer inputs are truncated to it
+    pub size: usize,
+    /// The global receiving the length of the input, and its size in bytes
+    pub len: Option<(GuestAddr, usize)>,
+}
+
+#[derive(Default)]
 pub struct InputInjectorModule {
-    // Save the Mutator's BytesInput
+    // Save the Mutator's BytesInput, it is not modified until the next pre_exec
     input: Vec<u8>,
+    // How much of the input has already been served by read()
+    read_pos: usize,
+    // With `multipart`, the segments of the input and the one read() is serving
+    multipart: bool,
+    segments: Vec<Range<usize>>,
+    segment: usize,
+    // With a `script`, the segments are only served in the order of its steps, `step` being the current one
+    script: Option<Script>,
+    step: usize,
+    // What read() returns once `read_pos` reached the end of the input
+    eof: EofBehavior,
     input_addr: GuestAddr,
     max_size: usize,
+    // Inputs shorter than this are zero-padded before injection
+    min_size: usize,
+    // With a selector, the first byte picks the encoding the rest is decoded with, before the schema
+    selector: Option<Vec<Encoding>>,
+    // Optional structure applied to the mutated bytes before injection
+    schema: Option<Schema>,
+    // With a window, the magic mmap()s map the next `window` bytes of the input at `window_pos`, one after the other
+    window: Option<usize>,
+    window_pos: usize,
+    // With a calling convention, the input buffer and its length are also passed to the harness entry as arguments
+    entry_args: Option<CallConv>,
+    // The global the input is also written to before every run
+    symbol_target: Option<SymbolTarget>,
+    // With `shm_inject`, the `shmget` of `shm_key` (or any) gets the input buffer's segment, `shm_id` once it did
+    shm_inject: bool,
+    shm_key: Option<u32>,
+    shm_id: Option<GuestAddr>,
+    // Guest path of the fuzzed file, its mmap()s are served the input buffer
+    mmap_path: Option<Vec<u8>>,
+    // The fd the target got for `mmap_path`, once its openat() returned
+    mmap_fd: Option<GuestAddr>,
+    // Set between the openat() of `mmap_path` and its return
+    opening: bool,
+    // With `auto_pipe`, read() only serves the input to the read ends of the pipes in `pipe_fds`
+    auto_pipe: bool,
+    pipe_fds: Vec<GuestAddr>,
+    // Whether a SIGABRT raised by the target is a clean exit rather than a crash
+    ignore_aborts: bool,
+    // Whether each run is a forked child, which can simply exit
+    forked: bool,
+    // Whether the input buffer past the input is filled with `CANARY`
+    canary_fill: bool,
+    // `max_size` canary bytes, written under the input
+    canary_buf: Vec<u8>,
+    // Whether the injection time of every run is added to the state's `InjectionTime`, and that of the current one
+    timed: bool,
+    injection_time: Duration,
+    handlers: Vec<(i64, SyscallHandler)>,
+}
+
+impl fmt::Debug for InputInjectorModule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("InputInjectorModule")
+            .field("input_len", &self.input.len())
+            .field("read_pos", &self.read_pos)
+            .field("multipart", &self.multipart)
+            .field("segments", &self.segments.len())
+            .field("script", &self.script)
+            .field("step", &self.step)
+            .field("eof", &self.eof)
+            .field("input_addr", &self.input_addr)
+            .field("max_size", &self.max_size)
+            .field("min_size", &self.min_size)
+            .field("selector", &self.selector)
+            .field("schema", &self.schema)
+            .field("window", &self.window)
+            .field("entry_args", &self.entry_args)
+            .field("symbol_target", &self.symbol_target)
+            .field("shm_inject", &self.shm_inject)
+            .field("shm_key", &self.shm_key)
+            .field("shm_id", &self.shm_id)
+            .field("mmap_path", &self.mmap_path.as_deref().map(String::from_utf8_lossy))
+            .field("mmap_fd", &self.mmap_fd)
+            .field("auto_pipe", &self.auto_pipe)
+            .field("pipe_fds", &self.pipe_fds)
+            .field("ignore_aborts", &self.ignore_aborts)
+            .field("forked", &self.forked)
+            .field("canary_fill", &self.canary_fill)
+            .field("timed", &self.timed)
+            .field(
+                "handlers",
+                &self.handlers.iter().map(|(num, _)| *num).collect::<Vec<i64>>(),
+            )
+            .finish()
+    }
 }
 
 impl InputInjectorModule {
+    /// The injector with the built-in handlers for `read`, `mmap`, `munmap`, `exit`/`exit_group`
+    /// and the `SIGABRT`s sent by `kill`/`tkill`/`tgkill` registered
     pub fn new() -> Self {
-        Self {
-            max_size: 1048576,
+        let mut module = Self {
+            max_size: MAX_INPUT_SIZE,
             ..Default::default()
-        }
+        };
+        module.add_handler(SYS_read, Box::new(handle_read));
+        module.add_handler(SYS_mmap, Box::new(handle_mmap));
+        module.add_handler(SYS_munmap, Box::new(handle_munmap));
+        module.add_handler(SYS_exit, Box::new(handle_exit));
+        module.add_handler(SYS_exit_group, Box::new(handle_exit));
+        module.add_handler(SYS_kill, Box::new(handle_kill));
+        module.add_handler(SYS_tkill, Box::new(handle_kill));
+        module.add_handler(SYS_tgkill, Box::new(handle_tgkill));
+        module
+    }
+
+    /// Treat the target's own `abort()` (i.e. a `SIGABRT` it sends itself) like a clean exit instead of a crash
+    pub fn ignore_aborts(&mut self) {
+        self.ignore_aborts = true;
+    }
+
+    /// Runs happen in forked children, see `--executor fork`.
+    /// A child exits with the target instead of aborting, as the [`ExecMeta`] it would set dies with it.
+    pub fn set_forked(&mut self) {
+        self.forked = true;
+    }
+
+    /// Fill the input buffer past the input with [`CANARY`], so that reading it uninitialized shows up in the output, see [`crate::modules::FindingDetectorModule::detect_canary`]
+    pub fn set_canary_fill(&mut self) {
+        self.canary_fill = true;
+    }
+
+    /// Record the time spent injecting the input in the state's [`InjectionTime`], see `--bench`.
+    /// It times every intercepted syscall, so it's not meant for fuzzing.
+    pub fn set_timed(&mut self) {
+        self.timed = true;
+    }
+
+    /// Intercept `sys_num` with `handler`, see [`SyscallHandler`].
+    /// Handlers of the same syscall are tried from the most recently added one,
+    /// so a handler added after [`InputInjectorModule::new`] takes precedence over the built-in one.
+    pub fn add_handler(&mut self, sys_num: i64, handler: SyscallHandler) {
+        self.handlers.push((sys_num, handler));
     }
 
     pub fn set_input_addr(&mut self, addr: GuestAddr) {
         self.input_addr = addr;
     }
+
+    /// Truncate longer inputs to `size`, which can't exceed the guest buffer
+    /// Pass the input buffer and the length of the input as the first two arguments of the harness entry,
+    /// following `conv`, before every run, see `--calling-convention`
+    pub fn set_entry_args(&mut self, conv: CallConv) {
+        self.entry_args = Some(conv);
+    }
+
+    pub fn set_max_size(&mut self, size: usize) {
+        self.max_size = size.min(MAX_INPUT_SIZE);
+    }
+
+    pub fn set_min_size(&mut self, size: usize) {
+        self.min_size = size.min(self.max_size);
+    }
+
+    pub fn set_eof_behavior(&mut self, eof: EofBehavior) {
+        self.eof = eof;
+    }
+
+    /// Split the input into segments of a 16 bit little-endian length followed by that many bytes,
+    /// and never let a `read()` go past the end of a segment, so that each read gets one message.
+    /// A truncated last segment gets the rest of the input.
+    pub fn set_multipart(&mut self) {
+        self.multipart = true;
+    }
+
+    /// Serve the segments following `script`: a `read` step is a segment, served once the previous `write` steps
+    /// happened, i.e. the target wrote its responses. A read during a `write` step gets 0 (end of file),
+    /// the session deviates from the script. After the last step the segments are served as with `multipart` alone.
+    pub fn set_script(&mut self, script: Script) {
+        self.multipart = true;
+        self.script = Some(script);
+        self.add_handler(SYS_write, Box::new(handle_script_write));
+    }
+
+    /// The current step of the script, if any is left
+    fn script_step(&self) -> Option<ScriptStep> {
+        self.script.as_ref().and_then(|script| script.steps.get(self.step).copied())
+    }
+
+    /// Consume the first byte of every input to pick one of `encodings` (the byte modulo their count),
+    /// and decode the rest of the input with it. The target never sees the selector byte.
+    pub fn set_selector(&mut self, encodings: Vec<Encoding>) {
+        self.selector = Some(encodings);
+    }
+
+    pub fn set_schema(&mut self, schema: Schema) {
+        self.schema = Some(schema);
+    }
+
+    /// Serve the input through the magic `PROT_READ, MAP_SHARED` `mmap`s in windows of `size` bytes: each one maps the next
+    /// window of the input, at the start of the input buffer, for streaming parsers mapping their input chunk by chunk.
+    /// Past the end of the input, the `--eof-behavior` applies (`error` fails the `mmap` with `ENOMEM`).
+    pub fn set_mmap_window(&mut self, size: usize) {
+        self.window = Some(size.min(MAX_INPUT_SIZE));
+    }
+
+    /// Also write the input to the global `target` before every run, for harnesses reading it from a buffer
+    /// like `uint8_t fuzz_buf[]` rather than through syscalls, and its length to the length global if any.
+    pub fn set_symbol_target(&mut self, target: SymbolTarget) {
+        self.symbol_target = Some(target);
+    }
+
+    /// Serve the input through SysV shared memory, for targets whose workers read it from a segment:
+    /// the `shmget` of `key` (of any key without one) gets a segment of its own, that `shmat` maps to the
+    /// input buffer, `shmdt` and `shmctl` on it succeed without doing anything. The guest's children inherit
+    /// the buffer, so every worker forked during a run sees its input. The other segments are real ones.
+    pub fn set_shm_inject(&mut self, key: Option<u32>) {
+        self.shm_inject = true;
+        self.shm_key = key;
+        self.add_handler(shm_nr::SHMGET, Box::new(handle_shmget));
+        self.add_handler(shm_nr::SHMAT, Box::new(handle_shmat));
+        self.add_handler(shm_nr::SHMDT, Box::new(handle_shmdt));
+        self.add_handler(shm_nr::SHMCTL, Box::new(handle_shmctl));
+        #[cfg(any(feature = "i386", feature = "ppc", feature = "mips"))]
+        self.add_handler(shm_nr::IPC, Box::new(handle_ipc));
+    }
+
+    /// Serve the input to the `mmap`s of the file the target opens at `path`, on top of the magic `PROT_READ, MAP_SHARED` ones.
+    /// The mapping is the input buffer itself, so `munmap` on it is skipped as well.
+    pub fn set_mmap_file(&mut self, path: &str) {
+        self.mmap_path = Some(path.as_bytes().to_vec());
+        self.add_handler(SYS_openat, Box::new(handle_openat));
+        self.add_handler(SYS_close, Box::new(handle_close));
+    }
+
+    /// Only serve the input to the `read()`s of pipes, the other fds are really read.
+    /// The read ends are the pipes already open when fuzzing starts (e.g. the one the target was spawned with)
+    /// and the ones the target creates with `pipe`/`pipe2`, until it closes them.
+    pub fn set_auto_pipe(&mut self) {
+        self.auto_pipe = true;
+        self.add_handler(SYS_close, Box::new(handle_pipe_close));
+    }
+
+    /// The current input, as injected into the guest
+    pub fn input(&self) -> &[u8] {
+        &self.input
+    }
+
+    /// The guest buffer holding the current input
+    pub fn input_addr(&self) -> GuestAddr {
+        self.input_addr
+    }
+
+    fn handle(
+        &mut self,
+        qemu: Qemu,
+        mut metadata: Option<&mut SerdeAnyMap>,
+        sys_num: i64,
+        args: &[GuestAddr; 8],
+    ) -> SyscallHookResult {
+        let start = self.timed.then(current_time);
+        // Handlers get the module itself, so take them out while they run
+        let mut handlers = mem::take(&mut self.handlers);
+        let result = handlers
+            .iter_mut()
+            .rev()
+            .filter(|(num, _)| *num == sys_num)
+            .find_map(|(_, handler)| handler(self, qemu, metadata.as_deref_mut(), args));
+        self.handlers = handlers;
+        if let Some(start) = start {
+            self.injection_time += current_time().saturating_sub(start);
+        }
+
+        result.unwrap_or_else(|| SyscallHookResult::new(None))
+    }
+
+    fn handle_post(&mut self, qemu: Qemu, sys_num: i64, result: GuestAddr, args: &[GuestAddr; 8]) -> GuestAddr {
+        // Negative results are errnos
+        if self.auto_pipe && (result as i64) >= 0 {
+            if let Some(fd) = pipe_read_end(qemu, sys_num, result, args) {
+                log::debug!("Pipe created, serving the input to its read end {fd}");
+                self.pipe_fds.push(fd);
+            }
+        }
+        if sys_num == SYS_openat && self.opening {
+            self.opening = false;
+            if (result as i64) >= 0 {
+                log::debug!("Fuzzed file opened as fd {result}");
+                self.mmap_fd = Some(result);
+            }
+        }
+        result
+    }
 }
 
 impl<I, S> EmulatorModule<I, S> for InputInjectorModule
 where
     S: Unpin + HasMetadata,
-    I: Unpin + HasTargetBytes, 
+    I: Unpin + HasTargetBytes,
 {
     type ModuleAddressFilter = NopAddressFilter;
 
@@ -53,6 +431,20 @@ where
             log::error!("Failed to install hook");
         }
 
+        if self.auto_pipe {
+            self.pipe_fds.extend(open_pipe_read_ends());
+            log::info!("Serving the input to the read ends of the pipes, already open: {:?}", self.pipe_fds);
+        }
+
+        // The fd of the fuzzed file is only known once openat() returns, and the fds of a pipe once pipe() does
+        if (self.mmap_path.is_some() || self.auto_pipe)
+            && _emulator_modules
+                .post_syscalls(Hook::Function(post_syscall_hooks::<ET, I, S>))
+                .is_none()
+        {
+            log::error!("Failed to install post syscall hook");
+        }
+
         let exec_meta = ExecMeta::new();
         _state.add_metadata(exec_meta);
     }
@@ -65,8 +457,9 @@ where
         _input: &I,
     ) where
         ET: EmulatorModuleTuple<I, S>,
-    {   
+    {
         log::debug!("InputInjectorModule::pre_exec running ...");
+        let start = self.timed.then(current_time);
 
         let mut tb = _input.target_bytes();
         if tb.len() > self.max_size {
@@ -76,8 +469,31 @@ where
             }
         }
 
+        let decoded;
+        let bytes: &[u8] = match &self.selector {
+            Some(encodings) if !tb.is_empty() && !encodings.is_empty() => {
+                decoded = encodings[tb[0] as usize % encodings.len()].decode(&tb[1..]);
+                &decoded
+            }
+            Some(_) => &[],
+            None => &tb,
+        };
+
         self.input.clear();
-        self.input.extend_from_slice(&tb);
+        match &self.schema {
+            Some(schema) => self.input.extend(schema.encode(bytes)),
+            None => self.input.extend_from_slice(bytes),
+        }
+        self.read_pos = 0;
+        self.window_pos = 0;
+        if self.input.len() < self.min_size {
+            self.input.resize(self.min_size, 0);
+        }
+        if self.multipart {
+            self.segments = parse_segments(&self.input);
+            self.segment = 0;
+            self.step = 0;
+        }
 
         // clean and fill the input_addr for further mmap usage
         let written_buf = if self.input.len() > self.max_size {
@@ -85,7 +501,45 @@ where
         } else {
             &self.input
         };
+        if self.canary_fill {
+            // What the target reads past the input is the canary, not the end of a previous input
+            self.canary_buf.resize(self.max_size, CANARY);
+            _qemu.write_mem(self.input_addr, &self.canary_buf).unwrap();
+        }
         _qemu.write_mem(self.input_addr, written_buf).unwrap();
+        if let Some(conv) = self.entry_args {
+            write_argument(_qemu, conv, 0, self.input_addr);
+            write_argument(_qemu, conv, 1, written_buf.len() as GuestReg);
+        }
+        if let Some(target) = self.symbol_target {
+            let written = &written_buf[..written_buf.len().min(target.size)];
+            _qemu.write_mem(target.addr, written).unwrap();
+            if let Some((len_addr, len_size)) = target.len {
+                _qemu.write_mem(len_addr, &guest_uint(written.len() as u64, len_size)).unwrap();
+            }
+        }
+        if let Some(start) = start {
+            self.injection_time += current_time().saturating_sub(start);
+        }
+    }
+
+    fn post_exec<OT, ET>(
+        &mut self,
+        _qemu: Qemu,
+        _emulator_modules: &mut EmulatorModules<ET, I, S>,
+        _state: &mut S,
+        _input: &I,
+        _observers: &mut OT,
+        _exit_kind: &mut ExitKind,
+    ) where
+        OT: ObserversTuple<I, S>,
+        ET: EmulatorModuleTuple<I, S>,
+    {
+        if self.timed {
+            let injection_time = _state.metadata_or_insert_with(InjectionTime::default);
+            injection_time.total += mem::take(&mut self.injection_time);
+            injection_time.runs += 1;
+        }
     }
 
     fn address_filter(&self) -> &Self::ModuleAddressFilter {
@@ -98,7 +552,7 @@ where
     }
 }
 
-/// This is user-defined syscall hook.
+/// This is user-defined syscall hook, dispatching to the registered [`SyscallHandler`]s.
 /// If create `SyscallHookResult` with `None`, the syscall will execute normally
 /// If create `SyscallHookResult` with `Some(retval)`, the syscall will directly return the retval and not execute
 fn syscall_hooks<ET, I, S>(
@@ -108,84 +562,515 @@ fn syscall_hooks<ET, I, S>(
     sys_num: i32,
     a0: GuestAddr,
     a1: GuestAddr,
-    _a2: GuestAddr,
-    _a3: GuestAddr,
-    _a4: GuestAddr,
-    _a5: GuestAddr,
-    _a6: GuestAddr,
-    _a7: GuestAddr,
+    a2: GuestAddr,
+    a3: GuestAddr,
+    a4: GuestAddr,
+    a5: GuestAddr,
+    a6: GuestAddr,
+    a7: GuestAddr,
 ) -> SyscallHookResult
 where
     S: Unpin + HasMetadata,
     I: Unpin + HasTargetBytes,
     ET: EmulatorModuleTuple<I, S>,
 {
-    let sys_num = sys_num as i64;
-    // Hook syscall read
-    if sys_num == SYS_read {
-        log::debug!("Read syscall intercepted ...");
-        let input_injector_module = emulator_modules
-            .get_mut::<InputInjectorModule>()
-            .expect("Failed to get InputInjectorModule");
-        
-        let input_len = input_injector_module.input.len();
-        let offset: usize = if _a2 == 0 {
-            0
-        } else if _a2 as usize <= input_len {
-            _a2.try_into().unwrap()
-        } else {
-            input_len
+    let input_injector_module = emulator_modules
+        .get_mut::<InputInjectorModule>()
+        .expect("Failed to get InputInjectorModule");
+
+    input_injector_module.handle(
+        _qemu,
+        _state.map(|state| state.metadata_map_mut()),
+        sys_num as i64,
+        &[a0, a1, a2, a3, a4, a5, a6, a7],
+    )
+}
+
+fn post_syscall_hooks<ET, I, S>(
+    _qemu: Qemu,
+    emulator_modules: &mut EmulatorModules<ET, I, S>,
+    _state: Option<&mut S>,
+    result: GuestAddr,
+    sys_num: i32,
+    a0: GuestAddr,
+    a1: GuestAddr,
+    a2: GuestAddr,
+    a3: GuestAddr,
+    a4: GuestAddr,
+    a5: GuestAddr,
+    a6: GuestAddr,
+    a7: GuestAddr,
+) -> GuestAddr
+where
+    S: Unpin + HasMetadata,
+    I: Unpin + HasTargetBytes,
+    ET: EmulatorModuleTuple<I, S>,
+{
+    emulator_modules
+        .get_mut::<InputInjectorModule>()
+        .expect("Failed to get InputInjectorModule")
+        .handle_post(_qemu, sys_num as i64, result, &[a0, a1, a2, a3, a4, a5, a6, a7])
+}
+
+/// Serve the input to every read()
+fn handle_read(
+    injector: &mut InputInjectorModule,
+    qemu: Qemu,
+    _metadata: Option<&mut SerdeAnyMap>,
+    args: &[GuestAddr; 8],
+) -> Option<SyscallHookResult> {
+    if injector.auto_pipe && !injector.pipe_fds.contains(&args[0]) {
+        return None;
+    }
+    log::debug!("Read syscall intercepted ...");
+
+    if injector.multipart {
+        return Some(read_segment(injector, qemu, args));
+    }
+
+    if injector.read_pos >= injector.input.len() && args[2] != 0 {
+        match injector.eof {
+            EofBehavior::Zero => {}
+            EofBehavior::Error => {
+                log::debug!("Input exhausted, read() fails with EIO");
+                return Some(SyscallHookResult::new(Some((-EIO) as u64)));
+            }
+            EofBehavior::Loop => {
+                log::debug!("Input exhausted, rewinding");
+                injector.read_pos = 0;
+            }
+        }
+    }
+
+    // Serve the next chunk straight from the saved input, there is no need to
+    // drain or copy it on the host as the input doesn't change during an execution
+    let remaining = &injector.input[injector.read_pos..];
+    let len = remaining.len().min(args[2] as usize);
+    qemu.write_mem(args[1], &remaining[..len]).unwrap();
+    injector.read_pos += len;
+
+    // Return the number of bytes read
+    Some(SyscallHookResult::new(Some(len as u64)))
+}
+
+/// Serve the rest of the current segment to a read(), at most what it asked for
+fn read_segment(injector: &mut InputInjectorModule, qemu: Qemu, args: &[GuestAddr; 8]) -> SyscallHookResult {
+    if let Some(ScriptStep::Write(_)) = injector.script_step() {
+        log::debug!("Read while the script waits for write step {}, end of file", injector.step);
+        return SyscallHookResult::new(Some(0));
+    }
+
+    if injector.segment >= injector.segments.len() {
+        match injector.eof {
+            EofBehavior::Loop if !injector.segments.is_empty() => {
+                log::debug!("Segments exhausted, rewinding");
+                injector.segment = 0;
+                injector.read_pos = 0;
+            }
+            EofBehavior::Error => {
+                log::debug!("Segments exhausted, read() fails with EIO");
+                return SyscallHookResult::new(Some((-EIO) as u64));
+            }
+            _ => return SyscallHookResult::new(Some(0)),
+        }
+    }
+
+    let segment = injector.segments[injector.segment].clone();
+    let pos = injector.read_pos.max(segment.start);
+    let len = (segment.end - pos).min(args[2] as usize);
+    qemu.write_mem(args[1], &injector.input[pos..pos + len]).unwrap();
+    injector.read_pos = pos + len;
+    // An empty segment is served as a single 0-length read
+    if injector.read_pos >= segment.end {
+        injector.segment += 1;
+        if injector.script_step() == Some(ScriptStep::Read) {
+            injector.step += 1;
+        }
+    }
+
+    SyscallHookResult::new(Some(len as u64))
+}
+
+/// Count the writes of the target against the `write` steps of the script, the writes happen as usual
+fn handle_script_write(
+    injector: &mut InputInjectorModule,
+    _qemu: Qemu,
+    _metadata: Option<&mut SerdeAnyMap>,
+    args: &[GuestAddr; 8],
+) -> Option<SyscallHookResult> {
+    if let Some(ScriptStep::Write(fd)) = injector.script_step() {
+        let expected = match fd {
+            Some(fd) => args[0] == fd,
+            None => args[0] != 2,
         };
+        if expected {
+            log::debug!("Script write step {} done", injector.step);
+            injector.step += 1;
+        }
+    }
+    None
+}
 
-        let drained = input_injector_module
-            .input
-            .drain(..offset)
-            .as_slice().to_owned();
+/// Split `input` into `<u16 little-endian length><data>` segments, a trailing partial length is ignored
+fn parse_segments(input: &[u8]) -> Vec<Range<usize>> {
+    let mut segments = Vec::new();
+    let mut pos = 0;
+    while pos + 2 <= input.len() {
+        let len = u16::from_le_bytes([input[pos], input[pos + 1]]) as usize;
+        let start = pos + 2;
+        let end = (start + len).min(input.len());
+        segments.push(start..end);
+        pos = end;
+    }
+    segments
+}
 
-        _qemu.write_mem(a1, drained.as_slice()).unwrap();
+/// Hand out the input buffer to `mmap(_, _, PROT_READ, MAP_SHARED, ...)` and to the mappings of the fuzzed file
+fn handle_mmap(
+    injector: &mut InputInjectorModule,
+    _qemu: Qemu,
+    _metadata: Option<&mut SerdeAnyMap>,
+    args: &[GuestAddr; 8],
+) -> Option<SyscallHookResult> {
+    if args[2] == 1 && args[3] == 1 && injector.window.is_some() {
+        Some(map_window(injector, _qemu))
+    } else if args[2] == 1 && args[3] == 1 {
+        log::debug!("Mmap syscall intercepted ...");
+        log::debug!("Mmap return address: {:#x}", injector.input_addr);
+        Some(SyscallHookResult::new(Some(injector.input_addr)))
+    } else if injector.mmap_fd == Some(args[4]) && (args[5] as usize) < injector.max_size {
+        // The file offset maps to the same offset in the input
+        let addr = injector.input_addr + args[5];
+        log::debug!("Mmap of the fuzzed file intercepted, return address: {addr:#x}");
+        Some(SyscallHookResult::new(Some(addr)))
+    } else {
+        None
+    }
+}
 
-        // Return the number of bytes read
-        SyscallHookResult::new(Some(drained.len() as u64))
+/// Write the next window of the input at the input buffer and map it, see [`InputInjectorModule::set_mmap_window`]
+fn map_window(injector: &mut InputInjectorModule, qemu: Qemu) -> SyscallHookResult {
+    let size = injector.window.unwrap_or(MAX_INPUT_SIZE);
+    if injector.window_pos >= injector.input.len() {
+        match injector.eof {
+            EofBehavior::Zero => {}
+            EofBehavior::Error => {
+                log::debug!("Input exhausted, mmap() fails with ENOMEM");
+                return SyscallHookResult::new(Some((-ENOMEM) as u64));
+            }
+            EofBehavior::Loop => {
+                log::debug!("Input exhausted, rewinding the window");
+                injector.window_pos = 0;
+            }
+        }
     }
-    else if sys_num == SYS_mmap {
-        if _a2 == 1 && _a3 == 1 {
-            log::debug!("Mmap syscall intercepted ...");
-            let input_injector_module = emulator_modules
-                .get_mut::<InputInjectorModule>()
-                .expect("Failed to get InputInjectorModule");
-            log::debug!("Mmap return address: {:#x}", input_injector_module.input_addr);
-            SyscallHookResult::new(Some(input_injector_module.input_addr))
-        } else {
-            SyscallHookResult::new(None)
-        }
-    }
-    else if sys_num == SYS_munmap {
-        let input_injector_module = emulator_modules
-                .get_mut::<InputInjectorModule>()
-                .expect("Failed to get InputInjectorModule");
-        let addr = input_injector_module.input_addr;
-        log::debug!("Munmap args: {:#x}, {:#x}", a0, a1);
-        if a0 == addr {
-            log::debug!("Munmap syscall intercepted ...");
-            SyscallHookResult::new(Some(0))
-        } else {
-            SyscallHookResult::new(None)
-        }
-    }
-    else if sys_num == SYS_exit || sys_num == SYS_exit_group {
-        log::debug!("Exit / Exit group syscall intercepted ...");
-        
-        // Simply abort() will cause the fuzzer treat it as a crash, so we need to set a flag to ignore it
-        let state = _state.expect("No state found");
-        let exec_meta = state
-            .metadata_map_mut()
-            .get_mut::<ExecMeta>()
-            .expect("Can't get exec_meta");
+
+    // The window is zero-padded past the end of the input, nothing of the previous one is left
+    let start = injector.window_pos.min(injector.input.len());
+    let end = (start + size).min(injector.input.len());
+    let mut window = injector.input[start..end].to_vec();
+    window.resize(size, 0);
+    qemu.write_mem(injector.input_addr, &window).unwrap();
+    injector.window_pos += size;
+
+    log::debug!("Mmap window {start:#x}..{end:#x} of the input at {:#x}", injector.input_addr);
+    SyscallHookResult::new(Some(injector.input_addr))
+}
+
+/// Watch for the target opening the fuzzed file, see [`InputInjectorModule::set_mmap_file`]
+fn handle_openat(
+    injector: &mut InputInjectorModule,
+    qemu: Qemu,
+    _metadata: Option<&mut SerdeAnyMap>,
+    args: &[GuestAddr; 8],
+) -> Option<SyscallHookResult> {
+    let path = injector.mmap_path.as_ref()?;
+    // Compare the terminating NUL too, so that a longer path doesn't match
+    let mut buf = vec![0; path.len() + 1];
+    if qemu.read_mem(args[1], &mut buf).is_ok() && buf[..path.len()] == path[..] && buf[path.len()] == 0 {
+        log::debug!("Openat of the fuzzed file intercepted ...");
+        injector.opening = true;
+    }
+    // The file is really opened, only its fd is needed
+    None
+}
+
+/// Stop serving the mappings of the fuzzed file once its fd is closed
+fn handle_close(
+    injector: &mut InputInjectorModule,
+    _qemu: Qemu,
+    _metadata: Option<&mut SerdeAnyMap>,
+    args: &[GuestAddr; 8],
+) -> Option<SyscallHookResult> {
+    if injector.mmap_fd == Some(args[0]) {
+        log::debug!("Fuzzed file closed ...");
+        injector.mmap_fd = None;
+    }
+    None
+}
+
+/// Forget the read end of a pipe once it's closed, see [`InputInjectorModule::set_auto_pipe`]
+fn handle_pipe_close(
+    injector: &mut InputInjectorModule,
+    _qemu: Qemu,
+    _metadata: Option<&mut SerdeAnyMap>,
+    args: &[GuestAddr; 8],
+) -> Option<SyscallHookResult> {
+    injector.pipe_fds.retain(|fd| *fd != args[0]);
+    None
+}
+
+/// The read end of the pipe a successful `pipe`/`pipe2` returning `_result` created, if `sys_num` is one of them
+fn pipe_read_end(qemu: Qemu, sys_num: i64, _result: GuestAddr, args: &[GuestAddr; 8]) -> Option<GuestAddr> {
+    // The MIPS pipe() returns the read end itself, and the write end in a second register
+    #[cfg(feature = "mips")]
+    if sys_num == SYS_pipe {
+        return Some(_result);
+    }
+    #[cfg(not(any(feature = "aarch64", feature = "hexagon", feature = "mips")))]
+    if sys_num == SYS_pipe {
+        return read_guest_fd(qemu, args[0]);
+    }
+    if sys_num == SYS_pipe2 {
+        return read_guest_fd(qemu, args[0]);
+    }
+    None
+}
+
+/// Write the `idx`th argument of the harness entry, following `conv`. It runs at the harness entry,
+/// where the arguments are expected. libafl_qemu only knows the C convention of each architecture,
+/// the others are written to their registers here.
+fn write_argument(qemu: Qemu, conv: CallConv, idx: u8, value: GuestReg) {
+    let written = match (conv, idx) {
+        (CallConv::Cdecl, _) => qemu.write_function_argument(CallingConvention::Cdecl, idx, value),
+        #[cfg(feature = "i386")]
+        (CallConv::Fastcall, 0) => qemu.write_reg(Regs::Ecx, value),
+        #[cfg(feature = "i386")]
+        (CallConv::Fastcall, 1) => qemu.write_reg(Regs::Edx, value),
+        #[cfg(feature = "x86_64")]
+        (CallConv::Win64, 0) => qemu.write_reg(Regs::Rcx, value),
+        #[cfg(feature = "x86_64")]
+        (CallConv::Win64, 1) => qemu.write_reg(Regs::Rdx, value),
+        // Rejected by `FuzzerOptions::validate`
+        (conv, _) => unreachable!("The {conv:?} calling convention has no register for argument {idx}"),
+    };
+    written.unwrap_or_else(|e| panic!("Failed to write argument {idx} ({conv:?}): {e:?}"));
+}
+
+/// `value` as a guest unsigned integer of `size` bytes (at most 8), truncated if it doesn't fit
+fn guest_uint(value: u64, size: usize) -> Vec<u8> {
+    let size = size.min(8);
+    #[cfg(feature = "be")]
+    let bytes = value.to_be_bytes()[8 - size..].to_vec();
+    #[cfg(not(feature = "be"))]
+    let bytes = value.to_le_bytes()[..size].to_vec();
+    bytes
+}
+
+/// The guest `int` fd at `addr`
+fn read_guest_fd(qemu: Qemu, addr: GuestAddr) -> Option<GuestAddr> {
+    let mut fd = [0; 4];
+    qemu.read_mem(addr, &mut fd).ok()?;
+    #[cfg(feature = "be")]
+    let fd = i32::from_be_bytes(fd);
+    #[cfg(not(feature = "be"))]
+    let fd = i32::from_le_bytes(fd);
+    Some(fd as GuestAddr)
+}
+
+/// The read ends of the pipes open in this process. qemu-user passes the guest's fds through,
+/// so they are the guest's as well, along with any the fuzzer itself holds.
+fn open_pipe_read_ends() -> Vec<GuestAddr> {
+    let Ok(entries) = fs::read_dir("/proc/self/fd") else {
+        return Vec::new();
+    };
+    let mut fds = entries
+        .filter_map(Result::ok)
+        .filter_map(|entry| entry.file_name().to_str()?.parse::<i32>().ok())
+        .filter(|fd| {
+            let is_pipe = fs::read_link(format!("/proc/self/fd/{fd}"))
+                .is_ok_and(|target| target.to_string_lossy().starts_with("pipe:"));
+            let readable = fcntl(*fd, FcntlArg::F_GETFL).is_ok_and(|flags| {
+                OFlag::from_bits_truncate(flags) & OFlag::O_ACCMODE == OFlag::O_RDONLY
+            });
+            is_pipe && readable
+        })
+        .map(|fd| fd as GuestAddr)
+        .collect::<Vec<GuestAddr>>();
+    fds.sort_unstable();
+    fds
+}
+
+/// Give the input buffer's segment to the `shmget` of the served key, see [`InputInjectorModule::set_shm_inject`]
+fn shm_get(injector: &mut InputInjectorModule, key: GuestAddr, size: GuestAddr) -> Option<SyscallHookResult> {
+    // key_t is an int, sign-extended in the register
+    if injector.shm_key.is_some_and(|served| served != key as u32) {
+        return None;
+    }
+    if size as usize > injector.max_size {
+        log::warn!("shmget of {size:#x} bytes, larger than the input buffer, fails with EINVAL");
+        return Some(SyscallHookResult::new(Some((-EINVAL) as u64)));
+    }
+    if injector.shm_id.is_none() {
+        log::info!("Serving the input through the shared memory segment of key {:#x}", key as u32);
+    }
+    injector.shm_id = Some(SHM_INJECT_ID);
+    Some(SyscallHookResult::new(Some(SHM_INJECT_ID)))
+}
+
+fn handle_shmget(
+    injector: &mut InputInjectorModule,
+    _qemu: Qemu,
+    _metadata: Option<&mut SerdeAnyMap>,
+    args: &[GuestAddr; 8],
+) -> Option<SyscallHookResult> {
+    shm_get(injector, args[0], args[1])
+}
+
+fn handle_shmat(
+    injector: &mut InputInjectorModule,
+    _qemu: Qemu,
+    _metadata: Option<&mut SerdeAnyMap>,
+    args: &[GuestAddr; 8],
+) -> Option<SyscallHookResult> {
+    if injector.shm_id != Some(args[0]) {
+        return None;
+    }
+    // The requested address, if any, is ignored like for the magic mmap
+    log::debug!("Shmat of the input segment intercepted, return address: {:#x}", injector.input_addr);
+    Some(SyscallHookResult::new(Some(injector.input_addr)))
+}
+
+fn handle_shmdt(
+    injector: &mut InputInjectorModule,
+    _qemu: Qemu,
+    _metadata: Option<&mut SerdeAnyMap>,
+    args: &[GuestAddr; 8],
+) -> Option<SyscallHookResult> {
+    (injector.shm_id.is_some() && args[0] == injector.input_addr).then(|| SyscallHookResult::new(Some(0)))
+}
+
+/// `IPC_RMID` and the others, `IPC_STAT` leaves its buffer as it is
+fn handle_shmctl(
+    injector: &mut InputInjectorModule,
+    _qemu: Qemu,
+    _metadata: Option<&mut SerdeAnyMap>,
+    args: &[GuestAddr; 8],
+) -> Option<SyscallHookResult> {
+    (injector.shm_id == Some(args[0])).then(|| SyscallHookResult::new(Some(0)))
+}
+
+/// The shared memory calls of the `ipc` multiplexer, `ipc(call, first, second, third, ptr, fifth)`
+#[cfg(any(feature = "i386", feature = "ppc", feature = "mips"))]
+fn handle_ipc(
+    injector: &mut InputInjectorModule,
+    qemu: Qemu,
+    _metadata: Option<&mut SerdeAnyMap>,
+    args: &[GuestAddr; 8],
+) -> Option<SyscallHookResult> {
+    match args[0] as u64 & 0xffff {
+        // shmget(first, second, third)
+        ipc_call::SHMGET => shm_get(injector, args[1], args[2]),
+        // shmat(first, ptr, second), the address is returned through `third`
+        ipc_call::SHMAT if injector.shm_id == Some(args[1]) => {
+            let addr = guest_uint(injector.input_addr as u64, mem::size_of::<GuestAddr>());
+            qemu.write_mem(args[3], &addr).ok()?;
+            Some(SyscallHookResult::new(Some(0)))
+        }
+        // shmdt(ptr)
+        ipc_call::SHMDT if injector.shm_id.is_some() && args[4] == injector.input_addr => {
+            Some(SyscallHookResult::new(Some(0)))
+        }
+        // shmctl(first, second, ptr)
+        ipc_call::SHMCTL if injector.shm_id == Some(args[1]) => Some(SyscallHookResult::new(Some(0))),
+        _ => None,
+    }
+}
+
+/// Keep the input buffer mapped, including the parts handed out for the fuzzed file
+fn handle_munmap(
+    injector: &mut InputInjectorModule,
+    _qemu: Qemu,
+    _metadata: Option<&mut SerdeAnyMap>,
+    args: &[GuestAddr; 8],
+) -> Option<SyscallHookResult> {
+    log::debug!("Munmap args: {:#x}, {:#x}", args[0], args[1]);
+    let input_range = injector.input_addr..injector.input_addr + injector.max_size as GuestAddr;
+    if input_range.contains(&args[0]) {
+        log::debug!("Munmap syscall intercepted ...");
+        Some(SyscallHookResult::new(Some(0)))
+    } else {
+        None
+    }
+}
+
+fn handle_exit(
+    injector: &mut InputInjectorModule,
+    _qemu: Qemu,
+    metadata: Option<&mut SerdeAnyMap>,
+    args: &[GuestAddr; 8],
+) -> Option<SyscallHookResult> {
+    log::debug!("Exit / Exit group syscall intercepted ...");
+
+    if injector.forked {
+        _exit(0);
+    }
+
+    // Simply abort() will cause the fuzzer treat it as a crash, so we need to set a flag to ignore it
+    let exec_meta = metadata
+        .expect("No state found")
+        .get_mut::<ExecMeta>()
+        .expect("Can't get exec_meta");
+    exec_meta.ignore = true;
+    exec_meta.exit_code = Some(args[0] as i32);
+
+    abort();
+}
+
+/// `kill(pid, sig)` and `tkill(tid, sig)`
+fn handle_kill(
+    injector: &mut InputInjectorModule,
+    _qemu: Qemu,
+    metadata: Option<&mut SerdeAnyMap>,
+    args: &[GuestAddr; 8],
+) -> Option<SyscallHookResult> {
+    handle_signal(injector, metadata, args[1])
+}
+
+/// `tgkill(tgid, tid, sig)`, used by glibc's `abort()`
+fn handle_tgkill(
+    injector: &mut InputInjectorModule,
+    _qemu: Qemu,
+    metadata: Option<&mut SerdeAnyMap>,
+    args: &[GuestAddr; 8],
+) -> Option<SyscallHookResult> {
+    handle_signal(injector, metadata, args[2])
+}
+
+/// The target raising `SIGABRT` (e.g. `abort()` on a failed assertion or a sanitizer report) is a bug,
+/// unlike our own teardown in [`handle_exit`] which doesn't go through the guest.
+/// The signal is delivered to the guest as usual and reported by the crash handler.
+fn handle_signal(
+    injector: &InputInjectorModule,
+    metadata: Option<&mut SerdeAnyMap>,
+    sig: GuestAddr,
+) -> Option<SyscallHookResult> {
+    if sig != SIGABRT {
+        return None;
+    }
+    log::debug!("SIGABRT raised by the target ...");
+
+    let exec_meta = metadata
+        .expect("No state found")
+        .get_mut::<ExecMeta>()
+        .expect("Can't get exec_meta");
+    if injector.ignore_aborts {
+        if injector.forked {
+            _exit(0);
+        }
         exec_meta.ignore = true;
-        
         abort();
     }
-    else {
-        SyscallHookResult::new(None)
-    }
+    exec_meta.ignore = false;
+    None
 }
@@ -0,0 +1,154 @@
+use std::ops::Range;
+
+use libafl::HasMetadata;
+use libafl_qemu::{
+    modules::{
+        utils::filters::{AddressFilter, NopAddressFilter},
+        EmulatorModule, EmulatorModuleTuple, StdEdgeCoverageChildModule, StdEdgeCoverageModule,
+    },
+    EmulatorModules, GuestAddr, Hook, Qemu, SYS_mmap, SYS_mprotect,
+};
+use regex::Regex;
+
+/// `PROT_EXEC`, the same on every Linux architecture
+const PROT_EXEC: GuestAddr = 0x4;
+
+/// Adds the executable mappings of the libraries matching a `--cover-lib` pattern to the edge module's allow list:
+/// the ones loaded by the first execution, then the ones the target `dlopen`s later, detected when it maps
+/// (or `mprotect`s) code. It has no patterns, and hooks nothing, unless given some.
+#[derive(Default, Debug)]
+pub struct LibCoverageModule {
+    patterns: Vec<Regex>,
+    // The ranges already added to the filter
+    covered: Vec<Range<GuestAddr>>,
+    filter: NopAddressFilter,
+}
+
+impl LibCoverageModule {
+    /// Cover the libraries whose path matches one of `patterns`, already checked by the options validation
+    pub fn new(patterns: &[String]) -> Self {
+        Self {
+            patterns: patterns
+                .iter()
+                .map(|pattern| Regex::new(pattern).expect("Invalid --cover-lib pattern"))
+                .collect(),
+            covered: Vec::new(),
+            filter: NopAddressFilter,
+        }
+    }
+
+    /// The executable mappings of matching libraries not covered yet, marked as covered
+    fn new_ranges(&mut self, qemu: Qemu) -> Vec<Range<GuestAddr>> {
+        let mut ranges = Vec::new();
+        for mapping in qemu.mappings() {
+            let Some(path) = mapping.path() else {
+                continue;
+            };
+            if !mapping.flags().executable() || !self.patterns.iter().any(|pattern| pattern.is_match(path)) {
+                continue;
+            }
+            let range = mapping.start()..mapping.end();
+            if self.covered.contains(&range) {
+                continue;
+            }
+            log::info!("Covering {path} @ {:#x}-{:#x}", range.start, range.end);
+            self.covered.push(range.clone());
+            ranges.push(range);
+        }
+        ranges
+    }
+}
+
+impl<I, S> EmulatorModule<I, S> for LibCoverageModule
+where
+    S: Unpin + HasMetadata,
+    I: Unpin,
+{
+    type ModuleAddressFilter = NopAddressFilter;
+
+    fn first_exec<ET>(
+        &mut self,
+        qemu: Qemu,
+        emulator_modules: &mut EmulatorModules<ET, I, S>,
+        _state: &mut S,
+    ) where
+        ET: EmulatorModuleTuple<I, S>,
+    {
+        if self.patterns.is_empty() {
+            return;
+        }
+
+        log::debug!("LibCoverageModule::first_exec running ...");
+        // The filter is only set once qemu is initialized, after the modules are built
+        let ranges = self.new_ranges(qemu);
+        cover_ranges(emulator_modules, &ranges);
+        if !ranges.is_empty() {
+            // The blocks translated so far were translated without their edges
+            qemu.flush_jit();
+        }
+
+        let hook_id = emulator_modules.post_syscalls(Hook::Function(on_syscall::<ET, I, S>));
+        log::debug!("Hook {:?} installed", hook_id);
+    }
+
+    fn address_filter(&self) -> &Self::ModuleAddressFilter {
+        &self.filter
+    }
+
+    fn address_filter_mut(&mut self) -> &mut Self::ModuleAddressFilter {
+        &mut self.filter
+    }
+}
+
+/// Add `ranges` to the allow list of the edge module, whichever variant the executor uses
+fn cover_ranges<ET, I, S>(emulator_modules: &mut EmulatorModules<ET, I, S>, ranges: &[Range<GuestAddr>])
+where
+    S: Unpin + HasMetadata,
+    I: Unpin,
+    ET: EmulatorModuleTuple<I, S>,
+{
+    for range in ranges {
+        if let Some(module) = emulator_modules.get_mut::<StdEdgeCoverageModule>() {
+            EmulatorModule::<I, S>::address_filter_mut(module).register(range);
+        } else if let Some(module) = emulator_modules.get_mut::<StdEdgeCoverageChildModule>() {
+            EmulatorModule::<I, S>::address_filter_mut(module).register(range);
+        }
+    }
+}
+
+/// A library's code is mapped executable, by `mmap` or by `mprotect` after its relocations
+fn on_syscall<ET, I, S>(
+    qemu: Qemu,
+    emulator_modules: &mut EmulatorModules<ET, I, S>,
+    _state: Option<&mut S>,
+    result: GuestAddr,
+    sys_num: i32,
+    _a0: GuestAddr,
+    _a1: GuestAddr,
+    a2: GuestAddr,
+    _a3: GuestAddr,
+    _a4: GuestAddr,
+    _a5: GuestAddr,
+    _a6: GuestAddr,
+    _a7: GuestAddr,
+) -> GuestAddr
+where
+    S: Unpin + HasMetadata,
+    I: Unpin,
+    ET: EmulatorModuleTuple<I, S>,
+{
+    let maps_code = (sys_num as i64 == SYS_mmap || sys_num as i64 == SYS_mprotect) && a2 & PROT_EXEC != 0;
+    // A failed syscall returns -errno
+    let failed = result > GuestAddr::MAX - 4096;
+    if !maps_code || failed {
+        return result;
+    }
+
+    let ranges = match emulator_modules.get_mut::<LibCoverageModule>() {
+        Some(module) => module.new_ranges(qemu),
+        None => return result,
+    };
+    // The code was just mapped, none of it is translated yet
+    cover_ranges(emulator_modules, &ranges);
+    result
+}
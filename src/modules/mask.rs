@@ -0,0 +1,119 @@
+use std::{fs, path::Path};
+
+use libafl::{executors::ExitKind, observers::ObserversTuple, Error, HasMetadata};
+use libafl_qemu::{
+    modules::{
+        edges::QemuEdgesMapMetadata, utils::filters::NopAddressFilter, EmulatorModule,
+        EmulatorModuleTuple,
+    },
+    EmulatorModules, GuestAddr, Qemu,
+};
+use libafl_targets::{edges_map_mut_ptr, EDGES_MAP_DEFAULT_SIZE};
+
+/// Clears a set of edges from the coverage map after every run, before the observers and feedbacks see it,
+/// so that inputs only reaching those edges (e.g. error handling paths) are not considered novel.
+#[derive(Default, Debug)]
+pub struct EdgeMaskModule {
+    indices: Vec<usize>,
+    edges: Vec<(GuestAddr, GuestAddr)>,
+    filter: NopAddressFilter,
+}
+
+impl EdgeMaskModule {
+    /// Read the edges to mask from `path`, one per line:
+    ///
+    /// ```text
+    /// # comment
+    /// <index>
+    /// <src> <dst>
+    /// <index> <src> <dst>
+    /// ```
+    ///
+    /// An edge is either its index in the map or its guest addresses in hex.
+    /// Lines with three fields are the ones written by `--coverage-out`, only their index is used.
+    pub fn from_file(path: &Path) -> Result<Self, Error> {
+        let content = fs::read_to_string(path)?;
+        let mut module = Self::default();
+        for (i, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let invalid = |what: &str| {
+                Error::illegal_argument(format!("{}:{}: invalid {what}: {line}", path.display(), i + 1))
+            };
+            match line.split_whitespace().collect::<Vec<&str>>()[..] {
+                [index] | [index, _, _] => {
+                    let index = parse_number(index).ok_or_else(|| invalid("edge index"))? as usize;
+                    if index >= EDGES_MAP_DEFAULT_SIZE {
+                        return Err(invalid("edge index (out of the map)"));
+                    }
+                    module.indices.push(index);
+                }
+                [src, dst] => {
+                    let src = parse_number(src).ok_or_else(|| invalid("source address"))?;
+                    let dst = parse_number(dst).ok_or_else(|| invalid("destination address"))?;
+                    module.edges.push((src, dst));
+                }
+                _ => return Err(invalid("edge")),
+            }
+        }
+        Ok(module)
+    }
+
+    fn mask(&self, metadata: Option<&QemuEdgesMapMetadata>) {
+        // Edges given by address only have an index once the edge module has seen them
+        let resolved = self
+            .edges
+            .iter()
+            .filter_map(|edge| metadata.and_then(|meta| meta.map.get(edge)))
+            .map(|id| *id as usize)
+            .filter(|idx| *idx < EDGES_MAP_DEFAULT_SIZE);
+
+        let map = edges_map_mut_ptr();
+        for idx in self.indices.iter().copied().chain(resolved) {
+            unsafe {
+                *map.add(idx) = 0;
+            }
+        }
+    }
+}
+
+/// A decimal number, or a hexadecimal one prefixed with `0x`
+fn parse_number(src: &str) -> Option<GuestAddr> {
+    match src.strip_prefix("0x") {
+        Some(hex) => GuestAddr::from_str_radix(hex, 16).ok(),
+        None => src.parse().ok(),
+    }
+}
+
+impl<I, S> EmulatorModule<I, S> for EdgeMaskModule
+where
+    S: Unpin + HasMetadata,
+    I: Unpin,
+{
+    type ModuleAddressFilter = NopAddressFilter;
+
+    fn post_exec<OT, ET>(
+        &mut self,
+        _qemu: Qemu,
+        _emulator_modules: &mut EmulatorModules<ET, I, S>,
+        _state: &mut S,
+        _input: &I,
+        _observers: &mut OT,
+        _exit_kind: &mut ExitKind,
+    ) where
+        OT: ObserversTuple<I, S>,
+        ET: EmulatorModuleTuple<I, S>,
+    {
+        self.mask(_state.metadata_map().get::<QemuEdgesMapMetadata>());
+    }
+
+    fn address_filter(&self) -> &Self::ModuleAddressFilter {
+        &self.filter
+    }
+
+    fn address_filter_mut(&mut self) -> &mut Self::ModuleAddressFilter {
+        &mut self.filter
+    }
+}
@@ -1,22 +1,63 @@
+pub mod blocks;
+pub mod collisions;
+pub mod crash;
+pub mod data;
+pub mod detector;
+pub mod determinism;
+pub mod heap;
 pub mod input_injector;
+pub mod libs;
+pub mod mask;
+pub mod reach;
+pub mod output;
 pub mod register;
+pub mod split;
+pub mod syscalls;
+pub mod trace;
 
-pub use input_injector::InputInjectorModule;
-pub use register::RegisterResetModule;
+pub use blocks::{BlockCoverage, BlockCoverageModule};
+pub use collisions::{CollisionSampleModule, CollisionStats};
+pub use crash::{CrashClassifierModule, CrashInfo};
+pub use data::DataResetModule;
+pub use detector::FindingDetectorModule;
+pub use heap::{HeapCheckModule, HeapFn};
+pub use input_injector::{InjectionTime, InputInjectorModule, SymbolTarget, SyscallHandler};
+pub use libs::LibCoverageModule;
+pub use mask::EdgeMaskModule;
+pub use output::{CapturedOutput, OutputCaptureModule};
+pub use reach::TargetReachModule;
+pub use register::{RegInput, RegisterResetModule};
+pub use split::{CoverageRegion, SplitCoverageModule, SPLIT_MAP_SIZE};
+pub use syscalls::{SyscallStats, SyscallStatsModule};
+pub use trace::BlockTraceModule;
 use serde::{Deserialize, Serialize};
 // use std::cell::UnsafeCell;
 // use libafl_qemu::modules::NopAddressFilter;
 
 // static mut NOP_ADDRESS_FILTER: UnsafeCell<NopAddressFilter> = UnsafeCell::new(NopAddressFilter);
 
+/// The byte `--canary-fill` fills the input buffer past the input with, `int3` on x86
+pub const CANARY: u8 = 0xCC;
+
 #[derive(Debug, Default, Serialize, Deserialize)]
 pub struct ExecMeta {
     pub ignore: bool,
+    /// The status the target passed to `exit()`
+    pub exit_code: Option<i32>,
+    /// Set by a detector when this execution is a finding, with the reason
+    pub finding: Option<String>,
+    /// Whether this execution reached the `--priority-target`
+    pub reached_target: bool,
 }
 
 impl ExecMeta {
     pub fn new() -> Self {
-        Self { ignore: false }
+        Self {
+            ignore: false,
+            exit_code: None,
+            finding: None,
+            reached_target: false,
+        }
     }
 }
 
@@ -0,0 +1,125 @@
+use libafl::HasMetadata;
+use libafl_qemu::{
+    modules::{utils::filters::NopAddressFilter, EmulatorModule, EmulatorModuleTuple},
+    EmulatorModules, GuestAddr, Hook, Qemu, SyscallHookResult, SYS_write,
+};
+use serde::{Deserialize, Serialize};
+
+/// Don't keep more than this many bytes of the output of a single execution
+const MAX_CAPTURED_OUTPUT: usize = 1 << 20;
+
+/// What the target wrote to its stdout during the current execution, see `--crash-on-output`
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct CapturedOutput {
+    pub bytes: Vec<u8>,
+}
+
+libafl_bolts::impl_serdeany!(CapturedOutput);
+
+/// Captures the `write`s of the target to its stdout into the [`CapturedOutput`] of the state. Disabled by default.
+///
+/// The client's stdout is `/dev/null` (or a log file), and it is shared by every execution, so the output
+/// is taken from the `write` syscalls instead, before it reaches the file descriptor.
+#[derive(Default, Debug)]
+pub struct OutputCaptureModule {
+    enabled: bool,
+    filter: NopAddressFilter,
+}
+
+impl OutputCaptureModule {
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            filter: NopAddressFilter,
+        }
+    }
+}
+
+impl<I, S> EmulatorModule<I, S> for OutputCaptureModule
+where
+    S: Unpin + HasMetadata,
+    I: Unpin,
+{
+    type ModuleAddressFilter = NopAddressFilter;
+
+    fn first_exec<ET>(
+        &mut self,
+        _qemu: Qemu,
+        _emulator_modules: &mut EmulatorModules<ET, I, S>,
+        _state: &mut S,
+    ) where
+        ET: EmulatorModuleTuple<I, S>,
+    {
+        if !self.enabled {
+            return;
+        }
+
+        log::debug!("OutputCaptureModule::first_exec running ...");
+        if let Some(hook_id) =
+            _emulator_modules.pre_syscalls(Hook::Function(syscall_hooks::<ET, I, S>))
+        {
+            log::debug!("Hook {:?} installed", hook_id);
+        } else {
+            log::error!("Failed to install hook");
+        }
+    }
+
+    fn pre_exec<ET>(
+        &mut self,
+        _qemu: Qemu,
+        _emulator_modules: &mut EmulatorModules<ET, I, S>,
+        _state: &mut S,
+        _input: &I,
+    ) where
+        ET: EmulatorModuleTuple<I, S>,
+    {
+        if self.enabled {
+            _state
+                .metadata_or_insert_with(CapturedOutput::default)
+                .bytes
+                .clear();
+        }
+    }
+
+    fn address_filter(&self) -> &Self::ModuleAddressFilter {
+        &self.filter
+    }
+
+    fn address_filter_mut(&mut self) -> &mut Self::ModuleAddressFilter {
+        &mut self.filter
+    }
+}
+
+fn syscall_hooks<ET, I, S>(
+    qemu: Qemu,
+    _emulator_modules: &mut EmulatorModules<ET, I, S>,
+    state: Option<&mut S>,
+    sys_num: i32,
+    a0: GuestAddr,
+    a1: GuestAddr,
+    a2: GuestAddr,
+    _a3: GuestAddr,
+    _a4: GuestAddr,
+    _a5: GuestAddr,
+    _a6: GuestAddr,
+    _a7: GuestAddr,
+) -> SyscallHookResult
+where
+    S: Unpin + HasMetadata,
+    I: Unpin,
+    ET: EmulatorModuleTuple<I, S>,
+{
+    if sys_num as i64 == SYS_write && a0 == 1 {
+        if let Some(state) = state {
+            let captured = &mut state.metadata_or_insert_with(CapturedOutput::default).bytes;
+            let len = (a2 as usize).min(MAX_CAPTURED_OUTPUT - captured.len());
+            let start = captured.len();
+            captured.resize(start + len, 0);
+            if qemu.read_mem(a1, &mut captured[start..]).is_err() {
+                captured.truncate(start);
+            }
+        }
+    }
+    // Only captured, the write happens as usual
+    SyscallHookResult::new(None)
+}
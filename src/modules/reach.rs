@@ -0,0 +1,91 @@
+use libafl::HasMetadata;
+use libafl_qemu::{
+    modules::{utils::filters::NopAddressFilter, EmulatorModule, EmulatorModuleTuple},
+    EmulatorModules, GuestAddr, Hook, Qemu,
+};
+
+use crate::modules::ExecMeta;
+
+/// Marks the executions reaching a target address (see [`ExecMeta::reached_target`])
+#[derive(Default, Debug)]
+pub struct TargetReachModule {
+    target: Option<GuestAddr>,
+    filter: NopAddressFilter,
+}
+
+impl TargetReachModule {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The address to watch, nothing is watched by default
+    pub fn set_target(&mut self, addr: GuestAddr) {
+        self.target = Some(addr);
+    }
+}
+
+impl<I, S> EmulatorModule<I, S> for TargetReachModule
+where
+    S: Unpin + HasMetadata,
+    I: Unpin,
+{
+    type ModuleAddressFilter = NopAddressFilter;
+
+    fn first_exec<ET>(
+        &mut self,
+        _qemu: Qemu,
+        _emulator_modules: &mut EmulatorModules<ET, I, S>,
+        _state: &mut S,
+    ) where
+        ET: EmulatorModuleTuple<I, S>,
+    {
+        let Some(target) = self.target else {
+            return;
+        };
+
+        log::debug!("TargetReachModule::first_exec running ...");
+        if let Some(hook_id) =
+            _emulator_modules.instructions(target, Hook::Function(on_target::<ET, I, S>), true)
+        {
+            log::debug!("Hook {:?} installed @ {target:#x}", hook_id);
+        } else {
+            log::error!("Failed to install hook");
+        }
+    }
+
+    fn pre_exec<ET>(
+        &mut self,
+        _qemu: Qemu,
+        _emulator_modules: &mut EmulatorModules<ET, I, S>,
+        _state: &mut S,
+        _input: &I,
+    ) where
+        ET: EmulatorModuleTuple<I, S>,
+    {
+        _state.metadata_or_insert_with(ExecMeta::new).reached_target = false;
+    }
+
+    fn address_filter(&self) -> &Self::ModuleAddressFilter {
+        &self.filter
+    }
+
+    fn address_filter_mut(&mut self) -> &mut Self::ModuleAddressFilter {
+        &mut self.filter
+    }
+}
+
+fn on_target<ET, I, S>(
+    _qemu: Qemu,
+    _emulator_modules: &mut EmulatorModules<ET, I, S>,
+    state: Option<&mut S>,
+    pc: GuestAddr,
+) where
+    S: Unpin + HasMetadata,
+    I: Unpin,
+    ET: EmulatorModuleTuple<I, S>,
+{
+    log::debug!("Target reached @ {pc:#x}");
+    if let Some(state) = state {
+        state.metadata_or_insert_with(ExecMeta::new).reached_target = true;
+    }
+}
@@ -1,12 +1,34 @@
+use libafl::inputs::HasTargetBytes;
 use libafl_qemu::{
     modules::{utils::filters::NopAddressFilter, EmulatorModule, EmulatorModuleTuple},
-    EmulatorModules, Qemu, QemuParams, Regs,
+    EmulatorModules, GuestReg, Qemu, QemuParams, Regs,
 };
 
+/// A slice of the input loaded into a register before every run, see `--reg-input`
+#[derive(Debug, Clone, Copy)]
+pub struct RegInput {
+    pub reg: Regs,
+    pub offset: usize,
+    /// In bytes, at most the width of a register
+    pub size: usize,
+}
+
+impl RegInput {
+    /// The little-endian value of the slice, the bytes past the end of the input are zeros
+    fn value(&self, input: &[u8]) -> GuestReg {
+        let mut bytes = [0; size_of::<GuestReg>()];
+        let slice = input.get(self.offset..).unwrap_or_default();
+        let len = slice.len().min(self.size);
+        bytes[..len].copy_from_slice(&slice[..len]);
+        GuestReg::from_le_bytes(bytes)
+    }
+}
+
 #[derive(Default, Debug)]
 pub struct RegisterResetModule {
     reg_num: usize,
     regs: Vec<u64>,
+    reg_inputs: Vec<RegInput>,
 }
 
 impl RegisterResetModule {
@@ -14,6 +36,11 @@ impl RegisterResetModule {
         Self::default()
     }
 
+    /// Load these slices of the input into their registers after restoring them, for functions taking scalar arguments
+    pub fn set_reg_inputs(&mut self, reg_inputs: Vec<RegInput>) {
+        self.reg_inputs = reg_inputs;
+    }
+
     pub fn save(&mut self, qemu: Qemu) {
         log::debug!("Saving register state at start point ...");
 
@@ -36,7 +63,7 @@ impl RegisterResetModule {
 impl<I, S> EmulatorModule<I, S> for RegisterResetModule
 where
     S: Unpin,
-    I: Unpin,
+    I: Unpin + HasTargetBytes,
 {
     type ModuleAddressFilter = NopAddressFilter;
 
@@ -68,6 +95,16 @@ where
     {
         log::debug!("RegisterResetModule::pre_exec running ...");
         self.restore(_qemu);
+
+        if !self.reg_inputs.is_empty() {
+            let input = _input.target_bytes();
+            for reg_input in &self.reg_inputs {
+                let value = reg_input.value(&input);
+                if _qemu.write_reg(reg_input.reg, value).is_err() {
+                    log::error!("Failed to load {:?} from the input, skipping ...", reg_input.reg);
+                }
+            }
+        }
     }
 
     fn address_filter(&self) -> &Self::ModuleAddressFilter {
@@ -0,0 +1,147 @@
+use std::ops::Range;
+
+use libafl_qemu::{
+    modules::{utils::filters::NopAddressFilter, EmulatorModule, EmulatorModuleTuple},
+    EmulatorModules, GuestAddr, Hook, Qemu,
+};
+
+/// Entries of the split coverage map given to every region
+pub const SPLIT_MAP_SIZE: usize = 1 << 16;
+
+/// A code object covered by its own slice of the split coverage map, see `--split-coverage`
+#[derive(Debug, Clone)]
+pub struct CoverageRegion {
+    pub name: String,
+    pub ranges: Vec<Range<GuestAddr>>,
+}
+
+/// Records the edges of each [`CoverageRegion`] in its own [`SPLIT_MAP_SIZE`] slice of a map,
+/// AFL style (hashed block addresses), so the edges of a plugin never collide with the main binary's.
+/// It has no regions, and hooks nothing, until [`SplitCoverageModule::set_regions`].
+#[derive(Default, Debug)]
+pub struct SplitCoverageModule {
+    regions: Vec<CoverageRegion>,
+    map: Vec<u8>,
+    // The previous block of each region, shifted
+    prev: Vec<usize>,
+    filter: NopAddressFilter,
+}
+
+impl SplitCoverageModule {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cover `regions`, the `i`th one in the slice at `i * SPLIT_MAP_SIZE`.
+    /// Returns the map, which stays at the same address for the lifetime of the module.
+    pub fn set_regions(&mut self, regions: Vec<CoverageRegion>) -> &mut [u8] {
+        self.map = vec![0; regions.len() * SPLIT_MAP_SIZE];
+        self.prev = vec![0; regions.len()];
+        self.regions = regions;
+        &mut self.map
+    }
+
+    pub fn regions(&self) -> &[CoverageRegion] {
+        &self.regions
+    }
+
+    fn region_of(&self, pc: GuestAddr) -> Option<usize> {
+        self.regions
+            .iter()
+            .position(|region| region.ranges.iter().any(|range| range.contains(&pc)))
+    }
+
+    fn hit(&mut self, id: u64) {
+        let region = (id >> 32) as usize;
+        let cur = (id & 0xffff_ffff) as usize;
+        let entry = &mut self.map[region * SPLIT_MAP_SIZE + (cur ^ self.prev[region])];
+        *entry = entry.wrapping_add(1);
+        self.prev[region] = cur >> 1;
+    }
+}
+
+impl<I, S> EmulatorModule<I, S> for SplitCoverageModule
+where
+    S: Unpin,
+    I: Unpin,
+{
+    type ModuleAddressFilter = NopAddressFilter;
+
+    fn first_exec<ET>(
+        &mut self,
+        qemu: Qemu,
+        _emulator_modules: &mut EmulatorModules<ET, I, S>,
+        _state: &mut S,
+    ) where
+        ET: EmulatorModuleTuple<I, S>,
+    {
+        if self.regions.is_empty() {
+            return;
+        }
+
+        log::debug!("SplitCoverageModule::first_exec running ...");
+        let hook_id = _emulator_modules.blocks(
+            Hook::Function(gen_block_id::<ET, I, S>),
+            Hook::Empty,
+            Hook::Function(on_block::<ET, I, S>),
+        );
+        log::debug!("Hook {:?} installed", hook_id);
+        // The blocks translated so far, e.g. while running to the harness entry, would never reach the hook
+        qemu.flush_jit();
+    }
+
+    fn pre_exec<ET>(
+        &mut self,
+        _qemu: Qemu,
+        _emulator_modules: &mut EmulatorModules<ET, I, S>,
+        _state: &mut S,
+        _input: &I,
+    ) where
+        ET: EmulatorModuleTuple<I, S>,
+    {
+        self.prev.fill(0);
+    }
+
+    fn address_filter(&self) -> &Self::ModuleAddressFilter {
+        &self.filter
+    }
+
+    fn address_filter_mut(&mut self) -> &mut Self::ModuleAddressFilter {
+        &mut self.filter
+    }
+}
+
+/// The id of a block of a region is the region's index in the upper half and the hashed block address in the lower one,
+/// the blocks outside of every region are not instrumented
+fn gen_block_id<ET, I, S>(
+    _qemu: Qemu,
+    emulator_modules: &mut EmulatorModules<ET, I, S>,
+    _state: Option<&mut S>,
+    pc: GuestAddr,
+) -> Option<u64>
+where
+    S: Unpin,
+    I: Unpin,
+    ET: EmulatorModuleTuple<I, S>,
+{
+    let module = emulator_modules.get::<SplitCoverageModule>()?;
+    let region = module.region_of(pc)?;
+    let pc = pc as u64;
+    let cur = ((pc >> 4) ^ (pc << 8)) & (SPLIT_MAP_SIZE as u64 - 1);
+    Some(((region as u64) << 32) | cur)
+}
+
+fn on_block<ET, I, S>(
+    _qemu: Qemu,
+    emulator_modules: &mut EmulatorModules<ET, I, S>,
+    _state: Option<&mut S>,
+    id: u64,
+) where
+    S: Unpin,
+    I: Unpin,
+    ET: EmulatorModuleTuple<I, S>,
+{
+    if let Some(module) = emulator_modules.get_mut::<SplitCoverageModule>() {
+        module.hit(id);
+    }
+}
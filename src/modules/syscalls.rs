@@ -0,0 +1,134 @@
+use std::collections::HashMap;
+
+use libafl::HasMetadata;
+use libafl_qemu::{
+    modules::{utils::filters::NopAddressFilter, EmulatorModule, EmulatorModuleTuple},
+    EmulatorModules, GuestAddr, Hook, Qemu, SyscallHookResult, SYS_brk, SYS_close, SYS_exit,
+    SYS_exit_group, SYS_kill, SYS_lseek, SYS_mmap, SYS_munmap, SYS_openat, SYS_read, SYS_tgkill,
+    SYS_tkill, SYS_write,
+};
+use serde::{Deserialize, Serialize};
+
+/// How many times the target made each syscall, over all the executions of the client, see `--syscall-stats`
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SyscallStats {
+    pub counts: HashMap<i64, u64>,
+}
+
+libafl_bolts::impl_serdeany!(SyscallStats);
+
+impl SyscallStats {
+    /// The `n` most frequent syscalls with their counts, most frequent first
+    pub fn top(&self, n: usize) -> Vec<(i64, u64)> {
+        let mut counts = self.counts.iter().map(|(num, count)| (*num, *count)).collect::<Vec<_>>();
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        counts.truncate(n);
+        counts
+    }
+
+    /// The name of the common syscalls, the number of the others
+    pub fn name(num: i64) -> String {
+        let name = match num {
+            n if n == SYS_read => "read",
+            n if n == SYS_write => "write",
+            n if n == SYS_openat => "openat",
+            n if n == SYS_close => "close",
+            n if n == SYS_lseek => "lseek",
+            n if n == SYS_mmap => "mmap",
+            n if n == SYS_munmap => "munmap",
+            n if n == SYS_brk => "brk",
+            n if n == SYS_exit => "exit",
+            n if n == SYS_exit_group => "exit_group",
+            n if n == SYS_kill => "kill",
+            n if n == SYS_tkill => "tkill",
+            n if n == SYS_tgkill => "tgkill",
+            _ => return format!("syscall_{num}"),
+        };
+        name.to_string()
+    }
+}
+
+/// Counts every syscall of the target into the [`SyscallStats`] of the state. Disabled by default.
+#[derive(Default, Debug)]
+pub struct SyscallStatsModule {
+    enabled: bool,
+    filter: NopAddressFilter,
+}
+
+impl SyscallStatsModule {
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            filter: NopAddressFilter,
+        }
+    }
+}
+
+impl<I, S> EmulatorModule<I, S> for SyscallStatsModule
+where
+    S: Unpin + HasMetadata,
+    I: Unpin,
+{
+    type ModuleAddressFilter = NopAddressFilter;
+
+    fn first_exec<ET>(
+        &mut self,
+        _qemu: Qemu,
+        _emulator_modules: &mut EmulatorModules<ET, I, S>,
+        _state: &mut S,
+    ) where
+        ET: EmulatorModuleTuple<I, S>,
+    {
+        if !self.enabled {
+            return;
+        }
+
+        log::debug!("SyscallStatsModule::first_exec running ...");
+        if let Some(hook_id) =
+            _emulator_modules.pre_syscalls(Hook::Function(syscall_hooks::<ET, I, S>))
+        {
+            log::debug!("Hook {:?} installed", hook_id);
+        } else {
+            log::error!("Failed to install hook");
+        }
+    }
+
+    fn address_filter(&self) -> &Self::ModuleAddressFilter {
+        &self.filter
+    }
+
+    fn address_filter_mut(&mut self) -> &mut Self::ModuleAddressFilter {
+        &mut self.filter
+    }
+}
+
+fn syscall_hooks<ET, I, S>(
+    _qemu: Qemu,
+    _emulator_modules: &mut EmulatorModules<ET, I, S>,
+    state: Option<&mut S>,
+    sys_num: i32,
+    _a0: GuestAddr,
+    _a1: GuestAddr,
+    _a2: GuestAddr,
+    _a3: GuestAddr,
+    _a4: GuestAddr,
+    _a5: GuestAddr,
+    _a6: GuestAddr,
+    _a7: GuestAddr,
+) -> SyscallHookResult
+where
+    S: Unpin + HasMetadata,
+    I: Unpin,
+    ET: EmulatorModuleTuple<I, S>,
+{
+    if let Some(state) = state {
+        *state
+            .metadata_or_insert_with(SyscallStats::default)
+            .counts
+            .entry(sys_num as i64)
+            .or_default() += 1;
+    }
+
+    // Only counted, the syscall happens as usual
+    SyscallHookResult::new(None)
+}
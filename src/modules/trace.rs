@@ -0,0 +1,117 @@
+use std::collections::VecDeque;
+
+use libafl_qemu::{
+    modules::{utils::filters::NopAddressFilter, EmulatorModule, EmulatorModuleTuple},
+    EmulatorModules, GuestAddr, Hook, Qemu,
+};
+
+/// Records the last `depth` executed blocks, an approximate backtrace attached to the
+/// [`crate::modules::CrashInfo`] of a crash. Every block execution is hooked, so keep `depth` small.
+/// A `depth` of 0 disables the module.
+#[derive(Default, Debug)]
+pub struct BlockTraceModule {
+    depth: usize,
+    ring: VecDeque<GuestAddr>,
+    filter: NopAddressFilter,
+}
+
+impl BlockTraceModule {
+    pub fn new(depth: usize) -> Self {
+        Self {
+            depth,
+            ring: VecDeque::with_capacity(depth),
+            filter: NopAddressFilter,
+        }
+    }
+
+    /// The recorded blocks, oldest first
+    pub fn trace(&self) -> Vec<GuestAddr> {
+        self.ring.iter().copied().collect()
+    }
+
+    fn record(&mut self, pc: GuestAddr) {
+        if self.ring.len() == self.depth {
+            self.ring.pop_front();
+        }
+        self.ring.push_back(pc);
+    }
+}
+
+impl<I, S> EmulatorModule<I, S> for BlockTraceModule
+where
+    S: Unpin,
+    I: Unpin,
+{
+    type ModuleAddressFilter = NopAddressFilter;
+
+    fn first_exec<ET>(
+        &mut self,
+        _qemu: Qemu,
+        _emulator_modules: &mut EmulatorModules<ET, I, S>,
+        _state: &mut S,
+    ) where
+        ET: EmulatorModuleTuple<I, S>,
+    {
+        if self.depth == 0 {
+            return;
+        }
+
+        log::debug!("BlockTraceModule::first_exec running ...");
+        let hook_id = _emulator_modules.blocks(
+            Hook::Function(gen_block_id::<ET, I, S>),
+            Hook::Empty,
+            Hook::Function(on_block::<ET, I, S>),
+        );
+        log::debug!("Hook {:?} installed", hook_id);
+    }
+
+    fn pre_exec<ET>(
+        &mut self,
+        _qemu: Qemu,
+        _emulator_modules: &mut EmulatorModules<ET, I, S>,
+        _state: &mut S,
+        _input: &I,
+    ) where
+        ET: EmulatorModuleTuple<I, S>,
+    {
+        self.ring.clear();
+    }
+
+    fn address_filter(&self) -> &Self::ModuleAddressFilter {
+        &self.filter
+    }
+
+    fn address_filter_mut(&mut self) -> &mut Self::ModuleAddressFilter {
+        &mut self.filter
+    }
+}
+
+/// The block id given to the execution hook is simply its address
+fn gen_block_id<ET, I, S>(
+    _qemu: Qemu,
+    _emulator_modules: &mut EmulatorModules<ET, I, S>,
+    _state: Option<&mut S>,
+    pc: GuestAddr,
+) -> Option<u64>
+where
+    S: Unpin,
+    I: Unpin,
+    ET: EmulatorModuleTuple<I, S>,
+{
+    Some(pc as u64)
+}
+
+fn on_block<ET, I, S>(
+    _qemu: Qemu,
+    emulator_modules: &mut EmulatorModules<ET, I, S>,
+    _state: Option<&mut S>,
+    id: u64,
+) where
+    S: Unpin,
+    I: Unpin,
+    ET: EmulatorModuleTuple<I, S>,
+{
+    if let Some(module) = emulator_modules.get_mut::<BlockTraceModule>() {
+        module.record(id as GuestAddr);
+    }
+}
@@ -0,0 +1,56 @@
+use std::borrow::Cow;
+
+use libafl::{
+    corpus::CorpusId,
+    inputs::HasMutatorBytes,
+    mutators::{MutationResult, Mutator},
+    Error,
+};
+use libafl_bolts::Named;
+
+/// Truncates the inputs `inner` grows by more than `max_grow` bytes in a single mutation (a whole havoc stack),
+/// see `--max-grow`. The input can still grow by `max_grow` bytes every time it is mutated.
+#[derive(Debug)]
+pub struct MaxGrowMutator<M> {
+    inner: M,
+    max_grow: Option<usize>,
+}
+
+impl<M> MaxGrowMutator<M> {
+    /// Clamp nothing unless given a `max_grow`
+    pub fn new(inner: M, max_grow: Option<usize>) -> Self {
+        Self { inner, max_grow }
+    }
+}
+
+impl<M> Named for MaxGrowMutator<M>
+where
+    M: Named,
+{
+    fn name(&self) -> &Cow<'static, str> {
+        self.inner.name()
+    }
+}
+
+impl<I, M, S> Mutator<I, S> for MaxGrowMutator<M>
+where
+    M: Mutator<I, S>,
+    I: HasMutatorBytes,
+{
+    fn mutate(&mut self, state: &mut S, input: &mut I) -> Result<MutationResult, Error> {
+        let Some(max_grow) = self.max_grow else {
+            return self.inner.mutate(state, input);
+        };
+
+        let max_len = input.bytes().len() + max_grow;
+        let result = self.inner.mutate(state, input)?;
+        if input.bytes().len() > max_len {
+            input.resize(max_len, 0);
+        }
+        Ok(result)
+    }
+
+    fn post_exec(&mut self, state: &mut S, new_corpus_id: Option<CorpusId>) -> Result<(), Error> {
+        self.inner.post_exec(state, new_corpus_id)
+    }
+}
@@ -0,0 +1,9 @@
+pub mod grow;
+pub mod predicate;
+pub mod provenance;
+pub mod record;
+
+pub use grow::MaxGrowMutator;
+pub use predicate::{InputPredicate, PredicateMutator};
+pub use provenance::{ProvenanceMutator, TrailMapper};
+pub use record::{MutationLog, MutationLogMutator};
@@ -0,0 +1,180 @@
+use std::borrow::Cow;
+
+use libafl::{
+    corpus::CorpusId,
+    inputs::HasTargetBytes,
+    mutators::{MutationResult, Mutator},
+    Error,
+};
+use libafl_bolts::Named;
+
+/// A comparison of an `--input-predicate` term
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Op {
+    fn holds(self, lhs: u64, rhs: u64) -> bool {
+        match self {
+            Op::Eq => lhs == rhs,
+            Op::Ne => lhs != rhs,
+            Op::Lt => lhs < rhs,
+            Op::Le => lhs <= rhs,
+            Op::Gt => lhs > rhs,
+            Op::Ge => lhs >= rhs,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Term {
+    /// `len <op> <n>`
+    Len(Op, u64),
+    /// `[<offset>] <op> <byte>`, false if the input is shorter
+    Byte(usize, Op, u8),
+    /// `prefix <hex>` or `prefix "<text>"`
+    Prefix(Vec<u8>),
+}
+
+impl Term {
+    fn holds(&self, bytes: &[u8]) -> bool {
+        match self {
+            Term::Len(op, n) => op.holds(bytes.len() as u64, *n),
+            Term::Byte(offset, op, value) => bytes
+                .get(*offset)
+                .is_some_and(|byte| op.holds(u64::from(*byte), u64::from(*value))),
+            Term::Prefix(prefix) => bytes.starts_with(prefix),
+        }
+    }
+}
+
+/// A condition on the inputs, see `--input-predicate`: terms joined by `&&`, each one of
+/// `len <op> <n>`, `[<offset>] <op> <byte>` and `prefix <hex bytes>` or `prefix "<text>"`,
+/// with `<op>` one of `==`, `!=`, `<`, `<=`, `>`, `>=` and the numbers decimal or `0x` hexadecimal.
+/// For example `[0] == 0x89 && len >= 8` or `prefix 0x89504e47`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InputPredicate {
+    terms: Vec<Term>,
+}
+
+impl InputPredicate {
+    pub fn parse(src: &str) -> Result<Self, Error> {
+        let terms = src
+            .split("&&")
+            .map(|term| Self::parse_term(term.trim()))
+            .collect::<Result<Vec<Term>, Error>>()?;
+        Ok(Self { terms })
+    }
+
+    fn parse_term(term: &str) -> Result<Term, Error> {
+        let invalid = |what: &str| Error::illegal_argument(format!("Invalid predicate term `{term}`: {what}"));
+
+        if let Some(prefix) = term.strip_prefix("prefix") {
+            let prefix = prefix.trim();
+            let bytes = if let Some(text) = prefix.strip_prefix('"').and_then(|p| p.strip_suffix('"')) {
+                text.as_bytes().to_vec()
+            } else {
+                let hex = prefix
+                    .strip_prefix("0x")
+                    .ok_or_else(|| invalid("expected 0x<hex bytes> or \"<text>\""))?;
+                if hex.is_empty() || hex.len() % 2 != 0 {
+                    return Err(invalid("expected an even number of hex digits"));
+                }
+                (0..hex.len())
+                    .step_by(2)
+                    .map(|i| u8::from_str_radix(&hex[i..i + 2], 16))
+                    .collect::<Result<Vec<u8>, _>>()
+                    .map_err(|e| invalid(&e.to_string()))?
+            };
+            return Ok(Term::Prefix(bytes));
+        }
+
+        // The two character operators first, `<=` must not be taken for `<`
+        let (lhs, op, rhs) = [
+            ("==", Op::Eq),
+            ("!=", Op::Ne),
+            ("<=", Op::Le),
+            (">=", Op::Ge),
+            ("<", Op::Lt),
+            (">", Op::Gt),
+        ]
+        .iter()
+        .find_map(|(token, op)| {
+            term.split_once(token)
+                .map(|(lhs, rhs)| (lhs.trim(), *op, rhs.trim()))
+        })
+        .ok_or_else(|| invalid("expected `len <op> <n>`, `[<offset>] <op> <byte>` or `prefix <bytes>`"))?;
+        let rhs = parse_number(rhs).ok_or_else(|| invalid(&format!("invalid number {rhs}")))?;
+
+        if lhs == "len" {
+            return Ok(Term::Len(op, rhs));
+        }
+        let offset = lhs
+            .strip_prefix('[')
+            .and_then(|lhs| lhs.strip_suffix(']'))
+            .and_then(|offset| parse_number(offset.trim()))
+            .ok_or_else(|| invalid(&format!("expected `len` or `[<offset>]`, got {lhs}")))?;
+        let byte = u8::try_from(rhs).map_err(|_| invalid(&format!("{rhs} is not a byte")))?;
+        Ok(Term::Byte(offset as usize, op, byte))
+    }
+
+    pub fn holds(&self, bytes: &[u8]) -> bool {
+        self.terms.iter().all(|term| term.holds(bytes))
+    }
+}
+
+fn parse_number(src: &str) -> Option<u64> {
+    match src.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => src.parse().ok(),
+    }
+}
+
+/// Skips the mutations of `inner` that don't satisfy the [`InputPredicate`], see `--input-predicate`.
+/// A mutational stage doesn't run a skipped mutation, so the inputs are filtered before they are executed,
+/// which a stage of its own can't do as the mutational stages run their inputs themselves.
+#[derive(Debug)]
+pub struct PredicateMutator<M> {
+    inner: M,
+    predicate: Option<InputPredicate>,
+}
+
+impl<M> PredicateMutator<M> {
+    /// Filter nothing unless given a `predicate`
+    pub fn new(inner: M, predicate: Option<InputPredicate>) -> Self {
+        Self { inner, predicate }
+    }
+}
+
+impl<M> Named for PredicateMutator<M>
+where
+    M: Named,
+{
+    fn name(&self) -> &Cow<'static, str> {
+        self.inner.name()
+    }
+}
+
+impl<I, M, S> Mutator<I, S> for PredicateMutator<M>
+where
+    M: Mutator<I, S>,
+    I: HasTargetBytes,
+{
+    fn mutate(&mut self, state: &mut S, input: &mut I) -> Result<MutationResult, Error> {
+        let result = self.inner.mutate(state, input)?;
+        match &self.predicate {
+            Some(predicate) if !predicate.holds(&input.target_bytes()) => Ok(MutationResult::Skipped),
+            _ => Ok(result),
+        }
+    }
+
+    fn post_exec(&mut self, state: &mut S, new_corpus_id: Option<CorpusId>) -> Result<(), Error> {
+        self.inner.post_exec(state, new_corpus_id)
+    }
+}
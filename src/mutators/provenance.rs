@@ -0,0 +1,136 @@
+use std::borrow::Cow;
+
+use libafl::{
+    corpus::{Corpus, CorpusId},
+    mutators::{MutationResult, Mutator},
+    state::{HasCorpus, HasCurrentCorpusId},
+    Error, HasMetadata,
+};
+use libafl_bolts::{tuples::MappingFunctor, Named};
+use serde::{Deserialize, Serialize};
+
+/// Where a corpus entry or a solution comes from, see `--provenance`: the entry it was mutated from and
+/// the mutators applied to it. Following `parent_name` from a crash back to a seed gives its lineage.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Provenance {
+    /// The corpus id of the parent in its client
+    pub parent: Option<CorpusId>,
+    /// The file name of the parent in the queue, i.e. the hash of its input
+    pub parent_name: Option<String>,
+    /// The mutations from the seed, a seed being at depth 0
+    pub depth: u64,
+    /// The mutators applied to the parent, in order
+    pub mutations: Vec<Cow<'static, str>>,
+}
+
+libafl_bolts::impl_serdeany!(Provenance);
+
+/// The [`Provenance`] of the input being mutated and run, kept in the state until the run is evaluated
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct MutationTrail(pub Provenance);
+
+libafl_bolts::impl_serdeany!(MutationTrail);
+
+/// Starts the [`MutationTrail`] of every mutation of `inner`, from the corpus entry being mutated,
+/// see `--provenance`. Without `enabled` it only forwards to `inner`.
+#[derive(Debug)]
+pub struct ProvenanceMutator<M> {
+    inner: M,
+    enabled: bool,
+}
+
+impl<M> ProvenanceMutator<M> {
+    pub fn new(inner: M, enabled: bool) -> Self {
+        Self { inner, enabled }
+    }
+}
+
+impl<M> Named for ProvenanceMutator<M>
+where
+    M: Named,
+{
+    fn name(&self) -> &Cow<'static, str> {
+        self.inner.name()
+    }
+}
+
+impl<I, M, S> Mutator<I, S> for ProvenanceMutator<M>
+where
+    M: Mutator<I, S>,
+    S: HasMetadata + HasCorpus<I> + HasCurrentCorpusId,
+{
+    fn mutate(&mut self, state: &mut S, input: &mut I) -> Result<MutationResult, Error> {
+        if !self.enabled {
+            return self.inner.mutate(state, input);
+        }
+
+        let mut provenance = Provenance::default();
+        if let Some(id) = state.current_corpus_id()? {
+            let parent = state.corpus().get(id)?.borrow();
+            provenance.parent = Some(id);
+            provenance.parent_name = parent.filename().clone();
+            provenance.depth = parent.metadata::<Provenance>().map_or(0, |p| p.depth) + 1;
+        }
+        state.add_metadata(MutationTrail(provenance));
+        let result = self.inner.mutate(state, input)?;
+        if result == MutationResult::Skipped {
+            // Not run, so no post_exec clears it
+            state.metadata_map_mut().remove::<MutationTrail>();
+        }
+        Ok(result)
+    }
+
+    fn post_exec(&mut self, state: &mut S, new_corpus_id: Option<CorpusId>) -> Result<(), Error> {
+        // The run is evaluated, the next executions (e.g. calibration) aren't this mutation's
+        state.metadata_map_mut().remove::<MutationTrail>();
+        self.inner.post_exec(state, new_corpus_id)
+    }
+}
+
+/// Adds its name to the [`MutationTrail`] every time `inner` mutates, see [`TrailMapper`]
+#[derive(Debug)]
+pub struct TrailMutator<M> {
+    inner: M,
+}
+
+impl<M> Named for TrailMutator<M>
+where
+    M: Named,
+{
+    fn name(&self) -> &Cow<'static, str> {
+        self.inner.name()
+    }
+}
+
+impl<I, M, S> Mutator<I, S> for TrailMutator<M>
+where
+    M: Mutator<I, S> + Named,
+    S: HasMetadata,
+{
+    fn mutate(&mut self, state: &mut S, input: &mut I) -> Result<MutationResult, Error> {
+        let result = self.inner.mutate(state, input)?;
+        if result == MutationResult::Mutated {
+            if let Some(trail) = state.metadata_map_mut().get_mut::<MutationTrail>() {
+                trail.0.mutations.push(self.inner.name().clone());
+            }
+        }
+        Ok(result)
+    }
+
+    fn post_exec(&mut self, state: &mut S, new_corpus_id: Option<CorpusId>) -> Result<(), Error> {
+        self.inner.post_exec(state, new_corpus_id)
+    }
+}
+
+/// Wraps every mutator of a tuple in a [`TrailMutator`], e.g. `havoc_mutations().map(TrailMapper)`.
+/// The mutators only log themselves while a [`ProvenanceMutator`] has a trail started.
+#[derive(Debug, Clone, Copy)]
+pub struct TrailMapper;
+
+impl<M> MappingFunctor<M> for TrailMapper {
+    type Output = TrailMutator<M>;
+
+    fn apply(&mut self, from: M) -> Self::Output {
+        TrailMutator { inner: from }
+    }
+}
@@ -0,0 +1,205 @@
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    fs::{self, File, OpenOptions},
+    io::{LineWriter, Write},
+    path::Path,
+};
+
+use libafl::{
+    corpus::CorpusId,
+    mutators::{MutationResult, Mutator},
+    state::{HasCurrentCorpusId, HasRand},
+    Error, HasMetadata,
+};
+use libafl_bolts::{current_nanos, rands::Rand, Named};
+use serde::{Deserialize, Serialize};
+
+/// The mutations done so far, kept in the state so that a restarted client carries on with the next step of the log
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct MutationStep(pub u64);
+
+libafl_bolts::impl_serdeany!(MutationStep);
+
+enum Mode {
+    Record(LineWriter<File>),
+    /// The recorded seed and base corpus entry of every step
+    Replay(HashMap<u64, (Option<CorpusId>, u64)>),
+}
+
+/// A log of the mutations of a client, see `--record-mutations` and `--replay-mutations`. Its format is
+///
+/// ```text
+/// seed <rng seed of the state>
+/// <step> <base corpus id or -> <rng seed of the mutation>
+/// restart
+/// ```
+///
+/// with a `restart` line where the client restarted, keeping its state.
+pub struct MutationLog {
+    mode: Mode,
+    seed: Option<u64>,
+    // The step the replay ended at, warned about once
+    replay_end: Option<u64>,
+}
+
+impl MutationLog {
+    /// Append the mutations to `path`. A client starting from scratch draws the seed of its state,
+    /// a restarted one (`fresh` false) keeps the one of its restored state.
+    pub fn record(path: &Path, fresh: bool) -> Result<Self, Error> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        // Every step is on disk before its input runs, a crash must not lose the steps leading to it
+        let mut writer = LineWriter::new(file);
+        let seed = fresh.then(current_nanos);
+        match seed {
+            Some(seed) => writeln!(writer, "seed {seed}")?,
+            None => writeln!(writer, "restart")?,
+        }
+        Ok(Self {
+            mode: Mode::Record(writer),
+            seed,
+            replay_end: None,
+        })
+    }
+
+    /// Replay the mutations recorded at `path`
+    pub fn replay(path: &Path) -> Result<Self, Error> {
+        let content = fs::read_to_string(path)?;
+        let invalid = |i: usize, what: &str| {
+            Error::illegal_argument(format!("{}:{}: {what}", path.display(), i + 1))
+        };
+
+        let mut seed = None;
+        let mut steps = HashMap::new();
+        for (i, line) in content.lines().enumerate() {
+            let words = line.split_whitespace().collect::<Vec<&str>>();
+            match words.as_slice() {
+                [] | ["restart"] => {}
+                ["seed", value] => {
+                    if seed.is_some() {
+                        return Err(invalid(i, "more than one seed, the log must come from a single campaign"));
+                    }
+                    seed = Some(value.parse().map_err(|_| invalid(i, "invalid seed"))?);
+                }
+                [step, base, mutation_seed] => {
+                    let step = step.parse().map_err(|_| invalid(i, "invalid step"))?;
+                    let base = match *base {
+                        "-" => None,
+                        id => Some(CorpusId(id.parse().map_err(|_| invalid(i, "invalid corpus id"))?)),
+                    };
+                    let mutation_seed = mutation_seed
+                        .parse()
+                        .map_err(|_| invalid(i, "invalid mutation seed"))?;
+                    steps.insert(step, (base, mutation_seed));
+                }
+                _ => return Err(invalid(i, "expected `seed <n>`, `<step> <corpus id> <seed>` or `restart`")),
+            }
+        }
+        if seed.is_none() {
+            return Err(Error::illegal_argument(format!("{} records no seed", path.display())));
+        }
+
+        Ok(Self {
+            mode: Mode::Replay(steps),
+            seed,
+            replay_end: None,
+        })
+    }
+
+    /// The seed to start the state's rng with, none if it is restored
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    /// The rng seed of the mutation `step` of the entry `base`, recorded or drawn from `rand`
+    fn step_seed<R: Rand>(&mut self, step: u64, base: Option<CorpusId>, rand: &mut R) -> Result<u64, Error> {
+        match &mut self.mode {
+            Mode::Record(writer) => {
+                let seed = rand.next();
+                let base = base.map_or_else(|| "-".to_string(), |id| id.0.to_string());
+                writeln!(writer, "{step} {base} {seed}")?;
+                Ok(seed)
+            }
+            Mode::Replay(steps) => match steps.get(&step) {
+                Some((recorded, seed)) => {
+                    if *recorded != base {
+                        log::warn!(
+                            "Mutation {step} was recorded on corpus entry {recorded:?}, replayed on {base:?}: the replay diverged"
+                        );
+                    }
+                    Ok(*seed)
+                }
+                None => {
+                    if self.replay_end.is_none() {
+                        log::info!("Replayed the recorded mutations, mutation {step} on are random again");
+                        self.replay_end = Some(step);
+                    }
+                    Ok(rand.next())
+                }
+            },
+        }
+    }
+}
+
+impl core::fmt::Debug for MutationLog {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let mode = match &self.mode {
+            Mode::Record(_) => "record",
+            Mode::Replay(_) => "replay",
+        };
+        f.debug_struct("MutationLog")
+            .field("mode", &mode)
+            .field("seed", &self.seed)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Reseeds the state's rng before every mutation of `inner`, with a seed recorded to or replayed from
+/// a [`MutationLog`]. A mutation only depends on the rng and its input (the corpus and tokens aside),
+/// so the seed of a step is enough to redo it. Without a log it only forwards to `inner`.
+#[derive(Debug)]
+pub struct MutationLogMutator<M> {
+    inner: M,
+    log: Option<MutationLog>,
+}
+
+impl<M> MutationLogMutator<M> {
+    pub fn new(inner: M, log: Option<MutationLog>) -> Self {
+        Self { inner, log }
+    }
+}
+
+impl<M> Named for MutationLogMutator<M>
+where
+    M: Named,
+{
+    fn name(&self) -> &Cow<'static, str> {
+        self.inner.name()
+    }
+}
+
+impl<I, M, S> Mutator<I, S> for MutationLogMutator<M>
+where
+    M: Mutator<I, S>,
+    S: HasRand + HasMetadata + HasCurrentCorpusId,
+{
+    fn mutate(&mut self, state: &mut S, input: &mut I) -> Result<MutationResult, Error> {
+        let Some(log) = &mut self.log else {
+            return self.inner.mutate(state, input);
+        };
+
+        let step = {
+            let step = state.metadata_or_insert_with(MutationStep::default);
+            step.0 += 1;
+            step.0
+        };
+        let base = state.current_corpus_id()?;
+        let seed = log.step_seed(step, base, state.rand_mut())?;
+        state.rand_mut().set_seed(seed);
+        self.inner.mutate(state, input)
+    }
+
+    fn post_exec(&mut self, state: &mut S, new_corpus_id: Option<CorpusId>) -> Result<(), Error> {
+        self.inner.post_exec(state, new_corpus_id)
+    }
+}
@@ -0,0 +1,211 @@
+use core::fmt::Debug;
+use std::{
+    fs::{File, OpenOptions},
+    io::{Read, Seek, SeekFrom, Write},
+    path::PathBuf,
+    process::{Command, Stdio},
+    thread,
+};
+
+use libafl::{executors::ExitKind, Error};
+use libafl_bolts::current_time;
+use nix::fcntl::{Flock, FlockArg};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+use crate::modules::CrashInfo;
+
+/// What was added, see [`EventSink`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventKind {
+    Solution,
+    Corpus,
+}
+
+impl EventKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            EventKind::Solution => "solution",
+            EventKind::Corpus => "corpus",
+        }
+    }
+}
+
+/// A new solution or corpus entry, as passed to an [`EventSink`]
+#[derive(Debug, Clone)]
+pub struct EventInfo {
+    pub kind: EventKind,
+    /// Where the input is saved, the file exists when the sink is called
+    pub path: PathBuf,
+    pub client: usize,
+    pub exit_kind: ExitKind,
+    /// Executions of the client so far
+    pub executions: u64,
+    pub len: usize,
+    /// How a solution crashed, if the objective classified it
+    pub crash: Option<CrashInfo>,
+}
+
+/// Gets told about the new solutions and corpus entries of a client, to trigger external actions
+/// (e.g. a notification). See [`crate::feedbacks::notify::EventSinkFeedback`], which calls it.
+pub trait EventSink: Debug {
+    fn on_new_solution(&mut self, _info: &EventInfo) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn on_new_corpus(&mut self, _info: &EventInfo) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+/// Runs a shell command for every new solution, see `--on-crash-cmd`.
+/// The command gets the input path as `$1` and the [`EventInfo`] in `FUZZ_*` environment variables.
+/// It runs in the background, the fuzzer doesn't wait for it.
+#[derive(Debug)]
+pub struct CommandSink {
+    command: String,
+}
+
+impl CommandSink {
+    pub fn new(command: String) -> Self {
+        Self { command }
+    }
+}
+
+impl EventSink for CommandSink {
+    fn on_new_solution(&mut self, info: &EventInfo) -> Result<(), Error> {
+        let mut child = Command::new("sh")
+            .arg("-c")
+            .arg(&self.command)
+            .arg("sh")
+            .arg(&info.path)
+            .env("FUZZ_EVENT", info.kind.as_str())
+            .env("FUZZ_INPUT", &info.path)
+            .env("FUZZ_CLIENT", info.client.to_string())
+            .env("FUZZ_EXIT_KIND", format!("{:?}", info.exit_kind))
+            .env("FUZZ_EXECUTIONS", info.executions.to_string())
+            .env("FUZZ_INPUT_LEN", info.len.to_string())
+            .stdin(Stdio::null())
+            .spawn()
+            .map_err(|e| Error::unknown(format!("Failed to run {:?}: {e}", self.command)))?;
+        // Reap it whenever it's done
+        thread::spawn(move || child.wait());
+        Ok(())
+    }
+}
+
+/// Records every new solution in a machine-readable report, see `--crash-report`: a JSON object per line,
+/// or a SARIF log if the file ends with `.sarif`. The clients share the file, it's locked while written.
+#[derive(Debug)]
+pub struct CrashReportSink {
+    path: PathBuf,
+    sarif: bool,
+}
+
+impl CrashReportSink {
+    pub fn new(path: PathBuf) -> Self {
+        let sarif = path.extension().is_some_and(|ext| ext == "sarif");
+        Self { path, sarif }
+    }
+
+    /// A hash of the faulting PC and the recorded trace (see `--crash-trace-depth`), the same for the crashes
+    /// taking the same path to the same place
+    fn backtrace_hash(crash: &CrashInfo) -> String {
+        let mut hasher = Sha256::new();
+        for addr in crash.pc.iter().chain(&crash.trace) {
+            hasher.update((*addr as u64).to_le_bytes());
+        }
+        format!("{:x}", hasher.finalize())[..16].to_string()
+    }
+
+    fn record(info: &EventInfo) -> Value {
+        let crash = info.crash.as_ref();
+        json!({
+            "input": info.path,
+            "size": info.len,
+            "exit_kind": format!("{:?}", info.exit_kind),
+            "signal": crash.and_then(|crash| crash.signal),
+            "pc": crash.and_then(|crash| crash.pc).map(|pc| format!("{pc:#x}")),
+            "backtrace_hash": crash.map(Self::backtrace_hash),
+            "client": info.client,
+            "executions": info.executions,
+            "time": current_time().as_secs(),
+        })
+    }
+
+    fn sarif_result(info: &EventInfo) -> Value {
+        let crash = info.crash.as_ref();
+        let rule = match (info.exit_kind, crash.and_then(|crash| crash.signal)) {
+            (ExitKind::Timeout, _) => "timeout".to_string(),
+            (_, Some(signal)) => format!("signal-{signal}"),
+            (exit_kind, None) => format!("{exit_kind:?}").to_lowercase(),
+        };
+        let pc = crash.and_then(|crash| crash.pc);
+        let mut location = json!({ "artifactLocation": { "uri": info.path } });
+        if let Some(pc) = pc {
+            location["address"] = json!({ "absoluteAddress": pc });
+        }
+        json!({
+            "ruleId": rule,
+            "level": "error",
+            "message": {
+                "text": match pc {
+                    Some(pc) => format!("{rule} at {pc:#x} with a {} byte input", info.len),
+                    None => format!("{rule} with a {} byte input", info.len),
+                }
+            },
+            "locations": [{ "physicalLocation": location }],
+            "partialFingerprints": { "backtraceHash/v1": crash.map(Self::backtrace_hash) },
+        })
+    }
+
+    /// Add the solution to the SARIF log in `file`, creating the log if the file is empty
+    fn append_sarif(file: &mut File, info: &EventInfo) -> Result<(), Error> {
+        let mut content = String::new();
+        file.read_to_string(&mut content)?;
+        let mut log = if content.trim().is_empty() {
+            json!({
+                "version": "2.1.0",
+                "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+                "runs": [{
+                    "tool": { "driver": { "name": "h1k0_qemu_launcher", "version": env!("CARGO_PKG_VERSION") } },
+                    "results": [],
+                }],
+            })
+        } else {
+            serde_json::from_str(&content)
+                .map_err(|e| Error::serialize(format!("Invalid SARIF log: {e}")))?
+        };
+        log["runs"][0]["results"]
+            .as_array_mut()
+            .ok_or_else(|| Error::illegal_state("The SARIF log has no results"))?
+            .push(Self::sarif_result(info));
+
+        let json = serde_json::to_string_pretty(&log)
+            .map_err(|e| Error::serialize(format!("Failed to serialize the SARIF log: {e}")))?;
+        file.set_len(0)?;
+        file.seek(SeekFrom::Start(0))?;
+        file.write_all(json.as_bytes())?;
+        Ok(())
+    }
+}
+
+impl EventSink for CrashReportSink {
+    fn on_new_solution(&mut self, info: &EventInfo) -> Result<(), Error> {
+        let file = OpenOptions::new()
+            .read(true)
+            .create(true)
+            .append(!self.sarif)
+            .write(true)
+            .open(&self.path)?;
+        let mut file = Flock::lock(file, FlockArg::LockExclusive)
+            .map_err(|(_, e)| Error::unknown(format!("Failed to lock {:?}: {e}", self.path)))?;
+
+        if self.sarif {
+            Self::append_sarif(&mut file, info)
+        } else {
+            writeln!(file, "{}", Self::record(info))?;
+            Ok(())
+        }
+    }
+}
@@ -1,15 +1,90 @@
 use core::time::Duration;
-use std::{env, ops::Range, path::PathBuf};
+use std::{
+    env,
+    fs::{self, File},
+    ops::Range,
+    path::PathBuf,
+};
 
-use clap::{error::ErrorKind, CommandFactory, Parser};
+use clap::{error::ErrorKind, CommandFactory, Parser, ValueEnum};
 use libafl::{events::ClientDescription, Error};
 use libafl_bolts::core_affinity::{CoreId, Cores};
-use libafl_qemu::GuestAddr;
+use libafl_qemu::{GuestAddr, Qemu};
+use nix::sys::statfs::{statfs, RAMFS_MAGIC, TMPFS_MAGIC};
+use regex::bytes::Regex;
+use serde::{Serialize, Serializer};
 
-use crate::version::Version;
+use crate::{
+    harness::{Harness, MAX_INPUT_SIZE},
+    mutators::InputPredicate,
+    version::Version,
+};
+
+/// Which coverage changes make an input interesting
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize)]
+pub enum CoverageMode {
+    /// Only newly hit edges, keeps the corpus small but misses loop count changes
+    Edges,
+    /// New edges and new hitcount buckets, more sensitive but grows a bigger corpus
+    Hitcounts,
+}
+
+/// What a `read()` returns once the input is exhausted
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Serialize)]
+pub enum EofBehavior {
+    /// 0, i.e. end of file, as for a regular file
+    #[default]
+    Zero,
+    /// -1 with `EIO`, for targets looping on repeated 0-length reads
+    Error,
+    /// Rewind and serve the input again, for targets expecting an endless stream
+    Loop,
+}
+
+/// An encoding of the input the target sees, picked by the `--header-selector` byte
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize)]
+pub enum Encoding {
+    /// The bytes as they are
+    Raw,
+    /// Base64, standard or URL-safe
+    Base64,
+    /// Hexadecimal digits
+    Hex,
+}
+
+/// How `--print-mappings` prints the guest memory map
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Serialize)]
+pub enum MappingsFormat {
+    /// One aligned row per mapping
+    #[default]
+    Table,
+    /// A JSON array of mappings
+    Json,
+}
+
+/// How each run of the target is isolated, see `--executor`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Serialize)]
+pub enum ExecutorKind {
+    /// Run in the fuzzer process and restore it with the snapshot/reset modules
+    #[default]
+    Inprocess,
+    /// Run in a child forked for every execution
+    Fork,
+}
+
+/// How the input buffer and its length are passed to the harness entry, see `--calling-convention`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize)]
+pub enum CallConv {
+    /// The C convention of the guest architecture (System V on x86_64, the stack on i386, AAPCS on arm and aarch64, ...)
+    Cdecl,
+    /// i386 `__fastcall`, the first two arguments in `ecx` and `edx`
+    Fastcall,
+    /// The Windows x64 convention, the first two arguments in `rcx` and `rdx`
+    Win64,
+}
 
 #[readonly::make]
-#[derive(Parser, Debug)]
+#[derive(Parser, Debug, Serialize)]
 #[clap(author, version, about, long_about = None)]
 #[command(
     name = format!("qemu_coverage-{}",env!("CPU_TARGET")),
@@ -21,83 +96,953 @@ pub struct FuzzerOptions {
     #[arg(short, long, help = "Input directory")]
     pub input: String,
 
-    #[arg(short, long, help = "Output directory")]
-    pub output: String,
+    #[arg(short, long, help = "Output directory")]
+    pub output: String,
+
+    #[arg(
+        long,
+        help = "Also seed the corpus with an AFL/AFL++ queue (the queue directory, or the fuzzer directory containing it)"
+    )]
+    pub import_afl: Option<PathBuf>,
+
+    #[arg(
+        long,
+        help = "Test again the seeds a previous run of the campaign already tested. By default their sha256 is recorded in <output>/client_<id>/seen_seeds and a resumed client, re-importing its previous queue, skips them"
+    )]
+    pub no_dedup_seeds: bool,
+
+    #[arg(
+        long,
+        help = "Read the seed files ahead on this many threads while the first ones are being executed. It helps when the corpus is not in the page cache, and costs time when it is",
+        default_value_t = 1
+    )]
+    pub load_threads: usize,
+
+    #[arg(
+        long,
+        help = "Run the seeds once first and import them by decreasing coverage gain, so an interrupted import has the most useful ones",
+        conflicts_with = "load_threads"
+    )]
+    pub smart_import: bool,
+
+    #[arg(
+        long,
+        help = "When the client's queue holds a previous run, drop the loaded entries whose coverage the others already have"
+    )]
+    pub cull_on_resume: bool,
+
+    #[arg(
+        long,
+        help = "Run the corpus this many times after loading it, so qemu has translated its hot blocks before fuzzing",
+        default_value_t = 0
+    )]
+    pub warmup: usize,
+
+    #[arg(
+        long,
+        help = "Target binary used for ELF parsing, defaults to the binary qemu derives from the target arguments"
+    )]
+    pub target: Option<PathBuf>,
+
+    #[arg(
+        long,
+        help = "Comma-separated symbols to start each run at, the first one found in the target is used",
+        value_delimiter = ','
+    )]
+    pub harness_entry: Vec<String>,
+
+    #[arg(
+        long,
+        help = "Comma-separated symbols to end each run at, the first one found in the target is used",
+        value_delimiter = ','
+    )]
+    pub harness_exit: Vec<String>,
+
+    #[arg(
+        long,
+        help = "Function called once at the harness entry, before the snapshot, so that every run starts after its setup"
+    )]
+    pub setup_symbol: Option<String>,
+
+    #[arg(
+        long,
+        help = "Same as --setup-symbol, by offset (hex) from the target's load address",
+        value_parser = FuzzerOptions::parse_addr,
+        conflicts_with = "setup_symbol"
+    )]
+    pub setup_addr: Option<GuestAddr>,
+
+    #[arg(
+        long,
+        help = "File of REG=VALUE lines, the registers are set at the harness entry so that every run starts with them"
+    )]
+    pub init_regs: Option<PathBuf>,
+
+    #[arg(
+        long,
+        help = "Load a slice of the input into a register before every run, as REG:OFFSET:SIZE (little-endian, repeatable)"
+    )]
+    pub reg_input: Vec<String>,
+
+    #[arg(
+        long,
+        help = "Pass the input buffer and its length to the harness entry as its first two arguments, in this calling convention (e.g. for an entry like LLVMFuzzerTestOneInput)",
+        value_enum
+    )]
+    pub calling_convention: Option<CallConv>,
+
+    #[arg(short = 'x', long, help = "Tokens file")]
+    pub tokens: Option<String>,
+
+    #[cfg(feature = "injections")]
+    #[arg(
+        short = 'j',
+        long,
+        help = "Injections TOML or YAML file definition. Filename must end in .toml or .yaml/.yml."
+    )]
+    pub injections: Option<String>,
+
+    #[arg(
+        long,
+        help = "Directory for findings, i.e. non-crashing inputs flagged by a detector (default: <output>/client_<id>/findings)"
+    )]
+    pub findings_dir: Option<PathBuf>,
+
+    #[arg(long, help = "Write a <solution>.sh replaying it next to every solution")]
+    pub reproducers: bool,
+
+    #[arg(
+        long,
+        help = "Also copy every solution of every client into this shared directory, prefixed with the client's core"
+    )]
+    pub solutions_dir: Option<PathBuf>,
+
+    #[arg(
+        long,
+        help = "Name the solutions after their signal, faulting PC and size, like sig11_pc0x401234_len128_<hash>"
+    )]
+    pub descriptive_crash_names: bool,
+
+    #[arg(
+        long,
+        help = "Guest signals counting as crashes (comma separated, names or numbers), the runs ending on another signal are ignored like clean exits",
+        value_delimiter = ',',
+        value_parser = FuzzerOptions::parse_signal,
+        default_values = ["SIGSEGV", "SIGABRT", "SIGBUS", "SIGILL", "SIGFPE"]
+    )]
+    pub crash_signals: Vec<i32>,
+
+    #[arg(
+        long,
+        help = "Save every crash as a solution, by default only the crashes reaching new coverage are saved"
+    )]
+    pub all_crashes: bool,
+
+    #[arg(
+        long,
+        help = "Shell command run for every new solution, with the solution path as $1 and details in FUZZ_* variables"
+    )]
+    pub on_crash_cmd: Option<String>,
+
+    #[arg(
+        long,
+        help = "Append a JSON record of every new solution to this file (JSON lines), or a SARIF result if it ends with .sarif"
+    )]
+    pub crash_report: Option<PathBuf>,
+
+    #[arg(long, help = "Flag inputs making the target exit() with this code as findings")]
+    pub objective_exit_code: Option<i32>,
+
+    #[arg(
+        long,
+        help = "Treat the target's own abort() (SIGABRT) like a clean exit, by default it is a crash"
+    )]
+    pub ignore_aborts: bool,
+
+    #[arg(long, help = "Flag inputs making the target write this string as findings")]
+    pub finding_pattern: Option<String>,
+
+    #[arg(
+        long,
+        help = "Flag inputs making the target print a match of this regex to its stdout as findings",
+        value_parser = Regex::new
+    )]
+    #[serde(serialize_with = "serialize_regex")]
+    pub crash_on_output: Option<Regex>,
+
+    #[arg(
+        long,
+        help = "Fill the input buffer past the input with 0xCC, flagging the runs leaking it as uninitialized reads"
+    )]
+    pub canary_fill: bool,
+
+    #[arg(
+        long,
+        help = "Track the target's malloc/free, flagging the double frees and frees of invalid pointers as findings"
+    )]
+    pub heap_check: bool,
+
+    #[arg(long, help = "Stdout Log file (For LLMPManager)")]
+    pub log: Option<String>,
+
+    #[arg(long, help = "Client Stdout log file", requires = "client_stderr_file")]
+    pub client_stdout_file: Option<String>,
+
+    #[arg(long, help = "Client Stderr log file", requires = "client_stdout_file")]
+    pub client_stderr_file: Option<String>,
+
+    #[arg(long, help = "Timeout in milliseconds", default_value = "1000", value_parser = FuzzerOptions::parse_timeout)]
+    pub timeout: Duration,
+
+    #[arg(
+        long,
+        help = "Milliseconds added to the timeout per KB of the input, 0 to disable",
+        default_value = "0",
+        value_parser = FuzzerOptions::parse_timeout
+    )]
+    pub timeout_per_kb: Duration,
+
+    #[arg(
+        long,
+        help = "Upper bound in milliseconds of the timeout scaled by `--timeout-per-kb`",
+        default_value = "10000",
+        value_parser = FuzzerOptions::parse_timeout
+    )]
+    pub timeout_max: Duration,
+
+    #[arg(long = "port", help = "Broker port", default_value_t = 1337_u16)]
+    pub port: u16,
+
+    #[arg(
+        long,
+        help = "Exit with an error if the broker relays no message for this many seconds",
+        value_parser = FuzzerOptions::parse_seconds
+    )]
+    pub broker_stall_timeout: Option<Duration>,
+
+    #[arg(
+        long,
+        help = "Port of a standby broker (see --standby-broker) the clients fail over to when the broker on --port is gone",
+        conflicts_with_all = ["replay", "print_mappings", "profile"]
+    )]
+    pub standby_port: Option<u16>,
+
+    #[arg(
+        long,
+        help = "Run only a broker on --port, without clients, as the standby of a campaign started with --standby-port",
+        conflicts_with_all = ["replay", "print_mappings", "profile", "standby_port", "broker_stall_timeout"]
+    )]
+    pub standby_broker: bool,
+
+    #[arg(long, help = "Cpu cores to use", default_value = "all", value_parser = Cores::from_cmdline)]
+    pub cores: Cores,
+
+    #[arg(
+        long,
+        help = "Don't keep clients pinned to their core, for hosts (e.g. containers) where affinity is restricted"
+    )]
+    pub no_affinity: bool,
+
+    #[arg(long, help = "Cpu cores to use for ASan", value_parser = Cores::from_cmdline)]
+    pub asan_cores: Option<Cores>,
+
+    #[arg(long, help = "Cpu cores to use for ASan", value_parser = Cores::from_cmdline)]
+    pub asan_guest_cores: Option<Cores>,
+
+    #[arg(long, help = "Cpu cores to use for CmpLog", value_parser = Cores::from_cmdline)]
+    pub cmplog_cores: Option<Cores>,
+
+    #[clap(short, long, help = "Enable output from the fuzzer clients", conflicts_with_all = ["client_stdout_file", "client_stderr_file"])]
+    pub verbose: bool,
+
+    #[clap(long, help = "Enable AFL++ style output", conflicts_with = "verbose")]
+    pub tui: bool,
+
+    #[arg(
+        long,
+        help = "Fuzz in a single client running in the fuzzer's process, on the first of --cores, without the launcher or output redirection, e.g. to profile it with perf. The runs are forked as with --executor fork",
+        conflicts_with_all = ["replay", "print_mappings", "tui", "client_stdout_file", "client_stderr_file"]
+    )]
+    pub profile: bool,
+
+    #[arg(long = "iterations", help = "Maximum number of iterations")]
+    pub iterations: Option<u64>,
+
+    #[arg(
+        long,
+        help = "Stop the campaign after this many seconds",
+        value_parser = FuzzerOptions::parse_seconds,
+        conflicts_with = "iterations"
+    )]
+    pub max_time: Option<Duration>,
+
+    #[arg(
+        long,
+        help = "Re-seed the RNG when no new corpus entry appeared for this many seconds, the corpus is kept",
+        value_parser = FuzzerOptions::parse_seconds,
+        conflicts_with_all = ["record_mutations", "replay_mutations"]
+    )]
+    pub plateau_restart: Option<Duration>,
+
+    #[arg(long, help = "Warn when the global exec/sec stays below this rate for --min-exec-rate-window")]
+    pub min_exec_rate: Option<f64>,
+
+    #[arg(
+        long,
+        help = "Seconds the exec/sec must stay below --min-exec-rate before warning",
+        default_value = "60",
+        value_parser = FuzzerOptions::parse_seconds
+    )]
+    pub min_exec_rate_window: Duration,
+
+    #[arg(
+        long,
+        help = "Coverage feedback: `edges` keeps inputs hitting new edges only (smaller corpus), `hitcounts` also keeps new hitcount buckets (more sensitive)",
+        value_enum,
+        default_value_t = CoverageMode::Hitcounts
+    )]
+    pub coverage_mode: CoverageMode,
+
+    #[arg(
+        long,
+        help = "Estimate the basic blocks of the coverage region at startup and report edges found / blocks as the coverage_estimate stat (approximate)"
+    )]
+    pub coverage_estimate: bool,
+
+    #[arg(
+        long,
+        help = "Count the target's syscalls, reporting the most frequent per execution as the syscalls stat and all of them when the campaign ends"
+    )]
+    pub syscall_stats: bool,
+
+    #[arg(
+        long,
+        help = "Sample some runs to estimate how many edges share a coverage map slot, reported with the stats and at the end"
+    )]
+    pub coverage_diagnostics: bool,
+
+    #[arg(
+        long,
+        help = "Print the n slowest corpus entries with their execution times, after the seeds are loaded and when the campaign ends"
+    )]
+    pub slow_report: Option<usize>,
+
+    #[arg(
+        long,
+        help = "Also cover the target and each of its libraries in a map of their own, reporting their edges separately"
+    )]
+    pub split_coverage: bool,
+
+    #[arg(
+        long,
+        help = "Symbol of the target: corpus entries reaching it get --priority-weight times the power (on the cmplog cores, which use the power schedule)"
+    )]
+    pub priority_target: Option<String>,
+
+    #[arg(
+        long,
+        help = "Power multiplier of the corpus entries reaching --priority-target",
+        default_value_t = 4.0,
+        requires = "priority_target"
+    )]
+    pub priority_weight: f64,
+
+    #[arg(
+        long,
+        visible_alias = "trim",
+        help = "Trim every corpus entry once, as long as its coverage is preserved, to keep the corpus small"
+    )]
+    pub auto_trim: bool,
+
+    #[arg(
+        long,
+        help = "Calibrate every new corpus entry with a single run, without checking the stability of its coverage"
+    )]
+    pub skip_calibration: bool,
+
+    #[arg(
+        long,
+        help = "Fuzz without coverage feedback, the corpus never grows past the seeds (a baseline for evaluations)"
+    )]
+    pub blind: bool,
+
+    #[arg(
+        long,
+        help = "Schedule the corpus entries in insertion order instead of favoring the small and fast ones, for reproducible runs"
+    )]
+    pub fifo_schedule: bool,
+
+    #[arg(
+        long,
+        help = "Keep at most this many corpus entries, evicting the ones whose coverage is subsumed by the others",
+        value_parser = clap::value_parser!(u32).range(1..).map(|max| max as usize)
+    )]
+    pub max_corpus: Option<usize>,
+
+    #[arg(
+        long,
+        help = "Every this many fuzzing iterations, re-run a few corpus entries to refresh their timing and drop those whose coverage became unstable",
+        value_parser = clap::value_parser!(u64).range(1..)
+    )]
+    pub recalibrate_interval: Option<u64>,
+
+    #[arg(
+        long,
+        help = "Corpus entries re-run at every --recalibrate-interval",
+        default_value_t = 8,
+        value_parser = clap::value_parser!(u32).range(1..).map(|sample| sample as usize),
+        requires = "recalibrate_interval"
+    )]
+    pub recalibrate_sample: usize,
+
+    #[arg(
+        long,
+        help = "Copy the queue to <output>/client_<id>/checkpoints/<timestamp> every this many seconds",
+        value_parser = FuzzerOptions::parse_seconds
+    )]
+    pub checkpoint_interval: Option<Duration>,
+
+    #[arg(
+        long,
+        help = "Number of checkpoints to keep",
+        default_value_t = 3,
+        value_parser = clap::value_parser!(u64).range(1..),
+        requires = "checkpoint_interval"
+    )]
+    pub checkpoint_keep: u64,
+
+    #[arg(
+        long,
+        help = "Restart a client, saving its state, once its resident memory exceeds this many MB",
+        value_parser = clap::value_parser!(u64).range(1..)
+    )]
+    pub mem_limit: Option<u64>,
+
+    #[arg(
+        long,
+        help = "File polled every second: writing pause, resume or stop to it pauses the clients, resumes them or ends the campaign"
+    )]
+    pub control_file: Option<PathBuf>,
+
+    #[arg(
+        long,
+        help = "Keep the queue in <dir>/client_<id>/queue, on an already mounted tmpfs, and flush it to <output>/client_<id>/queue every --corpus-flush-interval, at client restarts and at the time limit. A reboot loses the entries found since the last flush, resume from the flushed queue"
+    )]
+    pub corpus_tmpfs: Option<PathBuf>,
+
+    #[arg(
+        long,
+        help = "Give the target of each core its own TMPDIR, <output>/tmp/core_<id>, emptied before every run"
+    )]
+    pub isolate_tmp: bool,
+
+    #[arg(
+        long,
+        help = "Flush the queue kept on the tmpfs every this many seconds",
+        default_value = "60",
+        value_parser = FuzzerOptions::parse_seconds,
+        requires = "corpus_tmpfs"
+    )]
+    pub corpus_flush_interval: Duration,
+
+    #[arg(
+        long,
+        help = "Write the covered edges to this file (suffixed with the client id) when the campaign ends"
+    )]
+    pub coverage_out: Option<PathBuf>,
+
+    #[arg(
+        long,
+        help = "File listing edges (map indices, or hex source and destination addresses) to clear from the coverage map, so reaching them alone doesn't make an input interesting"
+    )]
+    pub ignore_edges: Option<PathBuf>,
+
+    #[arg(long, help = "Maximum size in bytes of mutated inputs")]
+    pub max_mutation_size: Option<usize>,
+
+    #[arg(
+        long,
+        help = "Stack up to 2^N havoc mutations per mutated input",
+        default_value_t = 7,
+        value_parser = clap::value_parser!(u8).range(1..=12).map(usize::from)
+    )]
+    pub havoc_stack_pow: usize,
+
+    #[arg(
+        long,
+        help = "Truncate the inputs a single havoc mutation grows by more than this many bytes",
+        value_parser = clap::value_parser!(u32).range(1..).map(|grow| grow as usize)
+    )]
+    pub max_grow: Option<usize>,
+
+    #[arg(
+        long,
+        help = "Minimum size in bytes of inputs seen by the target, shorter inputs are zero-padded"
+    )]
+    pub min_mutation_size: Option<usize>,
+
+    #[arg(
+        long,
+        help = "Exact size in bytes of inputs seen by the target, shorter inputs are zero-padded and longer ones truncated",
+        conflicts_with_all = ["max_mutation_size", "min_mutation_size"]
+    )]
+    pub fixed_size: Option<usize>,
+
+    #[arg(
+        long,
+        help = "Only run the mutated inputs satisfying this predicate, e.g. \"[0] == 0x89 && len >= 8\" or \"prefix 0x89504e47\""
+    )]
+    pub input_predicate: Option<String>,
+
+    #[arg(long = "include", help="Include address ranges", value_parser = FuzzerOptions::parse_ranges)]
+    pub include: Option<Vec<Range<GuestAddr>>>,
+
+    #[arg(long = "exclude", help="Exclude address ranges", value_parser = FuzzerOptions::parse_ranges, conflicts_with="include")]
+    pub exclude: Option<Vec<Range<GuestAddr>>>,
+
+    #[arg(
+        long,
+        help = "Also cover the code of the libraries whose path matches this regex, including the ones loaded later with dlopen. Can be repeated.",
+        conflicts_with = "exclude"
+    )]
+    pub cover_lib: Vec<String>,
+
+    #[arg(
+        long,
+        help = "TOML schema describing the input fields, the mutated bytes are encoded with it before injection"
+    )]
+    pub schema: Option<PathBuf>,
+
+    #[arg(
+        long,
+        help = "The first input byte picks one of these encodings (byte modulo their count) to decode the rest with before injection",
+        value_enum,
+        value_delimiter = ',',
+        num_args = 0..,
+        default_missing_value = "raw,base64,hex"
+    )]
+    pub header_selector: Option<Vec<Encoding>>,
+
+    #[arg(
+        long,
+        help = "What read() returns once the input is exhausted",
+        value_enum,
+        default_value_t = EofBehavior::Zero
+    )]
+    pub eof_behavior: EofBehavior,
+
+    #[arg(
+        long,
+        help = "Split the input into 16 bit length-prefixed segments and serve one segment per read(), for targets reading a message sequence"
+    )]
+    pub multipart: bool,
+
+    #[arg(
+        long,
+        help = "File of read and write steps: each read gets the next multipart segment once the writes before it happened (request/response protocols)"
+    )]
+    pub script: Option<PathBuf>,
+
+    #[arg(
+        long,
+        help = "Only serve the input to the reads of pipes, the ones already open and the ones the target creates"
+    )]
+    pub auto_pipe: bool,
+
+    #[arg(
+        long,
+        help = "Run the target in-process (fast) or in a child forked for every execution (isolated)",
+        value_enum,
+        default_value_t = ExecutorKind::Inprocess
+    )]
+    pub executor: ExecutorKind,
+
+    #[arg(
+        long,
+        help = "Guest path of the fuzzed file, the target's mmap()s of it are served the input (for targets that don't read() it)"
+    )]
+    pub mmap_file: Option<String>,
+
+    #[arg(
+        long,
+        help = "Serve the input in windows of this many bytes, each magic mmap() mapping the next one at the same address, for streaming parsers"
+    )]
+    pub mmap_window: Option<usize>,
+
+    #[arg(
+        long,
+        help = "Serve the input through SysV shared memory: shmget() gets a segment shmat() maps to the input buffer, for multiprocess targets"
+    )]
+    pub shm_inject: bool,
+
+    #[arg(
+        long,
+        help = "Only serve the shmget() of this key (hex) with --shm-inject, the other segments are real",
+        value_parser = FuzzerOptions::parse_key,
+        requires = "shm_inject"
+    )]
+    pub shm_key: Option<u32>,
+
+    #[arg(
+        long,
+        help = "Also write the input to this global of the target before every run, e.g. a uint8_t fuzz_buf[] the harness reads"
+    )]
+    pub inject_symbol: Option<String>,
+
+    #[arg(
+        long,
+        help = "Write the length of the input to this integer global of the target, with --inject-symbol",
+        requires = "inject_symbol"
+    )]
+    pub inject_len_symbol: Option<String>,
+
+    #[arg(
+        long,
+        help = "Make getrandom, clock_gettime and gettimeofday return fixed values to improve coverage stability"
+    )]
+    pub determinize: bool,
+
+    #[arg(long, help = "Restore the target's writable data (.data/.bss) before every run")]
+    pub data_reset: bool,
+
+    #[arg(
+        long = "data-reset-range",
+        help = "Writable address range to restore instead of .data/.bss, can be repeated. Requires --data-reset.",
+        value_parser = FuzzerOptions::parse_ranges,
+        requires = "data_reset"
+    )]
+    pub data_reset_ranges: Vec<Range<GuestAddr>>,
+
+    #[arg(
+        long,
+        help = "Log the seed of the rng and of every mutation to this file, to replay them with --replay-mutations. Single core only.",
+        conflicts_with_all = ["replay_mutations", "replay"]
+    )]
+    pub record_mutations: Option<PathBuf>,
+
+    #[arg(
+        long,
+        help = "Redo the mutations logged by --record-mutations, from the same seed and inputs. Single core only.",
+        conflicts_with = "replay"
+    )]
+    pub replay_mutations: Option<PathBuf>,
+
+    #[arg(
+        long,
+        help = "Record the parent and the mutators of every corpus entry and solution in its metadata file",
+        conflicts_with = "replay"
+    )]
+    pub provenance: bool,
+
+    #[arg(
+        short = 'd',
+        help = "Write a DrCov Trace for the current input. Requires -r."
+    )]
+    pub drcov: Option<PathBuf>,
+
+    #[arg(
+        short = 'r',
+        help = "An input to rerun, instead of starting to fuzz. Will ignore all other settings apart from -d.",
+        group = "replay"
+    )]
+    pub rerun_input: Option<PathBuf>,
+
+    #[arg(
+        long,
+        help = "Like -r, with the input read from stdin (e.g. cat input | ... --stdin-input)",
+        group = "replay"
+    )]
+    pub stdin_input: bool,
+
+    #[arg(
+        long = "qemu-arg",
+        help = "Extra qemu parameter placed before the target binary, can be repeated (e.g. --qemu-arg=-d --qemu-arg=in_asm)",
+        allow_hyphen_values = true
+    )]
+    pub qemu_args: Vec<String>,
+
+    #[arg(
+        long,
+        help = "File with the guest program's arguments (after the binary), one per line. Quoting works as in a shell, lines starting with # are comments."
+    )]
+    pub argv_file: Option<PathBuf>,
+
+    #[arg(long, help = "Guest CPU model, expands to qemu's -cpu <model>")]
+    pub cpu: Option<String>,
+
+    #[arg(
+        long,
+        help = "Guest stack size in bytes (k, M and G suffixes allowed), expands to qemu's -s <size>, for deeply recursive targets",
+        value_parser = FuzzerOptions::parse_size
+    )]
+    pub guest_stack_size: Option<u64>,
+
+    #[arg(
+        long,
+        help = "Disable address space randomization, so the target and its libraries load at the same addresses in every run"
+    )]
+    pub no_aslr: bool,
 
-    #[arg(short = 'x', long, help = "Tokens file")]
-    pub tokens: Option<String>,
+    #[arg(
+        long,
+        help = "Run to the harness entry, where the libraries are loaded, print the guest memory map and exit, instead of starting to fuzz.",
+        conflicts_with_all = ["replay", "stability_check", "triage_dir"]
+    )]
+    pub print_mappings: bool,
 
-    #[cfg(feature = "injections")]
     #[arg(
-        short = 'j',
         long,
-        help = "Injections TOML or YAML file definition. Filename must end in .toml or .yaml/.yml."
+        help = "Output format of --print-mappings",
+        value_enum,
+        default_value_t = MappingsFormat::Table,
+        requires = "print_mappings"
     )]
-    pub injections: Option<String>,
+    pub format: MappingsFormat,
 
-    #[arg(long, help = "Stdout Log file (For LLMPManager)")]
-    pub log: Option<String>,
+    #[arg(
+        long,
+        help = "Run this input several times and report the stability of its coverage, instead of starting to fuzz.",
+        conflicts_with = "replay"
+    )]
+    pub stability_check: Option<PathBuf>,
 
-    #[arg(long, help = "Client Stdout log file", requires = "client_stderr_file")]
-    pub client_stdout_file: Option<String>,
+    #[arg(
+        long,
+        help = "Number of runs for --stability-check",
+        default_value_t = 8,
+        value_parser = clap::value_parser!(u32).range(2..)
+    )]
+    pub stability_runs: u32,
 
-    #[arg(long, help = "Client Stderr log file", requires = "client_stdout_file")]
-    pub client_stderr_file: Option<String>,
+    #[arg(
+        long,
+        help = "Mutate each region of this input in turn and report how much of its coverage every region drives, instead of starting to fuzz.",
+        conflicts_with_all = ["replay", "stability_check", "print_mappings"]
+    )]
+    pub byte_attribution: Option<PathBuf>,
 
-    #[arg(long, help = "Timeout in milliseconds", default_value = "1000", value_parser = FuzzerOptions::parse_timeout)]
-    pub timeout: Duration,
+    #[arg(
+        long,
+        help = "Maximum number of regions --byte-attribution cuts the input in, larger inputs are mutated by chunks",
+        default_value_t = 1024,
+        value_parser = clap::value_parser!(u32).range(1..)
+    )]
+    pub attribution_regions: u32,
 
-    #[arg(long = "port", help = "Broker port", default_value_t = 1337_u16)]
-    pub port: u16,
+    #[arg(long, help = "Also write the --byte-attribution report as JSON to this file", requires = "byte_attribution")]
+    pub attribution_json: Option<PathBuf>,
 
-    #[arg(long, help = "Cpu cores to use", default_value = "all", value_parser = Cores::from_cmdline)]
-    pub cores: Cores,
+    #[arg(
+        long,
+        help = "Run an empty input this many times (10000 if not given) and report the exec/sec and where the time goes, instead of starting to fuzz.",
+        num_args = 0..=1,
+        default_missing_value = "10000",
+        value_parser = clap::value_parser!(u32).range(1..),
+        conflicts_with_all = ["replay", "stability_check", "byte_attribution", "triage_dir", "print_mappings"]
+    )]
+    pub bench: Option<u32>,
 
-    #[arg(long, help = "Cpu cores to use for ASan", value_parser = Cores::from_cmdline)]
-    pub asan_cores: Option<Cores>,
+    #[arg(
+        long,
+        help = "Replay every input in a crashes directory and print a grouped summary, instead of starting to fuzz.",
+        conflicts_with = "replay"
+    )]
+    pub triage_dir: Option<PathBuf>,
 
-    #[arg(long, help = "Cpu cores to use for ASan", value_parser = Cores::from_cmdline)]
-    pub asan_guest_cores: Option<Cores>,
+    #[arg(long, help = "Write the triage summary as JSON to this file. Requires --triage-dir.", requires = "triage_dir")]
+    pub triage_json: Option<PathBuf>,
 
-    #[arg(long, help = "Cpu cores to use for CmpLog", value_parser = Cores::from_cmdline)]
-    pub cmplog_cores: Option<Cores>,
+    #[arg(
+        long,
+        help = "Replay every saved crash of this directory and exit with 1 if any of them no longer crashes, instead of starting to fuzz.",
+        conflicts_with_all = ["replay", "triage_dir", "diff_binary"]
+    )]
+    pub regress: Option<PathBuf>,
 
-    #[clap(short, long, help = "Enable output from the fuzzer clients", conflicts_with_all = ["client_stdout_file", "client_stderr_file"])]
-    pub verbose: bool,
+    #[arg(
+        long,
+        help = "Write a script replaying this crash under qemu's gdbstub, and gdb and VSCode configs breaking at its faulting PC, instead of starting to fuzz.",
+        conflicts_with_all = ["replay", "triage_dir", "diff_binary", "regress"]
+    )]
+    pub emit_debug_config: Option<PathBuf>,
 
-    #[clap(long, help = "Enable AFL++ style output", conflicts_with = "verbose")]
-    pub tui: bool,
+    #[arg(
+        long,
+        help = "Replay the input corpus on the target and on this other build of it, and report the edges only one of them hit, instead of starting to fuzz.",
+        conflicts_with_all = ["replay", "triage_dir"]
+    )]
+    pub diff_binary: Option<PathBuf>,
 
-    #[arg(long = "iterations", help = "Maximum number of iterations")]
-    pub iterations: Option<u64>,
+    #[arg(
+        long,
+        help = "Merge these corpora (comma separated) into the smallest set of their inputs covering the same edges, written to --out, instead of starting to fuzz.",
+        value_delimiter = ',',
+        requires = "merge_out",
+        conflicts_with_all = ["replay", "triage_dir", "diff_binary", "regress"]
+    )]
+    pub merge_corpora: Vec<PathBuf>,
 
-    #[arg(long = "include", help="Include address ranges", value_parser = FuzzerOptions::parse_ranges)]
-    pub include: Option<Vec<Range<GuestAddr>>>,
+    #[arg(long = "out", help = "Output directory of --merge-corpora", requires = "merge_corpora")]
+    pub merge_out: Option<PathBuf>,
 
-    #[arg(long = "exclude", help="Exclude address ranges", value_parser = FuzzerOptions::parse_ranges, conflicts_with="include")]
-    pub exclude: Option<Vec<Range<GuestAddr>>>,
+    #[arg(
+        long,
+        help = "Fuzz the bundled self-test target (build/selftest if not given) and check its bug is found, instead of starting to fuzz.",
+        num_args = 0..=1,
+        default_missing_value = "build/selftest",
+        conflicts_with_all = ["replay", "triage_dir", "diff_binary", "regress", "merge_corpora"]
+    )]
+    pub self_test: Option<PathBuf>,
 
     #[arg(
-        short = 'd',
-        help = "Write a DrCov Trace for the current input. Requires -r."
+        long,
+        help = "Fuzzing iterations the self-test may take to find the bug",
+        default_value_t = 5000,
+        value_parser = clap::value_parser!(u64).range(1..)
     )]
-    pub drcov: Option<PathBuf>,
+    pub self_test_iterations: u64,
+
+    // Used internally by the diff mode to collect the edges hit by a rerun input
+    #[arg(long, hide = true, requires = "rerun_input")]
+    pub edges_out: Option<PathBuf>,
 
     #[arg(
-        short = 'r',
-        help = "An input to rerun, instead of starting to fuzz. Will ignore all other settings apart from -d."
+        long,
+        help = "Write every input the client executes to this directory, not only the kept ones, until --log-all-count of them"
     )]
-    pub rerun_input: Option<PathBuf>,
+    pub log_all_inputs: Option<PathBuf>,
+
+    #[arg(
+        long,
+        help = "Number of executed inputs --log-all-inputs writes per client",
+        default_value_t = 1000,
+        value_parser = clap::value_parser!(u64).range(1..),
+        requires = "log_all_inputs"
+    )]
+    pub log_all_count: u64,
+
+    #[arg(
+        long,
+        help = "Write the code run by the replayed input (-r) as an LCOV tracefile, mapped to source lines with the target's DWARF info (block addresses without it)",
+        requires = "replay"
+    )]
+    pub lcov_out: Option<PathBuf>,
+
+    #[arg(
+        long,
+        help = "Record the last this many executed blocks and attach them to the crash info of solutions (0 disables it, every block is hooked otherwise)",
+        default_value_t = 0
+    )]
+    pub crash_trace_depth: usize,
+
+    // Used internally by the triage mode to collect the classification of a rerun input
+    #[arg(long, hide = true, requires = "rerun_input")]
+    pub crash_info: Option<PathBuf>,
 
     #[arg(last = true, help = "Arguments passed to the target")]
     pub args: Vec<String>,
 }
 
+/// A regex option is recorded as its pattern
+fn serialize_regex<S: Serializer>(regex: &Option<Regex>, serializer: S) -> Result<S::Ok, S::Error> {
+    regex.as_ref().map(Regex::as_str).serialize(serializer)
+}
+
+/// The options of a campaign with the build running it, see [`FuzzerOptions::write_campaign_options`]
+#[derive(Serialize)]
+struct CampaignOptions<'a> {
+    version: &'static str,
+    commit: &'static str,
+    arch: &'static str,
+    options: &'a FuzzerOptions,
+}
+
+/// The numbers of the guest signals `--crash-signals` accepts by name, mips numbers a few of them differently
+const GUEST_SIGNALS: &[(&str, i32)] = &[
+    ("HUP", 1),
+    ("INT", 2),
+    ("QUIT", 3),
+    ("ILL", 4),
+    ("TRAP", 5),
+    ("ABRT", 6),
+    #[cfg(not(feature = "mips"))]
+    ("BUS", 7),
+    #[cfg(feature = "mips")]
+    ("BUS", 10),
+    ("FPE", 8),
+    ("KILL", 9),
+    #[cfg(not(feature = "mips"))]
+    ("USR1", 10),
+    #[cfg(feature = "mips")]
+    ("USR1", 16),
+    ("SEGV", 11),
+    #[cfg(not(feature = "mips"))]
+    ("USR2", 12),
+    #[cfg(feature = "mips")]
+    ("USR2", 17),
+    ("PIPE", 13),
+    ("ALRM", 14),
+    ("TERM", 15),
+    #[cfg(not(feature = "mips"))]
+    ("CHLD", 17),
+    #[cfg(feature = "mips")]
+    ("CHLD", 18),
+    #[cfg(not(feature = "mips"))]
+    ("SYS", 31),
+    #[cfg(feature = "mips")]
+    ("SYS", 12),
+];
+
 impl FuzzerOptions {
     fn parse_timeout(src: &str) -> Result<Duration, Error> {
         Ok(Duration::from_millis(src.parse()?))
     }
 
+    fn parse_seconds(src: &str) -> Result<Duration, Error> {
+        Ok(Duration::from_secs(src.parse()?))
+    }
+
+    fn parse_addr(src: &str) -> Result<GuestAddr, Error> {
+        GuestAddr::from_str_radix(src.trim_start_matches("0x"), 16)
+            .map_err(|e| Error::illegal_argument(format!("Invalid address: {src} ({e:})")))
+    }
+
+    /// A size in bytes, with an optional `k`, `M` or `G` suffix (powers of 1024)
+    fn parse_size(src: &str) -> Result<u64, Error> {
+        let (digits, shift) = match src.char_indices().last() {
+            Some((idx, 'k' | 'K')) => (&src[..idx], 10),
+            Some((idx, 'm' | 'M')) => (&src[..idx], 20),
+            Some((idx, 'g' | 'G')) => (&src[..idx], 30),
+            _ => (src, 0),
+        };
+        digits
+            .parse::<u64>()
+            .ok()
+            .and_then(|size| size.checked_mul(1 << shift))
+            .filter(|size| *size != 0)
+            .ok_or_else(|| Error::illegal_argument(format!("Invalid size: {src}")))
+    }
+
+    /// A SysV IPC key, in hex like `ipcs` shows them
+    fn parse_key(src: &str) -> Result<u32, Error> {
+        u32::from_str_radix(src.trim_start_matches("0x"), 16)
+            .map_err(|e| Error::illegal_argument(format!("Invalid key: {src} ({e:})")))
+    }
+
+    /// A guest signal, by number or name (`SIGSEGV` or `segv`), numbered like the target architecture does
+    fn parse_signal(src: &str) -> Result<i32, Error> {
+        if let Ok(signal) = src.parse() {
+            return Ok(signal);
+        }
+        let name = src.to_uppercase();
+        let name = name.strip_prefix("SIG").unwrap_or(&name);
+        GUEST_SIGNALS
+            .iter()
+            .find(|(signal, _)| *signal == name)
+            .map(|(_, number)| *number)
+            .ok_or_else(|| Error::illegal_argument(format!("Unknown signal: {src}")))
+    }
+
     fn parse_ranges(src: &str) -> Result<Range<GuestAddr>, Error> {
         let parts = src.split('-').collect::<Vec<&str>>();
         if parts.len() == 2 {
@@ -135,29 +1080,223 @@ impl FuzzerOptions {
             .is_some_and(|c| c.contains(core_id))
     }
 
+    /// Whether a single input is replayed (`-r` or `--stdin-input`) instead of fuzzing
+    pub fn replays_input(&self) -> bool {
+        self.rerun_input.is_some() || self.stdin_input
+    }
+
+    /// Whether the client runs once and exits (replaying an input, printing the mappings, checking the stability,
+    /// attributing the bytes of an input, benchmarking) instead of fuzzing.
+    /// Such a run is a single client in the launcher's process, not a campaign.
+    pub fn runs_once(&self) -> bool {
+        self.replays_input()
+            || self.print_mappings
+            || self.stability_check.is_some()
+            || self.byte_attribution.is_some()
+            || self.bench.is_some()
+    }
+
+    /// How the runs are isolated. A profiled client forks them whatever `--executor` says, nothing would
+    /// respawn it if a crash, a timeout or an exit of the target ended its process.
+    pub fn executor_kind(&self) -> ExecutorKind {
+        if self.profile {
+            ExecutorKind::Fork
+        } else {
+            self.executor
+        }
+    }
+
+    /// The target binary to parse, see `--target`
+    pub fn target_path(&self, qemu: Qemu) -> PathBuf {
+        self.target
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(qemu.binary_path()))
+    }
+
+    /// The target binary as it appears in the target arguments: `--target`, or the first existing file.
+    /// Unlike [`FuzzerOptions::target_path`], it doesn't need qemu to be running.
+    pub fn target_binary(&self) -> Result<PathBuf, Error> {
+        if let Some(target) = &self.target {
+            return Ok(target.clone());
+        }
+        self.args
+            .iter()
+            .map(PathBuf::from)
+            .find(|path| path.is_file())
+            .ok_or_else(|| {
+                Error::illegal_argument("Failed to find the target binary in the target arguments, use --target")
+            })
+    }
+
     pub fn input_dir(&self) -> PathBuf {
         PathBuf::from(&self.input)
     }
 
+    /// Maximum size of mutated inputs, see `--max-mutation-size` and `--fixed-size`
+    pub fn max_input_size(&self) -> Option<usize> {
+        self.fixed_size.or(self.max_mutation_size)
+    }
+
+    /// Size inputs are padded to, see `--min-mutation-size` and `--fixed-size`
+    pub fn min_input_size(&self) -> Option<usize> {
+        self.fixed_size.or(self.min_mutation_size)
+    }
+
+    /// The AFL queue to import, see `--import-afl`
+    pub fn afl_queue_dir(&self) -> Option<PathBuf> {
+        self.import_afl.as_ref().map(|dir| {
+            let queue = dir.join("queue");
+            if queue.is_dir() {
+                queue
+            } else {
+                dir.clone()
+            }
+        })
+    }
+
+    /// Record the options, the crate version and the architecture of this build as `<output>/options.json`,
+    /// so that the campaign documents how it was run. Every option is written as given, target arguments included.
+    pub fn write_campaign_options(&self) -> Result<PathBuf, Error> {
+        let campaign = CampaignOptions {
+            version: env!("CARGO_PKG_VERSION"),
+            commit: env!("VERGEN_GIT_SHA"),
+            arch: env!("CPU_TARGET"),
+            options: self,
+        };
+        let json = serde_json::to_string_pretty(&campaign)
+            .map_err(|e| Error::serialize(format!("Failed to serialize the options: {e}")))?;
+        let path = PathBuf::from(&self.output).join("options.json");
+        fs::create_dir_all(&self.output)?;
+        fs::write(&path, json)?;
+        Ok(path)
+    }
+
     pub fn output_dir(&self, client_description: ClientDescription) -> PathBuf {
         let mut dir = PathBuf::from(&self.output);
         dir.push(format!("client_{:03}", client_description.id()));
         dir
     }
 
+    /// Name of the map feedback driving the coverage, see `--coverage-mode`
+    pub fn map_feedback_name(&self) -> &'static str {
+        match self.coverage_mode {
+            CoverageMode::Edges => "edges_presence",
+            CoverageMode::Hitcounts => "edges",
+        }
+    }
+
+    pub fn coverage_out(&self, client_description: ClientDescription) -> Option<PathBuf> {
+        self.coverage_out.as_ref().map(|path| {
+            let mut path = path.clone().into_os_string();
+            path.push(format!(".client_{:03}", client_description.id()));
+            PathBuf::from(path)
+        })
+    }
+
+    /// The directory of the corpus, on the `--corpus-tmpfs` if any
     pub fn queue_dir(&self, client_description: ClientDescription) -> PathBuf {
+        match &self.corpus_tmpfs {
+            Some(tmpfs) => tmpfs
+                .join(format!("client_{:03}", client_description.id()))
+                .join("queue"),
+            None => self.persistent_queue_dir(client_description),
+        }
+    }
+
+    /// The corpus directory in the output, where a `--corpus-tmpfs` queue is flushed to
+    pub fn persistent_queue_dir(&self, client_description: ClientDescription) -> PathBuf {
         let mut dir = self.output_dir(client_description).clone();
         dir.push("queue");
         dir
     }
 
+    /// The `TMPDIR` of the target on `core_id`, see `--isolate-tmp`
+    pub fn tmp_dir(&self, core_id: CoreId) -> PathBuf {
+        let mut dir = PathBuf::from(&self.output);
+        dir.push("tmp");
+        dir.push(format!("core_{:03}", core_id.0));
+        dir
+    }
+
+    /// Hashes of the seeds the client already tested, see `--no-dedup-seeds`
+    pub fn seen_seeds_file(&self, client_description: ClientDescription) -> PathBuf {
+        let mut path = self.output_dir(client_description);
+        path.push("seen_seeds");
+        path
+    }
+
+    pub fn checkpoints_dir(&self, client_description: ClientDescription) -> PathBuf {
+        let mut dir = self.output_dir(client_description).clone();
+        dir.push("checkpoints");
+        dir
+    }
+
     pub fn crashes_dir(&self, client_description: ClientDescription) -> PathBuf {
         let mut dir = self.output_dir(client_description).clone();
         dir.push("crashes");
         dir
     }
 
+    pub fn findings_dir(&self, client_description: ClientDescription) -> PathBuf {
+        match &self.findings_dir {
+            Some(dir) => dir.clone(),
+            None => {
+                let mut dir = self.output_dir(client_description);
+                dir.push("findings");
+                dir
+            }
+        }
+    }
+
     pub fn validate(&self) {
+        if let Some(target) = &self.target {
+            if let Err(e) = File::open(target) {
+                let mut cmd = FuzzerOptions::command();
+                cmd.error(
+                    ErrorKind::ValueValidation,
+                    format!("Target binary ({}) is not readable: {e}", target.display()),
+                )
+                .exit();
+            }
+        }
+
+        if let Some(argv_file) = &self.argv_file {
+            if let Err(e) = File::open(argv_file) {
+                let mut cmd = FuzzerOptions::command();
+                cmd.error(
+                    ErrorKind::ValueValidation,
+                    format!("Argv file ({}) is not readable: {e}", argv_file.display()),
+                )
+                .exit();
+            }
+        }
+
+        if let Some(afl_queue) = self.afl_queue_dir() {
+            if !afl_queue.is_dir() {
+                let mut cmd = FuzzerOptions::command();
+                cmd.error(
+                    ErrorKind::ValueValidation,
+                    format!("AFL queue ({}) is not a directory", afl_queue.display()),
+                )
+                .exit();
+            }
+        }
+
+        if let Some(init_regs) = &self.init_regs {
+            if let Err(e) = Harness::parse_init_regs(init_regs) {
+                let mut cmd = FuzzerOptions::command();
+                cmd.error(ErrorKind::ValueValidation, format!("Invalid init registers: {e}"))
+                    .exit();
+            }
+        }
+
+        for reg_input in &self.reg_input {
+            if let Err(e) = Harness::parse_reg_input(reg_input) {
+                let mut cmd = FuzzerOptions::command();
+                cmd.error(ErrorKind::ValueValidation, e.to_string()).exit();
+            }
+        }
+
         if let Some(asan_cores) = &self.asan_cores {
             for id in &asan_cores.ids {
                 if !self.cores.contains(*id) {
@@ -190,7 +1329,191 @@ impl FuzzerOptions {
             }
         }
 
-        if self.drcov.is_some() && self.rerun_input.is_none() {
+        if let Some(window) = self.mmap_window {
+            if window == 0 || window > MAX_INPUT_SIZE {
+                let mut cmd = FuzzerOptions::command();
+                cmd.error(
+                    ErrorKind::ValueValidation,
+                    format!("The mmap window ({window}) must be between 1 and the injector max size ({MAX_INPUT_SIZE})"),
+                )
+                .exit();
+            }
+        }
+
+        if let Some(max_size) = self.max_mutation_size {
+            if max_size > MAX_INPUT_SIZE {
+                let mut cmd = FuzzerOptions::command();
+                cmd.error(
+                    ErrorKind::ValueValidation,
+                    format!(
+                        "Max mutation size ({max_size}) must not exceed the injector max size ({MAX_INPUT_SIZE})"
+                    ),
+                )
+                .exit();
+            }
+        }
+
+        if let Some(min_size) = self.min_mutation_size {
+            let max_size = self.max_mutation_size.unwrap_or(MAX_INPUT_SIZE);
+            if min_size > max_size {
+                let mut cmd = FuzzerOptions::command();
+                cmd.error(
+                    ErrorKind::ValueValidation,
+                    format!(
+                        "Min mutation size ({min_size}) must not exceed max mutation size ({max_size})"
+                    ),
+                )
+                .exit();
+            }
+        }
+
+        if self.priority_weight.is_nan() || self.priority_weight <= 0.0 {
+            let mut cmd = FuzzerOptions::command();
+            cmd.error(
+                ErrorKind::ValueValidation,
+                format!("Priority weight ({}) must be positive", self.priority_weight),
+            )
+            .exit();
+        }
+
+        if let Some(size) = self.fixed_size {
+            if size == 0 || size > MAX_INPUT_SIZE {
+                let mut cmd = FuzzerOptions::command();
+                cmd.error(
+                    ErrorKind::ValueValidation,
+                    format!("Fixed size ({size}) must be between 1 and the injector max size ({MAX_INPUT_SIZE})"),
+                )
+                .exit();
+            }
+        }
+
+        let supported = match self.calling_convention {
+            None | Some(CallConv::Cdecl) => true,
+            Some(CallConv::Fastcall) => cfg!(feature = "i386"),
+            Some(CallConv::Win64) => cfg!(feature = "x86_64"),
+        };
+        if !supported {
+            let mut cmd = FuzzerOptions::command();
+            cmd.error(
+                ErrorKind::ValueValidation,
+                format!(
+                    "The {:?} calling convention is not supported by the architecture of this build",
+                    self.calling_convention.unwrap()
+                ),
+            )
+            .exit();
+        }
+
+        if let Some(predicate) = &self.input_predicate {
+            if let Err(e) = InputPredicate::parse(predicate) {
+                let mut cmd = FuzzerOptions::command();
+                cmd.error(ErrorKind::ValueValidation, e.to_string()).exit();
+            }
+        }
+
+        if let Some(tmpfs) = &self.corpus_tmpfs {
+            // Mounting one needs privileges, the user provides it
+            let on_tmpfs = statfs(tmpfs).is_ok_and(|stat| {
+                [TMPFS_MAGIC, RAMFS_MAGIC].contains(&stat.filesystem_type())
+            });
+            if !on_tmpfs {
+                let mut cmd = FuzzerOptions::command();
+                cmd.error(
+                    ErrorKind::ValueValidation,
+                    format!(
+                        "{} is not a tmpfs directory, create one on a tmpfs (e.g. /dev/shm) or mount one with `mount -t tmpfs`",
+                        tmpfs.display()
+                    ),
+                )
+                .exit();
+            }
+        }
+
+        if self.log_all_inputs.is_some() && self.executor_kind() == ExecutorKind::Fork {
+            let mut cmd = FuzzerOptions::command();
+            cmd.error(
+                ErrorKind::ValueValidation,
+                "The inputs are counted in the fuzzer process, `--log-all-inputs` doesn't support `--executor fork` nor `--profile`",
+            )
+            .exit();
+        }
+
+        if self.coverage_diagnostics && self.executor_kind() == ExecutorKind::Fork {
+            let mut cmd = FuzzerOptions::command();
+            cmd.error(
+                ErrorKind::ValueValidation,
+                "The collision samples are recorded in the fuzzer process, `--coverage-diagnostics` doesn't support `--executor fork` nor `--profile`",
+            )
+            .exit();
+        }
+
+        if (self.record_mutations.is_some() || self.replay_mutations.is_some()) && self.cores.ids.len() != 1 {
+            let mut cmd = FuzzerOptions::command();
+            cmd.error(
+                ErrorKind::ValueValidation,
+                "The mutations of a single client can be recorded and replayed, run `--record-mutations` and `--replay-mutations` on one core",
+            )
+            .exit();
+        }
+
+        if self.standby_port == Some(self.port) {
+            let mut cmd = FuzzerOptions::command();
+            cmd.error(
+                ErrorKind::ValueValidation,
+                "The standby broker needs a port of its own, `--standby-port` must differ from `--port`",
+            )
+            .exit();
+        }
+
+        if cfg!(feature = "simplemgr") && (self.standby_port.is_some() || self.standby_broker) {
+            let mut cmd = FuzzerOptions::command();
+            cmd.error(
+                ErrorKind::ValueValidation,
+                "The simplemgr build runs a single client without a broker, `--standby-port` and `--standby-broker` need the launcher",
+            )
+            .exit();
+        }
+
+        for pattern in &self.cover_lib {
+            if let Err(e) = regex::Regex::new(pattern) {
+                let mut cmd = FuzzerOptions::command();
+                cmd.error(
+                    ErrorKind::ValueValidation,
+                    format!("Invalid --cover-lib pattern {pattern:?}: {e}"),
+                )
+                .exit();
+            }
+        }
+
+        if self.split_coverage && self.executor_kind() == ExecutorKind::Fork {
+            let mut cmd = FuzzerOptions::command();
+            cmd.error(
+                ErrorKind::ValueValidation,
+                "The split coverage is recorded in the fuzzer process, it doesn't support `--executor fork` nor `--profile`",
+            )
+            .exit();
+        }
+
+        if !self.timeout_per_kb.is_zero() {
+            if self.timeout_max < self.timeout {
+                let mut cmd = FuzzerOptions::command();
+                cmd.error(
+                    ErrorKind::ValueValidation,
+                    "The `--timeout-max` must be at least the `--timeout`",
+                )
+                .exit();
+            }
+            if self.executor_kind() == ExecutorKind::Fork {
+                let mut cmd = FuzzerOptions::command();
+                cmd.error(
+                    ErrorKind::ValueValidation,
+                    "The `--timeout-per-kb` scales the timer of the in-process executor, it doesn't support `--executor fork` nor `--profile`",
+                )
+                .exit();
+            }
+        }
+
+        if self.drcov.is_some() && !self.replays_input() {
             let mut cmd = FuzzerOptions::command();
             cmd.error(
                 ErrorKind::ValueValidation,
@@ -0,0 +1,67 @@
+use std::{
+    path::{Path, PathBuf},
+    process,
+};
+
+use libafl::{executors::ExitKind, Error};
+
+use crate::{options::FuzzerOptions, triage::Triage};
+
+/// Replays every saved crash and checks it still crashes, e.g. to verify a patch of the target.
+///
+/// The inputs are replayed like the [`Triage`] does, each by a child instance of this fuzzer running with `-r`.
+/// An input that now runs to completion counts as fixed, and any fixed input makes the process exit with 1.
+pub struct Regression<'a> {
+    options: &'a FuzzerOptions,
+}
+
+impl<'a> Regression<'a> {
+    pub fn new(options: &'a FuzzerOptions) -> Self {
+        Self { options }
+    }
+
+    pub fn run(&self) -> Result<(), Error> {
+        let dir = self
+            .options
+            .regress
+            .as_ref()
+            .ok_or_else(|| Error::empty_optional("No crashes directory provided"))?;
+
+        let triage = Triage::new(self.options);
+        let mut fixed = Vec::new();
+        let inputs = Self::crashes(dir)?;
+        for input in &inputs {
+            let info = triage.replay(input)?;
+            log::info!("{input:?}: {info:?}");
+            if info.exit_kind == ExitKind::Ok {
+                fixed.push(input.clone());
+            }
+        }
+
+        println!(
+            "Replayed {} crashes: {} still crash, {} fixed",
+            inputs.len(),
+            inputs.len() - fixed.len(),
+            fixed.len()
+        );
+        for input in &fixed {
+            println!("  fixed: {input:?}");
+        }
+
+        if !fixed.is_empty() {
+            process::exit(1);
+        }
+        Ok(())
+    }
+
+    /// The inputs of a crashes directory, without the `--reproducers` and `--emit-debug-config` files saved next to them
+    fn crashes(dir: &Path) -> Result<Vec<PathBuf>, Error> {
+        Ok(Triage::inputs(dir)?
+            .into_iter()
+            .filter(|path| {
+                path.extension()
+                    .is_none_or(|ext| !["sh", "gdb", "json"].iter().any(|skipped| ext == *skipped))
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,108 @@
+use libafl::{
+    corpus::{Corpus, CorpusId, Testcase},
+    schedulers::{HasQueueCycles, RemovableScheduler, Scheduler},
+    state::HasCorpus,
+    Error,
+};
+use libafl_bolts::tuples::MatchName;
+
+/// Schedules the corpus entries in insertion order, looping over the corpus like a plain `QueueScheduler`,
+/// instead of letting `inner` pick them, see `--fifo-schedule`.
+///
+/// The events are still forwarded to `inner`, so the metadata it attaches (e.g. the power schedule's,
+/// which the power mutational stage needs) stays up to date. Without `fifo` it only forwards to `inner`.
+#[derive(Debug)]
+pub struct FifoScheduler<CS> {
+    inner: CS,
+    fifo: bool,
+    queue_cycles: u64,
+}
+
+impl<CS> FifoScheduler<CS> {
+    pub fn new(inner: CS, fifo: bool) -> Self {
+        Self {
+            inner,
+            fifo,
+            queue_cycles: 0,
+        }
+    }
+}
+
+impl<CS, I, S> Scheduler<I, S> for FifoScheduler<CS>
+where
+    CS: Scheduler<I, S>,
+    S: HasCorpus<I>,
+{
+    fn on_add(&mut self, state: &mut S, id: CorpusId) -> Result<(), Error> {
+        self.inner.on_add(state, id)
+    }
+
+    fn on_evaluation<OT>(&mut self, state: &mut S, input: &I, observers: &OT) -> Result<(), Error>
+    where
+        OT: MatchName,
+    {
+        self.inner.on_evaluation(state, input, observers)
+    }
+
+    fn next(&mut self, state: &mut S) -> Result<CorpusId, Error> {
+        if !self.fifo {
+            return self.inner.next(state);
+        }
+        if state.corpus().count() == 0 {
+            return Err(Error::empty("No entries in corpus"));
+        }
+
+        let next = state
+            .corpus()
+            .current()
+            .and_then(|id| state.corpus().next(id));
+        let id = match next {
+            Some(id) => id,
+            None => {
+                self.queue_cycles += 1;
+                state.corpus().first().unwrap()
+            }
+        };
+        self.set_current_scheduled(state, Some(id))?;
+        Ok(id)
+    }
+
+    fn set_current_scheduled(
+        &mut self,
+        state: &mut S,
+        next_id: Option<CorpusId>,
+    ) -> Result<(), Error> {
+        self.inner.set_current_scheduled(state, next_id)
+    }
+}
+
+impl<CS, I, S> RemovableScheduler<I, S> for FifoScheduler<CS>
+where
+    CS: RemovableScheduler<I, S>,
+{
+    fn on_remove(
+        &mut self,
+        state: &mut S,
+        id: CorpusId,
+        testcase: &Option<Testcase<I>>,
+    ) -> Result<(), Error> {
+        self.inner.on_remove(state, id, testcase)
+    }
+
+    fn on_replace(&mut self, state: &mut S, id: CorpusId, prev: &Testcase<I>) -> Result<(), Error> {
+        self.inner.on_replace(state, id, prev)
+    }
+}
+
+impl<CS> HasQueueCycles for FifoScheduler<CS>
+where
+    CS: HasQueueCycles,
+{
+    fn queue_cycles(&self) -> u64 {
+        if self.fifo {
+            self.queue_cycles
+        } else {
+            self.inner.queue_cycles()
+        }
+    }
+}
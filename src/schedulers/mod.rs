@@ -0,0 +1,5 @@
+pub mod fifo;
+pub mod priority;
+
+pub use fifo::FifoScheduler;
+pub use priority::{PriorityMetadata, PriorityTestcaseScore};
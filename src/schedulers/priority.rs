@@ -0,0 +1,38 @@
+use libafl::{
+    corpus::Testcase,
+    inputs::BytesInput,
+    schedulers::testcase_score::{CorpusPowerTestcaseScore, TestcaseScore},
+    Error, HasMetadata,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::instance::ClientState;
+
+/// A multiplier of the power of a corpus entry, attached by a feedback (e.g. [`crate::feedbacks::target_reach::TargetReachFeedback`])
+/// to get the entries with some property mutated more often
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct PriorityMetadata {
+    pub weight: f64,
+}
+
+impl PriorityMetadata {
+    pub fn new(weight: f64) -> Self {
+        Self { weight }
+    }
+}
+
+libafl_bolts::impl_serdeany!(PriorityMetadata);
+
+/// The power schedule score of [`CorpusPowerTestcaseScore`], multiplied by the entry's [`PriorityMetadata`] if any
+#[derive(Debug, Clone)]
+pub struct PriorityTestcaseScore;
+
+impl TestcaseScore<ClientState> for PriorityTestcaseScore {
+    fn compute(state: &ClientState, entry: &mut Testcase<BytesInput>) -> Result<f64, Error> {
+        let score = CorpusPowerTestcaseScore::compute(state, entry)?;
+        let weight = entry
+            .metadata::<PriorityMetadata>()
+            .map_or(1.0, |priority| priority.weight);
+        Ok(score * weight)
+    }
+}
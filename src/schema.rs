@@ -0,0 +1,182 @@
+use std::{fs, path::Path};
+
+use libafl::Error;
+use serde::Deserialize;
+
+/// Byte order of the encoded integers
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Endian {
+    #[default]
+    Little,
+    Big,
+}
+
+/// A field of the encoded input. Fields take their content from the flat input in order,
+/// missing input bytes are zeroes.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Field {
+    /// Fixed bytes (e.g. a magic), given in hex. Consumes no input.
+    Const { value: String },
+    /// An integer of `size` (1, 2, 4 or 8) bytes
+    Uint {
+        size: usize,
+        #[serde(default)]
+        endian: Endian,
+    },
+    /// `size` raw bytes
+    Bytes { size: usize },
+    /// A `tag`, a `len_size` bytes length and the data. The length comes from the next input byte,
+    /// modulo `max_len + 1`, the data from the following bytes.
+    Tlv {
+        tag: u8,
+        len_size: usize,
+        max_len: usize,
+        #[serde(default)]
+        endian: Endian,
+    },
+    /// All the remaining input
+    Rest,
+}
+
+/// A lightweight structure for the target's input, so that the mutators keep working on flat bytes
+/// while the target always sees well-formed fields. Loaded from a TOML file:
+///
+/// ```toml
+/// [[field]]
+/// type = "const"
+/// value = "89504e47"
+///
+/// [[field]]
+/// type = "uint"
+/// size = 4
+/// endian = "big"
+///
+/// [[field]]
+/// type = "tlv"
+/// tag = 1
+/// len_size = 2
+/// max_len = 64
+///
+/// [[field]]
+/// type = "rest"
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+pub struct Schema {
+    #[serde(rename = "field")]
+    fields: Vec<Field>,
+}
+
+impl Schema {
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let content = fs::read_to_string(&path)?;
+        let schema: Schema = toml::from_str(&content).map_err(|e| {
+            Error::illegal_argument(format!("Invalid schema {:?}: {e}", path.as_ref()))
+        })?;
+        schema.validate()?;
+        Ok(schema)
+    }
+
+    fn validate(&self) -> Result<(), Error> {
+        for field in &self.fields {
+            match field {
+                Field::Const { value } => {
+                    decode_hex(value)?;
+                }
+                Field::Uint { size, .. } | Field::Tlv { len_size: size, .. }
+                    if ![1, 2, 4, 8].contains(size) =>
+                {
+                    return Err(Error::illegal_argument(format!(
+                        "Invalid integer size {size} in schema, must be 1, 2, 4 or 8"
+                    )));
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Encode the flat `input` following the schema
+    pub fn encode(&self, input: &[u8]) -> Vec<u8> {
+        let mut reader = Reader { input, pos: 0 };
+        let mut out = Vec::with_capacity(input.len());
+
+        for field in &self.fields {
+            match field {
+                Field::Const { value } => out.extend(decode_hex(value).unwrap_or_default()),
+                Field::Uint { size, endian } => {
+                    let value = reader.uint(*size, Endian::Little);
+                    push_uint(&mut out, value, *size, *endian);
+                }
+                Field::Bytes { size } => out.extend(reader.take(*size)),
+                Field::Tlv {
+                    tag,
+                    len_size,
+                    max_len,
+                    endian,
+                } => {
+                    let len = reader.uint(1, Endian::Little) as usize % (max_len + 1);
+                    out.push(*tag);
+                    push_uint(&mut out, len as u64, *len_size, *endian);
+                    out.extend(reader.take(len));
+                }
+                Field::Rest => out.extend_from_slice(reader.rest()),
+            }
+        }
+
+        out
+    }
+}
+
+struct Reader<'a> {
+    input: &'a [u8],
+    pos: usize,
+}
+
+impl Reader<'_> {
+    /// `size` bytes, zero-padded past the end of the input
+    fn take(&mut self, size: usize) -> Vec<u8> {
+        let mut bytes = self.rest().iter().take(size).copied().collect::<Vec<u8>>();
+        self.pos += bytes.len();
+        bytes.resize(size, 0);
+        bytes
+    }
+
+    fn uint(&mut self, size: usize, endian: Endian) -> u64 {
+        let bytes = self.take(size);
+        let fold = |acc: u64, b: &u8| (acc << 8) | u64::from(*b);
+        match endian {
+            Endian::Big => bytes.iter().fold(0, fold),
+            Endian::Little => bytes.iter().rev().fold(0, fold),
+        }
+    }
+
+    fn rest(&self) -> &[u8] {
+        &self.input[self.pos.min(self.input.len())..]
+    }
+}
+
+fn push_uint(out: &mut Vec<u8>, value: u64, size: usize, endian: Endian) {
+    match endian {
+        Endian::Little => out.extend_from_slice(&value.to_le_bytes()[..size]),
+        Endian::Big => out.extend_from_slice(&value.to_be_bytes()[8 - size..]),
+    }
+}
+
+fn decode_hex(value: &str) -> Result<Vec<u8>, Error> {
+    let value = value.trim_start_matches("0x");
+    if value.len() % 2 != 0 {
+        return Err(Error::illegal_argument(format!(
+            "Invalid hex value in schema: {value}"
+        )));
+    }
+    (0..value.len())
+        .step_by(2)
+        .map(|i| {
+            u8::from_str_radix(&value[i..i + 2], 16).map_err(|e| {
+                Error::illegal_argument(format!("Invalid hex value in schema: {value} ({e:})"))
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,56 @@
+use std::{fs, path::Path};
+
+use libafl::Error;
+use libafl_qemu::GuestAddr;
+
+/// A step of an interaction [`Script`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScriptStep {
+    /// The target reads the next segment of the input
+    Read,
+    /// The target writes once, to the given fd or to any fd but stderr
+    Write(Option<GuestAddr>),
+}
+
+/// The order of the reads and writes of a request/response exchange with the target, see `--script`.
+/// Loaded from a file with a step per line, `read` or `write [fd]`, `#` starting a comment:
+///
+/// ```text
+/// read     # the request
+/// write    # wait for the response
+/// read     # the next request, only served once the response was written
+/// ```
+#[derive(Debug, Clone)]
+pub struct Script {
+    pub steps: Vec<ScriptStep>,
+}
+
+impl Script {
+    pub fn from_file(path: &Path) -> Result<Self, Error> {
+        let content = fs::read_to_string(path)?;
+        let mut steps = Vec::new();
+        for (i, line) in content.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let invalid = |what: String| Error::illegal_argument(format!("{}:{}: {what}", path.display(), i + 1));
+
+            let mut words = line.split_whitespace();
+            let step = match (words.next(), words.next(), words.next()) {
+                (Some("read"), None, _) => ScriptStep::Read,
+                (Some("write"), None, _) => ScriptStep::Write(None),
+                (Some("write"), Some(fd), None) => ScriptStep::Write(Some(
+                    fd.parse()
+                        .map_err(|e| invalid(format!("invalid fd {fd}: {e}")))?,
+                )),
+                _ => return Err(invalid(format!("expected read or write [fd], got {line}"))),
+            };
+            steps.push(step);
+        }
+        if steps.is_empty() {
+            return Err(Error::illegal_argument(format!("The script {path:?} has no step")));
+        }
+        Ok(Self { steps })
+    }
+}
@@ -0,0 +1,185 @@
+use std::{
+    collections::{BinaryHeap, HashSet},
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc, Arc,
+    },
+    thread,
+};
+
+use libafl::Error;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// The sha256 of the seeds a client already tested, kept in a file of its output directory across runs.
+/// Resuming a campaign then skips the seeds it already went through, see `--no-dedup-seeds`.
+#[derive(Debug)]
+pub struct SeenSeeds {
+    path: PathBuf,
+    hashes: HashSet<String>,
+    // Seen for the first time in this run, not yet written to `path`
+    pending: Vec<String>,
+}
+
+impl SeenSeeds {
+    /// The hashes recorded at `path`, none if it doesn't exist yet
+    pub fn load(path: PathBuf) -> Result<Self, Error> {
+        let hashes = match fs::read_to_string(&path) {
+            Ok(content) => content.lines().map(str::to_string).collect(),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => HashSet::new(),
+            Err(e) => return Err(e.into()),
+        };
+        Ok(Self {
+            path,
+            hashes,
+            pending: Vec::new(),
+        })
+    }
+
+    /// All files under `dir`, recursively, sorted for a stable import order
+    pub fn files(dir: &Path) -> Result<Vec<PathBuf>, Error> {
+        let mut files = Vec::new();
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                files.extend(Self::files(&path)?);
+            } else if path.is_file() {
+                files.push(path);
+            }
+        }
+        files.sort();
+        Ok(files)
+    }
+
+    /// Split `inputs` into the unseen and the seen ones, marking the unseen ones as seen.
+    /// An input duplicating an earlier one of `inputs` counts as seen as well.
+    pub fn split(&mut self, inputs: &[PathBuf]) -> Result<(Vec<PathBuf>, Vec<PathBuf>), Error> {
+        let mut unseen = Vec::new();
+        let mut seen = Vec::new();
+        for input in inputs {
+            let hash = Self::hash(input)?;
+            if self.hashes.insert(hash.clone()) {
+                self.pending.push(hash);
+                unseen.push(input.clone());
+            } else {
+                seen.push(input.clone());
+            }
+        }
+        Ok((unseen, seen))
+    }
+
+    /// Mark `inputs` as seen, for a later run to skip them
+    pub fn record(&mut self, inputs: &[PathBuf]) -> Result<(), Error> {
+        for input in inputs {
+            let hash = Self::hash(input)?;
+            if self.hashes.insert(hash.clone()) {
+                self.pending.push(hash);
+            }
+        }
+        Ok(())
+    }
+
+    fn hash(input: &Path) -> Result<String, Error> {
+        Ok(format!("{:x}", Sha256::digest(fs::read(input)?)))
+    }
+
+    /// Append the newly seen hashes to the file
+    pub fn save(&mut self) -> Result<(), Error> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        for hash in self.pending.drain(..) {
+            writeln!(file, "{hash}")?;
+        }
+        Ok(())
+    }
+}
+
+/// The progress of the `--smart-import` coverage scan, kept in the state: a seed crashing the client restores
+/// the state saved by the crash handler, so the scan resumes after the seeds already run and skips the crashing one
+/// rather than running it again after every restart.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ImportScanMetadata {
+    /// The seeds being scanned, the scan starts over if the seed directory changed
+    pub seeds: Vec<PathBuf>,
+    /// The map entries covered by each seed run so far, `None` for a seed whose run never returned
+    pub coverage: Vec<Option<Vec<usize>>>,
+}
+
+libafl_bolts::impl_serdeany!(ImportScanMetadata);
+
+/// Files read ahead of their consumer, at most this many per reading thread
+const PREFETCH_DEPTH: usize = 64;
+
+/// Read the files `inputs` on `threads` threads, handing out their contents as they are read,
+/// i.e. not in the order of `inputs`. The threads stop when the returned iterator is dropped.
+pub fn prefetch(
+    inputs: &[PathBuf],
+    threads: usize,
+) -> impl Iterator<Item = (PathBuf, io::Result<Vec<u8>>)> {
+    let inputs: Arc<[PathBuf]> = inputs.into();
+    let next = Arc::new(AtomicUsize::new(0));
+    let (sender, receiver) = mpsc::sync_channel(threads * PREFETCH_DEPTH);
+
+    for _ in 0..threads {
+        let inputs = inputs.clone();
+        let next = next.clone();
+        let sender = sender.clone();
+        thread::spawn(move || loop {
+            let Some(path) = inputs.get(next.fetch_add(1, Ordering::Relaxed)) else {
+                break;
+            };
+            if sender.send((path.clone(), fs::read(path))).is_err() {
+                break;
+            }
+        });
+    }
+
+    receiver.into_iter()
+}
+
+/// Greedy order of the seeds covering the map entries `coverage[i]` (each one sorted and deduplicated),
+/// the seed adding the most new entries to the ones before it first, see `--smart-import`.
+/// Returns the seed indices with the entries each one adds, the seeds adding none last in their original order.
+pub fn greedy_order(coverage: &[Vec<usize>]) -> Vec<(usize, usize)> {
+    let mut covered = HashSet::new();
+    let mut order = Vec::with_capacity(coverage.len());
+    // Max-heap of (gain upper bound, reversed index): a gain only shrinks as the coverage grows,
+    // so a seed whose recomputed gain is still the best can be taken without recomputing the others
+    let mut heap = coverage
+        .iter()
+        .enumerate()
+        .map(|(i, entries)| (entries.len(), usize::MAX - i))
+        .collect::<BinaryHeap<(usize, usize)>>();
+    while let Some((bound, rev)) = heap.pop() {
+        let i = usize::MAX - rev;
+        let gain = coverage[i].iter().filter(|entry| !covered.contains(*entry)).count();
+        if gain == 0 {
+            // Appended below
+            continue;
+        }
+        if gain < bound && heap.peek().is_some_and(|(next, _)| *next > gain) {
+            heap.push((gain, rev));
+            continue;
+        }
+        covered.extend(coverage[i].iter().copied());
+        order.push((i, gain));
+    }
+
+    let mut taken = vec![false; coverage.len()];
+    for (i, _) in &order {
+        taken[*i] = true;
+    }
+    order.extend((0..coverage.len()).filter(|i| !taken[*i]).map(|i| (i, 0)));
+    order
+}
@@ -0,0 +1,133 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::{self, Command, Stdio},
+    thread,
+    time::Duration,
+};
+
+use libafl::Error;
+
+use crate::{options::FuzzerOptions, triage::Triage};
+
+/// The seed of the self-test, as far from the bug as a 4 byte input gets
+const SEED: &[u8] = b"AAAA";
+
+/// Fuzzes the bundled self-test target (`selftest/selftest.c`), which crashes on inputs starting with "FUZZ",
+/// and checks that the crash is found, to tell a broken setup from a target that's hard to fuzz.
+///
+/// The campaign is a child instance of this fuzzer running with `--profile`, in `<output>/self_test`.
+/// The bug can only be found through new coverage, so it exercises the injection, the coverage,
+/// the corpus and the objective. Every run ends at `selftest_end`, so the target never exits.
+/// The child keeps fuzzing after a crash, it's stopped once the first one is saved.
+pub struct SelfTest<'a> {
+    options: &'a FuzzerOptions,
+}
+
+impl<'a> SelfTest<'a> {
+    pub fn new(options: &'a FuzzerOptions) -> Self {
+        Self { options }
+    }
+
+    pub fn run(&self) -> Result<(), Error> {
+        let target = self
+            .options
+            .self_test
+            .as_ref()
+            .ok_or_else(|| Error::empty_optional("No self-test target provided"))?;
+        if !target.is_file() {
+            return Err(Error::illegal_argument(format!(
+                "Self-test target {target:?} not found, it is built to build/selftest by `cargo make`"
+            )));
+        }
+
+        let dir = PathBuf::from(&self.options.output).join("self_test");
+        // A leftover crash of an earlier self-test must not make this one pass
+        if dir.exists() {
+            fs::remove_dir_all(&dir)?;
+        }
+        let input = dir.join("input");
+        let output = dir.join("output");
+        fs::create_dir_all(&input)?;
+        fs::write(input.join("seed"), SEED)?;
+
+        println!(
+            "Self-test: fuzzing {target:?} for up to {} iterations",
+            self.options.self_test_iterations
+        );
+        let mut child = Command::new(env::current_exe()?)
+            .args(self.child_args(target, &input, &output))
+            .stdin(Stdio::null())
+            .stdout(Self::child_output(self.options.verbose))
+            .stderr(Self::child_output(self.options.verbose))
+            .spawn()?;
+        let client = output.join("client_000");
+        let status = loop {
+            if let Some(status) = child.try_wait()? {
+                break status;
+            }
+            if Self::entries(&client.join("crashes"))? > 0 {
+                child.kill()?;
+                break child.wait()?;
+            }
+            thread::sleep(Duration::from_millis(100));
+        };
+        log::info!("Self-test campaign exited with {status}");
+
+        let crashes = Self::entries(&client.join("crashes"))?;
+        let queue = Self::entries(&client.join("queue"))?;
+        println!("Self-test: {queue} corpus entries, {crashes} crashes");
+        if crashes > 0 {
+            println!("PASS");
+            return Ok(());
+        }
+
+        println!("FAIL: the bug of the self-test target wasn't found, the setup is broken");
+        if queue <= 1 {
+            println!("  No new corpus entry either: check the coverage (qemu build, edge module) and the input injection");
+        } else {
+            println!("  Coverage grows but no crash was saved: check the objective and the crash handling");
+        }
+        println!("  Rerun with --verbose and RUST_LOG=debug to see the campaign's output, its files are in {dir:?}");
+        process::exit(1);
+    }
+
+    /// A campaign on a single core, running `main` of the static target with the input on stdin up to `selftest_end`
+    fn child_args(&self, target: &Path, input: &Path, output: &Path) -> Vec<String> {
+        let mut args = vec![
+            "--input".to_string(),
+            input.to_string_lossy().into_owned(),
+            "--output".to_string(),
+            output.to_string_lossy().into_owned(),
+            "--cores".to_string(),
+            "0".to_string(),
+            "--profile".to_string(),
+            "--harness-entry".to_string(),
+            "main".to_string(),
+            "--harness-exit".to_string(),
+            "selftest_end".to_string(),
+            "--iterations".to_string(),
+            self.options.self_test_iterations.to_string(),
+        ];
+        args.push("--".to_string());
+        args.push(target.to_string_lossy().into_owned());
+        args
+    }
+
+    /// The campaign's output is only shown with `--verbose`
+    fn child_output(verbose: bool) -> Stdio {
+        if verbose {
+            Stdio::inherit()
+        } else {
+            Stdio::null()
+        }
+    }
+
+    /// The number of inputs in `dir`, none if it doesn't exist
+    fn entries(dir: &Path) -> Result<usize, Error> {
+        if !dir.is_dir() {
+            return Ok(0);
+        }
+        Ok(Triage::inputs(dir)?.len())
+    }
+}
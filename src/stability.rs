@@ -0,0 +1,67 @@
+/// Coverage stability of one input over repeated executions
+#[derive(Debug, Default)]
+pub struct StabilityReport {
+    pub runs: usize,
+    /// Number of map entries hit in at least one run
+    pub covered: usize,
+    /// Indices of the entries whose value differed between runs
+    pub flaky: Vec<usize>,
+}
+
+/// Below this stability the coverage filter likely includes nondeterministic code
+pub const STABILITY_WARN_THRESHOLD: f64 = 90.0;
+
+impl StabilityReport {
+    pub fn from_maps(maps: &[Vec<u8>]) -> Self {
+        let len = maps.iter().map(Vec::len).max().unwrap_or(0);
+        let mut report = StabilityReport {
+            runs: maps.len(),
+            ..Default::default()
+        };
+
+        for idx in 0..len {
+            let mut values = maps.iter().map(|map| map.get(idx).copied().unwrap_or(0));
+            let first = values.next().unwrap_or(0);
+            let mut hit = first != 0;
+            let mut stable = true;
+            for value in values {
+                hit |= value != 0;
+                stable &= value == first;
+            }
+            if hit {
+                report.covered += 1;
+                if !stable {
+                    report.flaky.push(idx);
+                }
+            }
+        }
+
+        report
+    }
+
+    /// Percentage of covered entries that were identical in every run
+    pub fn stability(&self) -> f64 {
+        if self.covered == 0 {
+            return 100.0;
+        }
+        (self.covered - self.flaky.len()) as f64 * 100.0 / self.covered as f64
+    }
+
+    pub fn print(&self) {
+        println!(
+            "Stability: {:.2}% ({} of {} covered edges stable over {} runs)",
+            self.stability(),
+            self.covered - self.flaky.len(),
+            self.covered,
+            self.runs
+        );
+        if !self.flaky.is_empty() {
+            println!("Flaky edge indices: {:?}", self.flaky);
+        }
+        if self.stability() < STABILITY_WARN_THRESHOLD {
+            log::warn!(
+                "Low coverage stability, consider tightening the coverage filter with --include/--exclude"
+            );
+        }
+    }
+}
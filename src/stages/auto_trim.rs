@@ -0,0 +1,169 @@
+use std::{borrow::Cow, marker::PhantomData};
+
+use libafl::{
+    corpus::{Corpus, HasCurrentCorpusId},
+    executors::{Executor, HasObservers},
+    fuzzer::ExecutesInput,
+    inputs::{BytesInput, HasMutatorBytes},
+    observers::{MapObserver, ObserversTuple},
+    schedulers::RemovableScheduler,
+    stages::Stage,
+    state::HasCorpus,
+    Error, HasMetadata, HasScheduler,
+};
+use libafl_bolts::{
+    tuples::{Handle, Handled, MatchNameRef},
+    Named,
+};
+use serde::{Deserialize, Serialize};
+
+/// Upper bound of executions spent trimming a single corpus entry
+const MAX_TRIM_EXECS: usize = 256;
+
+/// Marks a corpus entry as already trimmed
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct AutoTrimMetadata {
+    pub original_len: usize,
+}
+
+libafl_bolts::impl_serdeany!(AutoTrimMetadata);
+
+/// Shortens the current corpus entry as long as it keeps covering the same edges, with the same hitcount buckets,
+/// so that trimming never loses coverage in either `--coverage-mode`. The trimmed entry is run once more and
+/// only replaces the original if its coverage is still the same, a flaky edge could have let a candidate through.
+/// Every entry is trimmed at most once, within [`MAX_TRIM_EXECS`] executions, to bound the throughput loss.
+#[derive(Debug)]
+pub struct AutoTrimStage<C, O> {
+    map_handle: Handle<C>,
+    phantom: PhantomData<O>,
+}
+
+impl<C, O> AutoTrimStage<C, O>
+where
+    C: AsRef<O> + Named,
+{
+    pub fn new(map_observer: &C) -> Self {
+        Self {
+            map_handle: map_observer.handle(),
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<C, O> Named for AutoTrimStage<C, O> {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("AutoTrimStage");
+        &NAME
+    }
+}
+
+impl<C, O> AutoTrimStage<C, O>
+where
+    C: AsRef<O>,
+    O: MapObserver<Entry = u8>,
+{
+    /// The covered map entries after running `input`, with their bucketed hitcounts
+    fn coverage<E, EM, S, Z>(
+        &self,
+        fuzzer: &mut Z,
+        executor: &mut E,
+        state: &mut S,
+        manager: &mut EM,
+        input: &BytesInput,
+    ) -> Result<Vec<(usize, u8)>, Error>
+    where
+        E: HasObservers,
+        E::Observers: MatchNameRef,
+        Z: ExecutesInput<E, EM, BytesInput, S>,
+    {
+        fuzzer.execute_input(state, executor, manager, input)?;
+        let map = executor.observers()[&self.map_handle].as_ref().to_vec();
+        Ok(map
+            .iter()
+            .enumerate()
+            .filter(|(_, v)| **v != 0)
+            .map(|(idx, v)| (idx, *v))
+            .collect())
+    }
+}
+
+impl<C, E, EM, O, S, Z> Stage<E, EM, S, Z> for AutoTrimStage<C, O>
+where
+    C: AsRef<O>,
+    O: MapObserver<Entry = u8>,
+    E: Executor<EM, BytesInput, S, Z> + HasObservers,
+    E::Observers: ObserversTuple<BytesInput, S> + MatchNameRef,
+    S: HasCorpus<BytesInput> + HasCurrentCorpusId,
+    Z: ExecutesInput<E, EM, BytesInput, S> + HasScheduler<BytesInput, S>,
+    Z::Scheduler: RemovableScheduler<BytesInput, S>,
+{
+    fn should_restart(&mut self, _state: &mut S) -> Result<bool, Error> {
+        // Trimming is idempotent, it can always be restarted
+        Ok(true)
+    }
+
+    fn clear_progress(&mut self, _state: &mut S) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn perform(
+        &mut self,
+        fuzzer: &mut Z,
+        executor: &mut E,
+        state: &mut S,
+        manager: &mut EM,
+    ) -> Result<(), Error> {
+        let Some(id) = state.current_corpus_id()? else {
+            return Ok(());
+        };
+
+        let mut input = {
+            let mut testcase = state.corpus().get(id)?.borrow_mut();
+            if testcase.has_metadata::<AutoTrimMetadata>() {
+                return Ok(());
+            }
+            state.corpus().load_input_into(&mut testcase)?;
+            testcase.input().clone().unwrap()
+        };
+        let original_len = input.bytes().len();
+
+        let baseline = self.coverage(fuzzer, executor, state, manager, &input)?;
+        let mut execs = 1;
+
+        // Remove chunks from big to small, keeping every removal that preserves the coverage
+        let mut chunk = (original_len / 2).max(1);
+        while chunk > 0 && execs < MAX_TRIM_EXECS {
+            let mut pos = 0;
+            while pos + chunk <= input.bytes().len() && execs < MAX_TRIM_EXECS {
+                let mut candidate = input.bytes().to_vec();
+                candidate.drain(pos..pos + chunk);
+                let candidate = BytesInput::new(candidate);
+
+                execs += 1;
+                if self.coverage(fuzzer, executor, state, manager, &candidate)? == baseline {
+                    input = candidate;
+                } else {
+                    pos += chunk;
+                }
+            }
+            chunk /= 2;
+        }
+
+        let mut testcase = state.corpus().get(id)?.borrow().clone();
+        let trimmed_len = input.bytes().len();
+        testcase.add_metadata(AutoTrimMetadata { original_len });
+        if trimmed_len < original_len {
+            if self.coverage(fuzzer, executor, state, manager, &input)? == baseline {
+                log::info!("AutoTrimStage: trimmed {id} from {original_len} to {trimmed_len} bytes in {execs} execs");
+                testcase.set_input(input);
+            } else {
+                log::info!("AutoTrimStage: the coverage of {id} trimmed to {trimmed_len} bytes is unstable, keeping it whole");
+            }
+        }
+        let prev = state.corpus_mut().replace(id, testcase)?;
+        // The minimizer rates the entries by their length, it must see the new one
+        fuzzer.scheduler_mut().on_replace(state, id, &prev)?;
+
+        Ok(())
+    }
+}
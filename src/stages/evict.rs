@@ -0,0 +1,100 @@
+use std::{borrow::Cow, collections::HashSet};
+
+use libafl::{
+    corpus::{Corpus, CorpusId, HasCurrentCorpusId},
+    inputs::BytesInput,
+    schedulers::{minimizer::TopRatedsMetadata, RemovableScheduler},
+    stages::Stage,
+    state::HasCorpus,
+    Error, HasMetadata, HasScheduler,
+};
+use libafl_bolts::Named;
+
+/// Keeps the corpus within `max` entries, see `--max-corpus`.
+///
+/// The entries to evict are those whose coverage is subsumed by the others, i.e. which are the top rated
+/// (see [`TopRatedsMetadata`]) for no map entry, oldest first as they had the most fuzzing time.
+/// If every entry is top rated for some edge, the corpus is left to grow rather than lose coverage.
+/// Only the corpus is touched, the crash solutions are never evicted.
+#[derive(Debug)]
+pub struct CorpusEvictionStage {
+    max: Option<usize>,
+    // Only warn once when nothing can be evicted
+    warned: bool,
+}
+
+impl CorpusEvictionStage {
+    pub fn new(max: Option<usize>) -> Self {
+        Self { max, warned: false }
+    }
+}
+
+impl Named for CorpusEvictionStage {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("CorpusEvictionStage");
+        &NAME
+    }
+}
+
+impl<E, EM, S, Z> Stage<E, EM, S, Z> for CorpusEvictionStage
+where
+    S: HasCorpus<BytesInput> + HasCurrentCorpusId + HasMetadata,
+    Z: HasScheduler<BytesInput, S>,
+    Z::Scheduler: RemovableScheduler<BytesInput, S>,
+{
+    fn should_restart(&mut self, _state: &mut S) -> Result<bool, Error> {
+        // Evicting is idempotent, it can always be restarted
+        Ok(true)
+    }
+
+    fn clear_progress(&mut self, _state: &mut S) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn perform(
+        &mut self,
+        fuzzer: &mut Z,
+        _executor: &mut E,
+        state: &mut S,
+        _manager: &mut EM,
+    ) -> Result<(), Error> {
+        let Some(max) = self.max else {
+            return Ok(());
+        };
+        let count = state.corpus().count();
+        if count <= max {
+            return Ok(());
+        }
+
+        let top_rated = state
+            .metadata_map()
+            .get::<TopRatedsMetadata>()
+            .map(|top_rated| top_rated.map.values().copied().collect::<HashSet<CorpusId>>())
+            .unwrap_or_default();
+        // The entry being fuzzed must stay, the following stages use it
+        let current = state.current_corpus_id()?;
+        let victims = state
+            .corpus()
+            .ids()
+            .filter(|id| !top_rated.contains(id) && Some(*id) != current)
+            .take(count - max)
+            .collect::<Vec<_>>();
+
+        if victims.len() < count - max && !self.warned {
+            log::warn!(
+                "CorpusEvictionStage: every corpus entry left is the best for some edge, the corpus grows past {max}"
+            );
+            self.warned = true;
+        }
+
+        for id in victims {
+            let testcase = state.corpus_mut().remove(id)?;
+            fuzzer
+                .scheduler_mut()
+                .on_remove(state, id, &Some(testcase))?;
+            log::info!("CorpusEvictionStage: evicted {id}, its coverage is subsumed by other entries");
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,9 @@
+pub mod auto_trim;
+pub mod evict;
+pub mod recalibrate;
+pub mod single_calibrate;
+
+pub use auto_trim::AutoTrimStage;
+pub use evict::CorpusEvictionStage;
+pub use recalibrate::RecalibrationStage;
+pub use single_calibrate::SingleCalibrationStage;
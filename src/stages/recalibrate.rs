@@ -0,0 +1,174 @@
+use std::{borrow::Cow, marker::PhantomData};
+
+use libafl::{
+    corpus::{Corpus, CorpusId, HasCurrentCorpusId},
+    executors::{Executor, HasObservers},
+    fuzzer::ExecutesInput,
+    inputs::BytesInput,
+    observers::{MapObserver, ObserversTuple},
+    schedulers::RemovableScheduler,
+    stages::Stage,
+    state::{HasCorpus, HasExecutions},
+    Error, HasMetadata, HasScheduler,
+};
+use libafl_bolts::{
+    current_time,
+    tuples::{Handle, Handled, MatchNameRef},
+    Named,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::stability::StabilityReport;
+
+/// Runs of every recalibrated entry
+const RECALIBRATION_RUNS: usize = 4;
+
+/// Entries whose coverage is stable below this percentage are dropped from the corpus
+const DROP_STABILITY: f64 = 50.0;
+
+/// The result of the last recalibration of a corpus entry
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct RecalibrationMetadata {
+    /// Percentage of the covered map entries identical in every run
+    pub stability: f64,
+    /// Executions of the fuzzer when it was recalibrated
+    pub executions: u64,
+}
+
+libafl_bolts::impl_serdeany!(RecalibrationMetadata);
+
+/// Every `interval` fuzzing iterations, re-runs `sample` corpus entries [`RECALIBRATION_RUNS`] times, see
+/// `--recalibrate-interval`. The calibration only measures an entry when it's added, while the target's state
+/// (caches, files, time) can drift over a long campaign: the entries get their execution time refreshed with
+/// the average of the runs and their stability recorded in a [`RecalibrationMetadata`], and the entries whose
+/// coverage is stable below [`DROP_STABILITY`] percent are removed from the corpus.
+/// The entries are visited in turn, so the whole corpus is recalibrated over time.
+#[derive(Debug)]
+pub struct RecalibrationStage<C, O> {
+    map_handle: Handle<C>,
+    interval: Option<u64>,
+    sample: usize,
+    iterations: u64,
+    // The last entry recalibrated
+    cursor: Option<CorpusId>,
+    phantom: PhantomData<O>,
+}
+
+impl<C, O> RecalibrationStage<C, O>
+where
+    C: AsRef<O> + Named,
+{
+    pub fn new(map_observer: &C, interval: Option<u64>, sample: usize) -> Self {
+        Self {
+            map_handle: map_observer.handle(),
+            interval,
+            sample,
+            iterations: 0,
+            cursor: None,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<C, O> Named for RecalibrationStage<C, O> {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("RecalibrationStage");
+        &NAME
+    }
+}
+
+impl<C, O> RecalibrationStage<C, O> {
+    /// The next `sample` entries after the cursor, wrapping around, without `current`
+    fn next_entries<S>(&self, state: &S, current: Option<CorpusId>) -> Vec<CorpusId>
+    where
+        S: HasCorpus<BytesInput>,
+    {
+        let ids = state.corpus().ids().filter(|id| Some(*id) != current).collect::<Vec<_>>();
+        let start = self
+            .cursor
+            .map_or(0, |cursor| ids.iter().position(|id| *id > cursor).unwrap_or(0));
+        ids.iter().cycle().skip(start).take(self.sample.min(ids.len())).copied().collect()
+    }
+}
+
+impl<C, E, EM, O, S, Z> Stage<E, EM, S, Z> for RecalibrationStage<C, O>
+where
+    C: AsRef<O>,
+    O: MapObserver<Entry = u8>,
+    E: Executor<EM, BytesInput, S, Z> + HasObservers,
+    E::Observers: ObserversTuple<BytesInput, S> + MatchNameRef,
+    S: HasCorpus<BytesInput> + HasCurrentCorpusId + HasExecutions,
+    Z: ExecutesInput<E, EM, BytesInput, S> + HasScheduler<BytesInput, S>,
+    Z::Scheduler: RemovableScheduler<BytesInput, S>,
+{
+    fn should_restart(&mut self, _state: &mut S) -> Result<bool, Error> {
+        // Recalibrating is idempotent, it can always be restarted
+        Ok(true)
+    }
+
+    fn clear_progress(&mut self, _state: &mut S) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn perform(
+        &mut self,
+        fuzzer: &mut Z,
+        executor: &mut E,
+        state: &mut S,
+        manager: &mut EM,
+    ) -> Result<(), Error> {
+        let Some(interval) = self.interval else {
+            return Ok(());
+        };
+        self.iterations += 1;
+        if self.iterations % interval != 0 {
+            return Ok(());
+        }
+
+        // The entry being fuzzed must stay, the following stages use it
+        let current = state.current_corpus_id()?;
+        let mut dropped = 0;
+        for id in self.next_entries(state, current) {
+            self.cursor = Some(id);
+            let input = {
+                let mut testcase = state.corpus().get(id)?.borrow_mut();
+                state.corpus().load_input_into(&mut testcase)?;
+                testcase.input().clone().unwrap()
+            };
+
+            let mut maps = Vec::with_capacity(RECALIBRATION_RUNS);
+            let start = current_time();
+            for _ in 0..RECALIBRATION_RUNS {
+                fuzzer.execute_input(state, executor, manager, &input)?;
+                maps.push(executor.observers()[&self.map_handle].as_ref().to_vec());
+            }
+            let exec_time = current_time().saturating_sub(start) / RECALIBRATION_RUNS as u32;
+            let report = StabilityReport::from_maps(&maps);
+
+            // Keep at least one entry to fuzz
+            if report.stability() < DROP_STABILITY && state.corpus().count() > 1 {
+                let testcase = state.corpus_mut().remove(id)?;
+                fuzzer.scheduler_mut().on_remove(state, id, &Some(testcase))?;
+                log::info!(
+                    "RecalibrationStage: dropped {id}, only {:.2}% of its coverage is stable",
+                    report.stability()
+                );
+                dropped += 1;
+                continue;
+            }
+
+            let executions = *state.executions();
+            let mut testcase = state.corpus().get(id)?.borrow_mut();
+            testcase.set_exec_time(exec_time);
+            testcase.add_metadata(RecalibrationMetadata {
+                stability: report.stability(),
+                executions,
+            });
+        }
+        if dropped != 0 {
+            log::info!("RecalibrationStage: dropped {dropped} unstable entries, {} left", state.corpus().count());
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,126 @@
+use std::{borrow::Cow, marker::PhantomData};
+
+use libafl::{
+    corpus::{Corpus, HasCurrentCorpusId, SchedulerTestcaseMetadata},
+    executors::{Executor, HasObservers},
+    fuzzer::ExecutesInput,
+    inputs::BytesInput,
+    observers::{MapObserver, ObserversTuple},
+    schedulers::powersched::SchedulerMetadata,
+    stages::Stage,
+    state::HasCorpus,
+    Error, HasMetadata,
+};
+use libafl_bolts::{
+    current_time,
+    tuples::{Handle, Handled, MatchNameRef},
+    Named,
+};
+
+/// Calibrates every new corpus entry with a single run, see `--skip-calibration`. It records what the power
+/// schedule needs, the entry's exec time and map size, as the `CalibrationStage` does, but doesn't run the entry
+/// again to find its unstable map entries: flaky edges stay in the coverage and keep adding entries to the corpus.
+#[derive(Debug)]
+pub struct SingleCalibrationStage<C, O> {
+    map_handle: Handle<C>,
+    phantom: PhantomData<O>,
+}
+
+impl<C, O> SingleCalibrationStage<C, O>
+where
+    C: AsRef<O> + Named,
+{
+    pub fn new(map_observer: &C) -> Self {
+        Self {
+            map_handle: map_observer.handle(),
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<C, O> Named for SingleCalibrationStage<C, O> {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("SingleCalibrationStage");
+        &NAME
+    }
+}
+
+impl<C, E, EM, O, S, Z> Stage<E, EM, S, Z> for SingleCalibrationStage<C, O>
+where
+    C: AsRef<O>,
+    O: MapObserver<Entry = u8>,
+    E: Executor<EM, BytesInput, S, Z> + HasObservers,
+    E::Observers: ObserversTuple<BytesInput, S> + MatchNameRef,
+    S: HasCorpus<BytesInput> + HasCurrentCorpusId + HasMetadata,
+    Z: ExecutesInput<E, EM, BytesInput, S>,
+{
+    fn should_restart(&mut self, _state: &mut S) -> Result<bool, Error> {
+        // A single run, restarting it costs nothing more than running it
+        Ok(true)
+    }
+
+    fn clear_progress(&mut self, _state: &mut S) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn perform(
+        &mut self,
+        fuzzer: &mut Z,
+        executor: &mut E,
+        state: &mut S,
+        manager: &mut EM,
+    ) -> Result<(), Error> {
+        let Some(id) = state.current_corpus_id()? else {
+            return Ok(());
+        };
+
+        let input = {
+            let mut testcase = state.corpus().get(id)?.borrow_mut();
+            // Only the entries never fuzzed yet are calibrated
+            if testcase.scheduled_count() > 0 {
+                return Ok(());
+            }
+            state.corpus().load_input_into(&mut testcase)?;
+            testcase.input().clone().unwrap()
+        };
+
+        let start = current_time();
+        fuzzer.execute_input(state, executor, manager, &input)?;
+        let exec_time = current_time().saturating_sub(start);
+        let bitmap_size = executor.observers()[&self.map_handle].as_ref().count_bytes();
+
+        let Ok(psmeta) = state.metadata_mut::<SchedulerMetadata>() else {
+            return Ok(());
+        };
+        let handicap = psmeta.queue_cycles();
+        psmeta.set_exec_time(psmeta.exec_time() + exec_time);
+        psmeta.set_cycles(psmeta.cycles() + 1);
+        psmeta.set_bitmap_size(psmeta.bitmap_size() + bitmap_size);
+        psmeta.set_bitmap_size_log(psmeta.bitmap_size_log() + (bitmap_size.max(1) as f64).log2());
+        psmeta.set_bitmap_entries(psmeta.bitmap_entries() + 1);
+
+        let depth = {
+            let testcase = state.corpus().get(id)?.borrow();
+            match testcase.parent_id() {
+                Some(parent) => state
+                    .corpus()
+                    .get(parent)?
+                    .borrow()
+                    .metadata::<SchedulerTestcaseMetadata>()
+                    .map_or(0, |meta| meta.depth() + 1),
+                None => 0,
+            }
+        };
+        let mut testcase = state.corpus().get(id)?.borrow_mut();
+        testcase.set_exec_time(exec_time);
+        if !testcase.has_metadata::<SchedulerTestcaseMetadata>() {
+            testcase.add_metadata(SchedulerTestcaseMetadata::new(depth));
+        }
+        let meta = testcase.metadata_mut::<SchedulerTestcaseMetadata>()?;
+        meta.set_cycle_and_time((exec_time, 1));
+        meta.set_bitmap_size(bitmap_size);
+        meta.set_handicap(handicap);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,217 @@
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    path::{Path, PathBuf},
+    process::{self, Command, Stdio},
+};
+
+use libafl::{executors::ExitKind, Error};
+use libafl_qemu::GuestAddr;
+use serde::Serialize;
+
+use crate::{modules::CrashInfo, options::FuzzerOptions};
+
+/// Options that only make sense for the drivers replaying inputs through [`Triage::replay`]
+/// (triage, regression, debug config) and must not be forwarded to the replays
+const TRIAGE_ONLY_ARGS: [(&str, bool); 5] = [
+    ("--triage-dir", true),
+    ("--triage-json", true),
+    ("--regress", true),
+    ("--emit-debug-config", true),
+    ("--tui", false),
+];
+
+/// One group of inputs that ended the same way at the same place
+#[derive(Debug, Serialize)]
+pub struct TriageGroup {
+    pub exit_kind: String,
+    pub signal: Option<i32>,
+    pub pc: Option<GuestAddr>,
+    pub count: usize,
+    pub inputs: Vec<PathBuf>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct TriageReport {
+    pub total: usize,
+    pub unique_pcs: usize,
+    pub groups: Vec<TriageGroup>,
+}
+
+/// Replays every input of a directory and groups them by exit kind / signal / faulting PC.
+///
+/// A crash tears down the in-process executor, so each input is replayed by a child
+/// instance of this fuzzer running with `-r`, which reports its [`CrashInfo`] back through a file.
+pub struct Triage<'a> {
+    options: &'a FuzzerOptions,
+}
+
+impl<'a> Triage<'a> {
+    pub fn new(options: &'a FuzzerOptions) -> Self {
+        Self { options }
+    }
+
+    pub fn run(&self) -> Result<(), Error> {
+        let dir = self
+            .options
+            .triage_dir
+            .as_ref()
+            .ok_or_else(|| Error::empty_optional("No triage directory provided"))?;
+
+        let report = self.triage(&Self::inputs(dir)?)?;
+        Self::print(&report);
+
+        if let Some(json) = &self.options.triage_json {
+            let content = serde_json::to_string_pretty(&report)
+                .map_err(|e| Error::serialize(format!("Failed to serialize triage report: {e}")))?;
+            fs::write(json, content)?;
+            println!("Triage report written to {json:?}");
+        }
+
+        Ok(())
+    }
+
+    /// All regular, non-hidden files of `dir`, sorted for a stable output
+    pub fn inputs(dir: &Path) -> Result<Vec<PathBuf>, Error> {
+        let mut inputs = fs::read_dir(dir)?
+            .filter_map(Result::ok)
+            .map(|entry| entry.path())
+            .filter(|path| path.is_file())
+            .filter(|path| {
+                path.file_name()
+                    .and_then(|name| name.to_str())
+                    .is_some_and(|name| !name.starts_with('.'))
+            })
+            .collect::<Vec<PathBuf>>();
+        inputs.sort();
+        Ok(inputs)
+    }
+
+    pub fn triage(&self, inputs: &[PathBuf]) -> Result<TriageReport, Error> {
+        let mut groups = BTreeMap::<(String, Option<i32>, Option<GuestAddr>), Vec<PathBuf>>::new();
+
+        for input in inputs {
+            let info = self.replay(input)?;
+            log::info!("{input:?}: {info:?}");
+            groups
+                .entry((format!("{:?}", info.exit_kind), info.signal, info.pc))
+                .or_default()
+                .push(input.clone());
+        }
+
+        let mut groups = groups
+            .into_iter()
+            .map(|((exit_kind, signal, pc), inputs)| TriageGroup {
+                exit_kind,
+                signal,
+                pc,
+                count: inputs.len(),
+                inputs,
+            })
+            .collect::<Vec<TriageGroup>>();
+        groups.sort_by(|a, b| b.count.cmp(&a.count));
+
+        let mut pcs = groups.iter().filter_map(|g| g.pc).collect::<Vec<GuestAddr>>();
+        pcs.sort_unstable();
+        pcs.dedup();
+
+        Ok(TriageReport {
+            total: inputs.len(),
+            unique_pcs: pcs.len(),
+            groups,
+        })
+    }
+
+    /// Replay a single input in a child fuzzer process and collect its classification
+    pub fn replay(&self, input: &Path) -> Result<CrashInfo, Error> {
+        let info_file = env::temp_dir().join(format!("h1k0_crash_info_{}.json", process::id()));
+        let _ = fs::remove_file(&info_file);
+
+        let status = Command::new(env::current_exe()?)
+            .args(Self::replay_args(input, &info_file))
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()?;
+
+        let info = match fs::read_to_string(&info_file) {
+            Ok(content) => serde_json::from_str::<CrashInfo>(&content)
+                .map_err(|e| Error::serialize(format!("Invalid crash info for {input:?}: {e}")))?,
+            Err(_) => {
+                // The child died before the objective could classify the run
+                log::warn!("No crash info for {input:?} (child exited with {status})");
+                CrashInfo {
+                    exit_kind: ExitKind::Crash,
+                    signal: None,
+                    pc: None,
+                    trace: Vec::new(),
+                }
+            }
+        };
+        let _ = fs::remove_file(&info_file);
+
+        Ok(info)
+    }
+
+    /// Our own command line, minus the triage options, plus `-r <input>` before the target arguments
+    fn replay_args(input: &Path, info_file: &Path) -> Vec<String> {
+        child_args(
+            &TRIAGE_ONLY_ARGS,
+            [
+                "-r".to_string(),
+                input.to_string_lossy().into_owned(),
+                "--crash-info".to_string(),
+                info_file.to_string_lossy().into_owned(),
+            ],
+        )
+    }
+
+    fn print(report: &TriageReport) {
+        println!(
+            "Triaged {} inputs: {} groups, {} unique faulting PCs",
+            report.total,
+            report.groups.len(),
+            report.unique_pcs
+        );
+        for group in &report.groups {
+            let signal = group
+                .signal
+                .map_or_else(|| "-".to_string(), |s| s.to_string());
+            let pc = group
+                .pc
+                .map_or_else(|| "-".to_string(), |pc| format!("{pc:#x}"));
+            println!(
+                "{:>6}  {:<10} signal {:<4} pc {:<18} e.g. {:?}",
+                group.count, group.exit_kind, signal, pc, group.inputs[0]
+            );
+        }
+    }
+}
+
+/// Our own command line for a child replaying inputs: the options of `skip` (name, whether it takes a value)
+/// are removed and `extra` is inserted before the target arguments
+pub fn child_args(skip: &[(&str, bool)], extra: impl IntoIterator<Item = String>) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut iter = env::args().skip(1);
+    while let Some(arg) = iter.next() {
+        if arg == "--" {
+            args.push(arg);
+            args.extend(iter.by_ref());
+            break;
+        }
+        if let Some((name, takes_value)) = skip
+            .iter()
+            .find(|(name, _)| arg == *name || arg.starts_with(&format!("{name}=")))
+        {
+            if *takes_value && arg == *name {
+                iter.next();
+            }
+            continue;
+        }
+        args.push(arg);
+    }
+
+    let pos = args.iter().position(|a| a == "--").unwrap_or(args.len());
+    args.splice(pos..pos, extra);
+    args
+}